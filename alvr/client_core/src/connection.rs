@@ -6,7 +6,7 @@ use crate::{
     platform,
     sockets::AnnouncerSocket,
     statistics::StatisticsManager,
-    storage::Config,
+    storage::{self, Config},
     ClientCoreEvent, EVENT_QUEUE, LIFECYCLE_STATE, STATISTICS_MANAGER,
 };
 use alvr_audio::AudioDevice;
@@ -211,7 +211,7 @@ fn connection_pipeline(
         settings: Box::new(settings.clone()),
     };
 
-    *STATISTICS_MANAGER.lock() = Some(StatisticsManager::new(
+    let mut stats = StatisticsManager::new(
         settings.connection.statistics_history_size,
         Duration::from_secs_f32(1.0 / refresh_rate_hint),
         if let Switch::Enabled(config) = settings.headset.controllers {
@@ -219,7 +219,15 @@ fn connection_pipeline(
         } else {
             0.0
         },
-    ));
+    );
+    if settings.logging.record_client_frame_history {
+        let res = storage::frame_history_path(settings.logging.compress_frame_logs)
+            .and_then(|path| stats.enable_history_export(&path));
+        if let Err(e) = res {
+            warn!("Failed to record the frame history: {e}");
+        }
+    }
+    *STATISTICS_MANAGER.lock() = Some(stats);
 
     let (mut control_sender, mut control_receiver) = proto_control_socket
         .split(STREAMING_RECV_TIMEOUT)
@@ -269,6 +277,7 @@ fn connection_pipeline(
         settings.connection.packet_size as _,
         HANDSHAKE_ACTION_TIMEOUT,
     )?;
+    stream_socket.set_owd_filter_config(&settings.connection.client_owd_filter);
 
     info!("Connected to server");
     {
@@ -390,6 +399,7 @@ fn connection_pipeline(
                             header.timestamp,
                             data.get_frame_index(),
                             frames_dropped,
+                            data.get_reordered_shard_counter(),
                         );
                     }
                     frames_dropped = 0;
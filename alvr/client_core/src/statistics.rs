@@ -1,10 +1,15 @@
-use alvr_common::{warn, SlidingWindowAverage};
+use alvr_common::{anyhow::Result, warn, SlidingWindowAverage};
 use alvr_packets::ClientStatistics;
+use serde::Serialize;
 use std::{
     collections::VecDeque,
+    io::Write,
+    path::Path,
     time::{Duration, Instant},
 };
 
+const HISTORY_EXPORT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Clone)]
 struct HistoryFrame {
     input_acquired: Instant,
@@ -16,6 +21,37 @@ struct HistoryFrame {
     is_submitted: bool,
 }
 
+// Instant is not serializable, so all timestamps are offsets from input_acquired
+#[derive(Serialize)]
+struct HistoryFrameSnapshot<'a> {
+    video_packet_received_us: u64,
+    frame_decoded_us: u64,
+    compositor_start_us: u64,
+    frame_submitted_us: u64,
+    client_stats: &'a ClientStatistics,
+}
+
+impl<'a> HistoryFrameSnapshot<'a> {
+    fn new(frame: &'a HistoryFrame) -> Self {
+        let stats = &frame.client_stats;
+
+        let video_packet_received = frame
+            .video_packet_received
+            .saturating_duration_since(frame.input_acquired);
+        let frame_decoded = video_packet_received + stats.video_decode;
+        let compositor_start = frame_decoded + stats.video_decoder_queue;
+        let frame_submitted = compositor_start + stats.rendering;
+
+        Self {
+            video_packet_received_us: video_packet_received.as_micros() as u64,
+            frame_decoded_us: frame_decoded.as_micros() as u64,
+            compositor_start_us: compositor_start.as_micros() as u64,
+            frame_submitted_us: frame_submitted.as_micros() as u64,
+            client_stats: stats,
+        }
+    }
+}
+
 pub struct StatisticsManager {
     history_buffer: VecDeque<HistoryFrame>,
     max_history_size: usize,
@@ -24,6 +60,9 @@ pub struct StatisticsManager {
     steamvr_pipeline_latency: Duration,
 
     stats_history_buffer: VecDeque<HistoryFrame>,
+
+    history_export_writer: Option<Box<dyn Write + Send>>,
+    last_history_export_flush: Instant,
 }
 
 impl StatisticsManager {
@@ -44,6 +83,36 @@ impl StatisticsManager {
                 steamvr_pipeline_frames * nominal_server_frame_interval.as_secs_f32(),
             ),
             stats_history_buffer: VecDeque::new(),
+
+            history_export_writer: None,
+            last_history_export_flush: Instant::now(),
+        }
+    }
+
+    // Write every completed frame as a JSON line to the specified file. Opt-in, used for debugging
+    // the latency breakdown. The file is gzip compressed if the path ends with .gz
+    pub fn enable_history_export(&mut self, path: &Path) -> Result<()> {
+        self.history_export_writer = Some(alvr_common::create_log_file(path)?);
+        self.last_history_export_flush = Instant::now();
+
+        Ok(())
+    }
+
+    fn export_history_frame(&mut self, frame: &HistoryFrame) {
+        let Some(writer) = &mut self.history_export_writer else {
+            return;
+        };
+
+        let res = serde_json::to_writer(&mut *writer, &HistoryFrameSnapshot::new(frame))
+            .map_err(Into::into)
+            .and_then(|_| writer.write_all(b"\n"));
+        if let Err(e) = res {
+            warn!("Failed to export frame statistics: {e}");
+        }
+
+        if self.last_history_export_flush + HISTORY_EXPORT_FLUSH_INTERVAL < Instant::now() {
+            writer.flush().ok();
+            self.last_history_export_flush = Instant::now();
         }
     }
 
@@ -93,6 +162,7 @@ impl StatisticsManager {
         target_timestamp: Duration,
         frame_index: u32,
         frames_dropped: u32,
+        reordered_shard_counter: u32,
     ) {
         if let Some(frame) = self.stats_history_buffer.iter_mut().find(|frame| {
             frame.client_stats.target_timestamp == target_timestamp
@@ -100,6 +170,7 @@ impl StatisticsManager {
         }) {
             frame.client_stats.frame_index = frame_index as i32;
             frame.client_stats.frames_dropped = frames_dropped;
+            frame.client_stats.reordered_shard_counter = reordered_shard_counter;
         }
     }
 
@@ -183,6 +254,9 @@ impl StatisticsManager {
                         true
                     }
                 });
+
+                self.export_history_frame(&frame);
+
                 Some(frame_client_stats_clone)
             } else {
                 None
@@ -204,3 +278,76 @@ impl StatisticsManager {
             .saturating_sub(self.steamvr_pipeline_latency)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_export_offsets() {
+        // Unique per process, so that parallel test runs don't share the file
+        let path = std::env::temp_dir().join(format!(
+            "alvr_client_history_export_test.{}.jsonl",
+            std::process::id()
+        ));
+
+        let mut manager = StatisticsManager::new(256, Duration::from_secs_f32(1.0 / 90.0), 0.0);
+        manager.enable_history_export(&path).unwrap();
+
+        // (target timestamp ms, packet received, decode, decoder queue, rendering in us)
+        let frames = [
+            (100, 5_000, 3_000, 2_000, 4_000),
+            (111, 7_500, 1_250, 500, 6_000),
+        ];
+
+        let input_acquired = Instant::now();
+        for &(timestamp_ms, received_us, decode_us, queue_us, rendering_us) in &frames {
+            manager.stats_history_buffer.push_back(HistoryFrame {
+                input_acquired,
+                video_packet_received: input_acquired + Duration::from_micros(received_us),
+                client_stats: ClientStatistics {
+                    target_timestamp: Duration::from_millis(timestamp_ms),
+                    frame_index: timestamp_ms as i32,
+                    video_decode: Duration::from_micros(decode_us),
+                    video_decoder_queue: Duration::from_micros(queue_us),
+                    rendering: Duration::from_micros(rendering_us),
+                    ..Default::default()
+                },
+                is_decoded: true,
+                is_composed: true,
+                is_submitted: true,
+            });
+        }
+        for &(timestamp_ms, ..) in &frames {
+            assert!(manager
+                .summary(Duration::from_millis(timestamp_ms))
+                .is_some());
+        }
+
+        // Dropping the manager flushes the writer
+        drop(manager);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let snapshots = contents
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(snapshots.len(), 2);
+
+        let offsets_us = |snapshot: &serde_json::Value| {
+            [
+                "video_packet_received_us",
+                "frame_decoded_us",
+                "compositor_start_us",
+                "frame_submitted_us",
+            ]
+            .map(|key| snapshot[key].as_u64().unwrap())
+        };
+        assert_eq!(offsets_us(&snapshots[0]), [5_000, 8_000, 10_000, 14_000]);
+        assert_eq!(offsets_us(&snapshots[1]), [7_500, 8_750, 9_250, 15_250]);
+        assert_eq!(snapshots[0]["client_stats"]["frame_index"], 100);
+        assert_eq!(snapshots[1]["client_stats"]["frame_index"], 111);
+    }
+}
@@ -1,19 +1,37 @@
-use alvr_common::{error, info};
+use alvr_common::{anyhow::Result, error, info};
 use app_dirs2::{AppDataType, AppInfo};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const APP_INFO: AppInfo = AppInfo {
+    name: "ALVR Client",
+    author: "ALVR",
+};
 
 fn config_path() -> PathBuf {
-    app_dirs2::app_root(
-        AppDataType::UserConfig,
-        &AppInfo {
-            name: "ALVR Client",
-            author: "ALVR",
-        },
-    )
-    .unwrap()
-    .join("session.json")
+    app_dirs2::app_root(AppDataType::UserConfig, &APP_INFO)
+        .unwrap()
+        .join("session.json")
+}
+
+// A new file for every stream, named after the start time
+pub fn frame_history_path(compressed: bool) -> Result<PathBuf> {
+    let start_time_s = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let data_dir = app_dirs2::app_root(AppDataType::UserData, &APP_INFO)?;
+
+    Ok(data_dir.join(format!(
+        "frame_history.{start_time_s}.jsonl{}",
+        if compressed { ".gz" } else { "" }
+    )))
 }
 
 #[derive(Serialize, Deserialize)]
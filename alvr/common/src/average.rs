@@ -1,19 +1,40 @@
+use serde::{Deserialize, Serialize};
 use std::{collections::VecDeque, time::Duration};
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct SlidingWindowAverage<T> {
     history_buffer: VecDeque<T>,
     max_history_size: usize,
+    // Set while the buffer holds only an initial value that the first sample replaces
+    #[serde(default)]
+    replace_seed: bool,
 }
 
 impl<T> SlidingWindowAverage<T> {
+    // The initial value counts as a sample until it is evicted
     pub fn new(initial_value: T, max_history_size: usize) -> Self {
         Self {
             history_buffer: [initial_value].into_iter().collect(),
             max_history_size,
+            replace_seed: false,
+        }
+    }
+
+    // The initial value is returned only until the first sample, then the window holds real
+    // samples only
+    pub fn new_replacing_seed(initial_value: T, max_history_size: usize) -> Self {
+        Self {
+            replace_seed: true,
+            ..Self::new(initial_value, max_history_size)
         }
     }
 
     pub fn submit_sample(&mut self, sample: T) {
+        if self.replace_seed {
+            self.history_buffer.clear();
+            self.replace_seed = false;
+        }
+
         if self.history_buffer.len() >= self.max_history_size {
             self.history_buffer.pop_front();
         }
@@ -29,6 +50,74 @@ impl<T> SlidingWindowAverage<T> {
     pub fn history_buffer_len(&self) -> usize {
         self.history_buffer.len()
     }
+
+    // Current window contents, oldest first
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = &T> {
+        self.history_buffer.iter()
+    }
+}
+
+impl<T: Clone> SlidingWindowAverage<T> {
+    // Approximation: the relative timing of the samples of the two windows is unknown, so they are
+    // interleaved starting from the most recent ones and the newest max_history_size are kept. Both
+    // windows contribute equally to the result until the shorter one runs out. A window holding only
+    // a seed to be replaced doesn't contribute
+    pub fn merge(&mut self, other: &Self) {
+        if other.replace_seed {
+            return;
+        }
+        if self.replace_seed {
+            self.history_buffer.clear();
+            self.replace_seed = false;
+        }
+
+        let mut own = self.history_buffer.iter().rev();
+        let mut others = other.history_buffer.iter().rev();
+        let mut merged = VecDeque::new();
+        while merged.len() < self.max_history_size {
+            let (own_sample, other_sample) = (own.next(), others.next());
+            if own_sample.is_none() && other_sample.is_none() {
+                break;
+            }
+
+            for sample in [own_sample, other_sample].into_iter().flatten() {
+                if merged.len() < self.max_history_size {
+                    merged.push_front(sample.clone());
+                }
+            }
+        }
+
+        self.history_buffer = merged;
+    }
+}
+
+impl<T: PartialOrd + Copy> SlidingWindowAverage<T> {
+    // O(n) over the current window, evicted samples are not considered
+    pub fn get_min(&self) -> T {
+        self.history_buffer
+            .iter()
+            .copied()
+            .reduce(|min, x| if x < min { x } else { min })
+            .unwrap()
+    }
+
+    pub fn get_max(&self) -> T {
+        self.history_buffer
+            .iter()
+            .copied()
+            .reduce(|max, x| if x > max { x } else { max })
+            .unwrap()
+    }
+
+    // Nearest-rank percentile of the current window, percentile in [0, 100]
+    pub fn get_percentile(&self, percentile: f32) -> T {
+        let mut samples = self.history_buffer.iter().copied().collect::<Vec<_>>();
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * samples.len() as f32).ceil() as usize;
+
+        samples[rank.clamp(1, samples.len()) - 1]
+    }
 }
 
 impl SlidingWindowAverage<f32> {
@@ -49,10 +138,168 @@ impl SlidingWindowAverage<f32> {
             / (self.history_buffer.len() - 1) as f32; // sample variance
         variance.sqrt()
     }
+
+    // Sample standard deviation over the mean. Zero with less than two samples or a mean that is
+    // not positive
+    pub fn coefficient_of_variation(&self) -> f32 {
+        let average = self.get_average();
+        if self.history_buffer.len() < 2 || average <= f32::EPSILON {
+            return 0.;
+        }
+
+        self.get_std() / average
+    }
 }
 
 impl SlidingWindowAverage<Duration> {
     pub fn get_average(&self) -> Duration {
         self.history_buffer.iter().sum::<Duration>() / self.history_buffer.len() as u32
     }
+
+    pub fn coefficient_of_variation(&self) -> f32 {
+        let average = self.get_average().as_secs_f32();
+        if self.history_buffer.len() < 2 || average <= f32::EPSILON {
+            return 0.;
+        }
+        let variance = self
+            .history_buffer
+            .iter()
+            .map(|x| (x.as_secs_f32() - average).powf(2.))
+            .sum::<f32>()
+            / (self.history_buffer.len() - 1) as f32;
+
+        variance.sqrt() / average
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_max_with_eviction() {
+        let mut window = SlidingWindowAverage::new(Duration::from_millis(10), 3);
+        assert_eq!(window.get_min(), Duration::from_millis(10));
+        assert_eq!(window.get_max(), Duration::from_millis(10));
+
+        for (sample_ms, min_ms, max_ms) in [(4, 4, 10), (7, 4, 10), (6, 4, 7), (5, 5, 7), (8, 5, 8)]
+        {
+            window.submit_sample(Duration::from_millis(sample_ms));
+            assert_eq!(window.get_min(), Duration::from_millis(min_ms));
+            assert_eq!(window.get_max(), Duration::from_millis(max_ms));
+        }
+    }
+
+    #[test]
+    fn test_samples_follow_window() {
+        let mut window = SlidingWindowAverage::new(0.0, 4);
+        for sample in [1.0, 2.0, 3.0, 4.0, 5.0] {
+            window.submit_sample(sample);
+        }
+        assert_eq!(
+            window.samples().copied().collect::<Vec<_>>(),
+            vec![2.0, 3.0, 4.0, 5.0]
+        );
+
+        window.retain(2);
+        assert_eq!(
+            window.samples().copied().collect::<Vec<_>>(),
+            vec![4.0, 5.0]
+        );
+        assert_eq!(window.samples().len(), window.history_buffer_len());
+    }
+
+    #[test]
+    fn test_merge_interleaves_recent_samples() {
+        let mut window = SlidingWindowAverage::new(1.0, 4);
+        window.submit_sample(2.0);
+        window.submit_sample(3.0);
+
+        let mut other = SlidingWindowAverage::new(10.0, 4);
+        for sample in [20.0, 30.0, 40.0] {
+            other.submit_sample(sample);
+        }
+
+        window.merge(&other);
+        assert_eq!(
+            window.samples().copied().collect::<Vec<_>>(),
+            vec![30.0, 2.0, 40.0, 3.0]
+        );
+
+        // A seed to be replaced is not a sample
+        window.merge(&SlidingWindowAverage::new_replacing_seed(100.0, 4));
+        assert_eq!(window.get_average(), 18.75);
+    }
+
+    #[test]
+    fn test_replaced_seed_converges_faster() {
+        let mut seeded = SlidingWindowAverage::new(Duration::from_millis(5), 16);
+        let mut replaced = SlidingWindowAverage::new_replacing_seed(Duration::from_millis(5), 16);
+        assert_eq!(replaced.get_average(), Duration::from_millis(5));
+
+        let target = Duration::from_millis(20);
+        let within_1_percent = |average: Duration| {
+            (average.as_secs_f32() - target.as_secs_f32()).abs() <= target.as_secs_f32() / 100.0
+        };
+
+        let mut seeded_converged_after = None;
+        let mut replaced_converged_after = None;
+        for count in 1..=16 {
+            seeded.submit_sample(target);
+            replaced.submit_sample(target);
+
+            if seeded_converged_after.is_none() && within_1_percent(seeded.get_average()) {
+                seeded_converged_after = Some(count);
+            }
+            if replaced_converged_after.is_none() && within_1_percent(replaced.get_average()) {
+                replaced_converged_after = Some(count);
+            }
+        }
+
+        // The seed is evicted only when the window is full
+        assert_eq!(replaced_converged_after, Some(1));
+        assert_eq!(seeded_converged_after, Some(16));
+        assert_eq!(replaced.history_buffer_len(), 16);
+    }
+
+    #[test]
+    fn test_coefficient_of_variation() {
+        // Mean 5, sample standard deviation 2
+        let mut window = SlidingWindowAverage::new_replacing_seed(0.0, 8);
+        for sample in [3.0, 3.0, 5.0, 5.0, 7.0, 7.0, 5.0, 5.0] {
+            window.submit_sample(sample);
+        }
+        let expected_cv = (16.0_f32 / 7.0).sqrt() / 5.0;
+        assert!((window.coefficient_of_variation() - expected_cv).abs() < 1e-6);
+
+        let mut intervals = SlidingWindowAverage::new_replacing_seed(Duration::ZERO, 8);
+        for sample in [3, 3, 5, 5, 7, 7, 5, 5] {
+            intervals.submit_sample(Duration::from_millis(sample));
+        }
+        assert!((intervals.coefficient_of_variation() - expected_cv).abs() < 1e-5);
+
+        let mut zero_mean = SlidingWindowAverage::new(0.0, 4);
+        for sample in [-1.0, 1.0, -1.0, 1.0] {
+            zero_mean.submit_sample(sample);
+        }
+        assert_eq!(zero_mean.coefficient_of_variation(), 0.0);
+
+        assert_eq!(
+            SlidingWindowAverage::new(5.0, 4).coefficient_of_variation(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_percentile() {
+        let mut window = SlidingWindowAverage::new(0.0, 20);
+        for sample in 1..=20 {
+            window.submit_sample(sample as f32);
+        }
+
+        assert_eq!(window.get_percentile(0.0), 1.0);
+        assert_eq!(window.get_percentile(50.0), 10.0);
+        assert_eq!(window.get_percentile(95.0), 19.0);
+        assert_eq!(window.get_percentile(100.0), 20.0);
+    }
 }
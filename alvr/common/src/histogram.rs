@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+// Fixed-bucket histogram. A sample is counted in the first bucket whose upper bound is greater or
+// equal to the sample. The last bucket has no upper bound and collects the overflow.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Histogram {
+    upper_bounds: Vec<f32>,
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn new(mut upper_bounds: Vec<f32>) -> Self {
+        upper_bounds.sort_by(f32::total_cmp);
+        upper_bounds.dedup();
+
+        Self {
+            counts: vec![0; upper_bounds.len() + 1],
+            upper_bounds,
+        }
+    }
+
+    pub fn submit_sample(&mut self, sample: f32) {
+        let index = self.upper_bounds.partition_point(|&bound| bound < sample);
+        self.counts[index] += 1;
+    }
+
+    pub fn upper_bounds(&self) -> &[f32] {
+        &self.upper_bounds
+    }
+
+    // One more entry than upper_bounds, the last one is the overflow bucket
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    pub fn reset(&mut self) {
+        self.counts.iter_mut().for_each(|count| *count = 0);
+    }
+
+    // Adds the counts of a histogram with the same buckets. Returns false and leaves the counts
+    // unchanged if the buckets differ
+    pub fn merge(&mut self, other: &Histogram) -> bool {
+        if self.upper_bounds != other.upper_bounds {
+            return false;
+        }
+
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_boundaries() {
+        let mut histogram = Histogram::new(vec![20.0, 10.0]);
+        assert_eq!(histogram.upper_bounds(), &[10.0, 20.0]);
+
+        for sample in [0.0, 10.0, 10.5, 20.0, 1000.0] {
+            histogram.submit_sample(sample);
+        }
+        assert_eq!(histogram.counts(), &[2, 2, 1]);
+
+        histogram.reset();
+        assert_eq!(histogram.counts(), &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut histogram = Histogram::new(vec![10.0]);
+        histogram.submit_sample(5.0);
+
+        let mut other = Histogram::new(vec![10.0]);
+        other.submit_sample(5.0);
+        other.submit_sample(50.0);
+
+        assert!(histogram.merge(&other));
+        assert_eq!(histogram.counts(), &[2, 1]);
+
+        assert!(!histogram.merge(&Histogram::new(vec![20.0])));
+        assert_eq!(histogram.counts(), &[2, 1]);
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+// Uninformative prior of the delay rate, which is unknown until a few samples arrive
+const INITIAL_RATE_VARIANCE: f32 = 1.0;
+
+// Kalman filter of a delay (in seconds) with a constant rate model. The rate is the delay gradient,
+// in seconds per second. The process noise is the spectral density of the rate changes, in
+// (s/s)^2 per second, and the measurement noise is the variance of the delay samples, in s^2
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DelayKalmanFilter {
+    process_noise: f32,
+    measurement_noise: f32,
+    delay: f32,
+    rate: f32,
+    covariance: [[f32; 2]; 2],
+    initialized: bool,
+}
+
+impl DelayKalmanFilter {
+    pub fn new(process_noise: f32, measurement_noise: f32) -> Self {
+        Self {
+            process_noise,
+            measurement_noise,
+            delay: 0.0,
+            rate: 0.0,
+            covariance: [[0.0; 2]; 2],
+            initialized: false,
+        }
+    }
+
+    // The state is kept, only the following samples use the new noise values
+    pub fn set_noise(&mut self, process_noise: f32, measurement_noise: f32) {
+        self.process_noise = process_noise;
+        self.measurement_noise = measurement_noise;
+    }
+
+    // dt_s is the time elapsed since the previous sample
+    pub fn submit_sample(&mut self, delay: f32, dt_s: f32) {
+        if !self.initialized {
+            self.delay = delay;
+            self.rate = 0.0;
+            self.covariance = [[self.measurement_noise, 0.0], [0.0, INITIAL_RATE_VARIANCE]];
+            self.initialized = true;
+
+            return;
+        }
+
+        // Predict
+        let q = self.process_noise;
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        self.delay += self.rate * dt_s;
+        let p00 = p00 + dt_s * (p01 + p10) + dt_s * dt_s * p11 + q * dt_s.powi(3) / 3.0;
+        let p01 = p01 + dt_s * p11 + q * dt_s * dt_s / 2.0;
+        let p10 = p10 + dt_s * p11 + q * dt_s * dt_s / 2.0;
+        let p11 = p11 + q * dt_s;
+
+        // Update, only the delay is measured
+        let innovation_variance = p00 + self.measurement_noise;
+        let k0 = p00 / innovation_variance;
+        let k1 = p10 / innovation_variance;
+        let innovation = delay - self.delay;
+        self.delay += k0 * innovation;
+        self.rate += k1 * innovation;
+        self.covariance = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+
+    pub fn delay(&self) -> f32 {
+        self.delay
+    }
+
+    pub fn delay_rate(&self) -> f32 {
+        self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variance(values: &[f32]) -> f32 {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn test_tracks_noisy_ramp() {
+        let dt_s = 0.011;
+        let mut filter = DelayKalmanFilter::new(1E-6, 4E-6);
+
+        let mut filtered_errors = vec![];
+        let mut measured_errors = vec![];
+        for i in 0..1000 {
+            // 2 ms/s ramp with up to 3 ms of noise
+            let delay = 0.010 + 0.002 * i as f32 * dt_s;
+            let noise = (((i * 7919) % 13) as f32 / 12.0 - 0.5) * 0.006;
+            filter.submit_sample(delay + noise, dt_s);
+
+            // Skip the convergence
+            if i >= 200 {
+                filtered_errors.push(filter.delay() - delay);
+                measured_errors.push(noise);
+            }
+        }
+
+        assert!(variance(&filtered_errors) < 0.1 * variance(&measured_errors));
+        assert!((filter.delay_rate() - 0.002).abs() < 0.0005);
+    }
+}
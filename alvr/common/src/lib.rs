@@ -1,8 +1,13 @@
 mod average;
 mod connection_result;
+mod histogram;
 mod inputs;
+mod kalman;
+mod log_file;
 mod logging;
+mod maximum;
 mod primitives;
+mod sum;
 mod timely;
 mod version;
 mod weighted;
@@ -21,10 +26,15 @@ pub use settings_schema;
 
 pub use average::*;
 pub use connection_result::*;
+pub use histogram::*;
 pub use inputs::*;
+pub use kalman::*;
 pub use log::{debug, error, info, warn};
+pub use log_file::*;
 pub use logging::*;
+pub use maximum::*;
 pub use primitives::*;
+pub use sum::*;
 pub use timely::*;
 pub use version::*;
 pub use weighted::*;
@@ -0,0 +1,57 @@
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+// Buffered writer for long per-frame logs. Paths ending with .gz are written through a gzip
+// encoder, the stream is finished when the writer is dropped
+pub fn create_log_file(path: &Path) -> io::Result<Box<dyn Write + Send>> {
+    let file = File::create(path)?;
+
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        Ok(Box::new(BufWriter::new(GzEncoder::new(
+            file,
+            Compression::fast(),
+        ))))
+    } else {
+        Ok(Box::new(BufWriter::new(file)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_gzip_log_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("alvr_log_file_test.{}.csv.gz", std::process::id()));
+
+        let rows = ["frame_index,shards_lost", "0,0", "1,3", "2,0"];
+        let mut writer = create_log_file(&path).unwrap();
+        for row in rows {
+            writeln!(writer, "{row}").unwrap();
+        }
+        drop(writer);
+
+        let mut compressed = vec![];
+        File::open(&path)
+            .unwrap()
+            .read_to_end(&mut compressed)
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // gzip magic number
+        assert_eq!(compressed[..2], [0x1f, 0x8b]);
+
+        let mut contents = String::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents.lines().collect::<Vec<_>>(), rows);
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Tracks the maximum of the last max_history_size samples. The candidates queue is kept in
+// decreasing order, so every sample is pushed and popped at most once.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SlidingWindowMax<T> {
+    candidates: VecDeque<(usize, T)>,
+    samples_count: usize,
+    max_history_size: usize,
+}
+
+impl<T: PartialOrd + Copy> SlidingWindowMax<T> {
+    pub fn new(initial_value: T, max_history_size: usize) -> Self {
+        Self {
+            candidates: [(0, initial_value)].into_iter().collect(),
+            samples_count: 1,
+            max_history_size,
+        }
+    }
+
+    pub fn submit_sample(&mut self, sample: T) {
+        while let Some(&(_, value)) = self.candidates.back() {
+            if value <= sample {
+                self.candidates.pop_back();
+            } else {
+                break;
+            }
+        }
+
+        self.candidates.push_back((self.samples_count, sample));
+        self.samples_count += 1;
+
+        while let Some(&(index, _)) = self.candidates.front() {
+            if index + self.max_history_size < self.samples_count {
+                self.candidates.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn get_max(&self) -> T {
+        // The latest sample is never evicted, so the queue is never empty
+        self.candidates.front().unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_with_eviction() {
+        let samples = [5.0, 1.0, 3.0, 9.0, 2.0, 2.0, 1.0, 0.5, 4.0, 3.0, 8.0, 1.0];
+        let window_size = 4;
+
+        let mut window = SlidingWindowMax::new(0.0_f32, window_size);
+        let mut history = vec![0.0_f32];
+
+        for sample in samples {
+            window.submit_sample(sample);
+            history.push(sample);
+
+            let expected = history
+                .iter()
+                .rev()
+                .take(window_size)
+                .cloned()
+                .fold(f32::MIN, f32::max);
+            assert_eq!(window.get_max(), expected);
+        }
+    }
+}
@@ -46,3 +46,43 @@ pub struct DeviceMotion {
     pub linear_velocity: Vec3,
     pub angular_velocity: Vec3,
 }
+
+// Bits per second. Serialized as a plain number of bits per second
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, PartialOrd)]
+#[serde(transparent)]
+pub struct Bitrate(f32);
+
+impl Bitrate {
+    pub const fn from_bps(bps: f32) -> Self {
+        Self(bps)
+    }
+
+    pub fn from_mbps(mbps: f32) -> Self {
+        Self(mbps * 1e6)
+    }
+
+    pub const fn as_bps(self) -> f32 {
+        self.0
+    }
+
+    pub fn as_mbps(self) -> f32 {
+        self.0 / 1e6
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitrate_round_trip() {
+        for mbps in [0.0, 0.5, 12.5, 30.0, 100.0] {
+            assert_eq!(Bitrate::from_mbps(mbps).as_mbps(), mbps);
+        }
+        for bps in [0.0, 1.0, 2_500_000.0, 150_000_000.0] {
+            assert_eq!(Bitrate::from_bps(bps).as_bps(), bps);
+        }
+        assert_eq!(Bitrate::from_mbps(30.0).as_bps(), 30_000_000.0);
+        assert_eq!(Bitrate::from_bps(2_500_000.0).as_mbps(), 2.5);
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// Sum of the samples submitted within the last window_duration. Each sample is attributed to the
+// window by its own timestamp, so the sum does not depend on when it is read
+#[derive(Clone)]
+pub struct SlidingWindowSum {
+    samples: VecDeque<(Instant, f32)>,
+    window_duration: Duration,
+}
+
+impl SlidingWindowSum {
+    pub fn new(window_duration: Duration) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window_duration,
+        }
+    }
+
+    // Samples must be submitted in timestamp order
+    pub fn submit_sample(&mut self, sample: f32, timestamp: Instant) {
+        self.samples.push_back((timestamp, sample));
+
+        while let Some(&(oldest, _)) = self.samples.front() {
+            if timestamp.saturating_duration_since(oldest) >= self.window_duration {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn get_sum(&self, now: Instant) -> f32 {
+        self.samples
+            .iter()
+            .filter(|(timestamp, _)| {
+                now.saturating_duration_since(*timestamp) < self.window_duration
+            })
+            .map(|(_, sample)| sample)
+            .sum()
+    }
+
+    // Sum per second
+    pub fn get_rate(&self, now: Instant) -> f32 {
+        self.get_sum(now) / self.window_duration.as_secs_f32()
+    }
+
+    // Exact, the samples keep their own timestamps
+    pub fn merge(&mut self, other: &SlidingWindowSum) {
+        let mut merged = VecDeque::with_capacity(self.samples.len() + other.samples.len());
+        let mut own = self.samples.iter().peekable();
+        let mut others = other.samples.iter().peekable();
+        while let Some(&entry) = match (own.peek(), others.peek()) {
+            (Some(a), Some(b)) if b.0 < a.0 => others.next(),
+            (Some(_), _) => own.next(),
+            (None, _) => others.next(),
+        } {
+            merged.push_back(entry);
+        }
+
+        self.samples = merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_rate_is_smoother_than_reset_rate() {
+        let start = Instant::now();
+        let window = Duration::from_millis(500);
+
+        // A burst of 100 KB every 100 ms is 1 MB/s. The reports come 5 ms early or late
+        let bursts = (1..=40).map(|i| start + Duration::from_millis(i * 100));
+        let reports = (1..8).map(|i| {
+            let jitter_ms = if i % 2 == 0 { 5 } else { -5 };
+            start + Duration::from_millis((i * 500 + jitter_ms) as u64)
+        });
+
+        let mut sum = SlidingWindowSum::new(window);
+        let mut partial_sum = 0.0;
+        let mut last_report = start;
+        let mut bursts = bursts.peekable();
+
+        let mut sliding_rates = vec![];
+        let mut reset_rates = vec![];
+        for report in reports {
+            while let Some(burst) = bursts.next_if(|&burst| burst <= report) {
+                sum.submit_sample(100_000.0, burst);
+                partial_sum += 100_000.0;
+            }
+
+            sliding_rates.push(sum.get_rate(report));
+            reset_rates.push(partial_sum / (report - last_report).as_secs_f32());
+
+            partial_sum = 0.0;
+            last_report = report;
+        }
+
+        // The first report covers less than a full window
+        let spread = |rates: &[f32]| {
+            let max = rates.iter().copied().fold(f32::MIN, f32::max);
+            let min = rates.iter().copied().fold(f32::MAX, f32::min);
+            max - min
+        };
+        assert!(spread(&sliding_rates[1..]) < 1.0);
+        assert!(spread(&reset_rates[1..]) > 300_000.0);
+        assert!((sliding_rates[3] - 1e6).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_old_samples_leave_the_window() {
+        let start = Instant::now();
+        let mut sum = SlidingWindowSum::new(Duration::from_millis(500));
+
+        sum.submit_sample(1.0, start);
+        sum.submit_sample(2.0, start + Duration::from_millis(300));
+        assert_eq!(sum.get_sum(start + Duration::from_millis(300)), 3.0);
+        assert_eq!(sum.get_sum(start + Duration::from_millis(600)), 2.0);
+
+        sum.submit_sample(4.0, start + Duration::from_millis(900));
+        assert_eq!(sum.samples.len(), 1);
+        assert_eq!(sum.get_sum(start + Duration::from_millis(900)), 4.0);
+    }
+}
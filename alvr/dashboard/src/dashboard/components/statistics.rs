@@ -1,4 +1,5 @@
 use crate::{dashboard::theme::graph_colors, dashboard::ServerRequest};
+use alvr_common::Bitrate;
 use alvr_events::{GraphNetworkStatistics, GraphStatistics, StatisticsSummary};
 use alvr_gui_common::theme;
 use eframe::{
@@ -44,6 +45,11 @@ impl StatisticsTab {
     }
 
     pub fn update_graph_statistics(&mut self, statistics: GraphStatistics) {
+        // All zero client latencies, not a measurement
+        if statistics.client_stats_defaulted {
+            return;
+        }
+
         self.history.pop_front();
         self.history.push_back(statistics);
     }
@@ -521,7 +527,8 @@ impl StatisticsTab {
                     let value_nw = pointer_graphstatistics.interval_avg_plot_throughput;
                     network_throughput_bps.push(to_screen_trans * pos2(i as f32, value_nw / 1e6));
 
-                    requested.push(to_screen_trans * pos2(i as f32, nom_br.requested_bps / 1e6));
+                    requested
+                        .push(to_screen_trans * pos2(i as f32, nom_br.requested_bps.as_mbps()));
                 }
                 draw_lines(painter, network_throughput_bps, Color32::BLUE);
                 draw_lines(painter, requested, theme::OK_GREEN);
@@ -549,7 +556,7 @@ impl StatisticsTab {
                 maybe_label(
                     ui,
                     "Requested Bitrate",
-                    Some(n.requested_bps),
+                    Some(n.requested_bps.as_bps()),
                     theme::OK_GREEN,
                 );
             },
@@ -573,6 +580,8 @@ impl StatisticsTab {
                 let mut decoder_latency_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut network_latency_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut encoder_latency_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
+                let mut packet_loss_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
+                let mut capacity_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut manual_max = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut manual_min = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut requested = Vec::with_capacity(GRAPH_HISTORY_SIZE);
@@ -582,25 +591,35 @@ impl StatisticsTab {
                     let nom_br = &self.history[i].nominal_bitrate;
 
                     if let Some(value) = nom_br.scaled_calculated_bps {
-                        scaled_calculated.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                        scaled_calculated.push(to_screen_trans * pos2(i as f32, value.as_mbps()))
                     }
                     if let Some(value) = nom_br.decoder_latency_limiter_bps {
-                        decoder_latency_limiter.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                        decoder_latency_limiter
+                            .push(to_screen_trans * pos2(i as f32, value.as_mbps()))
                     }
                     if let Some(value) = nom_br.network_latency_limiter_bps {
-                        network_latency_limiter.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                        network_latency_limiter
+                            .push(to_screen_trans * pos2(i as f32, value.as_mbps()))
                     }
                     if let Some(value) = nom_br.encoder_latency_limiter_bps {
-                        encoder_latency_limiter.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                        encoder_latency_limiter
+                            .push(to_screen_trans * pos2(i as f32, value.as_mbps()))
+                    }
+                    if let Some(value) = nom_br.packet_loss_limiter_bps {
+                        packet_loss_limiter.push(to_screen_trans * pos2(i as f32, value.as_mbps()))
+                    }
+                    if let Some(value) = nom_br.capacity_limiter_bps {
+                        capacity_limiter.push(to_screen_trans * pos2(i as f32, value.as_mbps()))
                     }
                     if let Some(value) = nom_br.manual_max_bps {
-                        manual_max.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                        manual_max.push(to_screen_trans * pos2(i as f32, value.as_mbps()))
                     }
                     if let Some(value) = nom_br.manual_min_bps {
-                        manual_min.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                        manual_min.push(to_screen_trans * pos2(i as f32, value.as_mbps()))
                     }
 
-                    requested.push(to_screen_trans * pos2(i as f32, nom_br.requested_bps / 1e6));
+                    requested
+                        .push(to_screen_trans * pos2(i as f32, nom_br.requested_bps.as_mbps()));
                     actual.push(
                         to_screen_trans * pos2(i as f32, self.history[i].actual_bitrate_bps / 1e6),
                     );
@@ -609,6 +628,8 @@ impl StatisticsTab {
                 draw_lines(painter, scaled_calculated, Color32::GRAY);
                 draw_lines(painter, encoder_latency_limiter, graph_colors::TRANSCODE);
                 draw_lines(painter, network_latency_limiter, graph_colors::NETWORK);
+                draw_lines(painter, packet_loss_limiter, graph_colors::NETWORK);
+                draw_lines(painter, capacity_limiter, graph_colors::NETWORK);
                 draw_lines(painter, decoder_latency_limiter, graph_colors::TRANSCODE);
                 draw_lines(painter, manual_max, graph_colors::RENDER);
                 draw_lines(painter, manual_min, graph_colors::RENDER);
@@ -619,11 +640,11 @@ impl StatisticsTab {
                 fn maybe_label(
                     ui: &mut Ui,
                     text: &str,
-                    maybe_value_bps: Option<f32>,
+                    maybe_value: Option<Bitrate>,
                     color: Color32,
                 ) {
-                    if let Some(value) = maybe_value_bps {
-                        ui.colored_label(color, &format!("{text}: {:.2} Mbps", value / 1e6));
+                    if let Some(value) = maybe_value {
+                        ui.colored_label(color, &format!("{text}: {:.2} Mbps", value.as_mbps()));
                     }
                 }
 
@@ -647,6 +668,49 @@ impl StatisticsTab {
                     n.network_latency_limiter_bps,
                     graph_colors::NETWORK,
                 );
+                maybe_label(
+                    ui,
+                    "Packet loss limiter",
+                    n.packet_loss_limiter_bps,
+                    graph_colors::NETWORK,
+                );
+                maybe_label(
+                    ui,
+                    "Capacity limiter",
+                    n.capacity_limiter_bps,
+                    graph_colors::NETWORK,
+                );
+                maybe_label(ui, "TFRC rate", n.tfrc_rate_bps, graph_colors::NETWORK);
+                maybe_label(ui, "QoE floor", n.qoe_floor_bps, graph_colors::NETWORK);
+                maybe_label(
+                    ui,
+                    "Blend adaptive",
+                    n.blend_adaptive_bps,
+                    graph_colors::NETWORK,
+                );
+                maybe_label(
+                    ui,
+                    "Blend heuristic",
+                    n.blend_heuristic_bps,
+                    graph_colors::NETWORK,
+                );
+                if let Some(step_bps) = n.heuristic_step_bps {
+                    ui.colored_label(
+                        Color32::GRAY,
+                        &format!("Heuristic step: {:+.2} Mbps", step_bps / 1e6),
+                    );
+                }
+                ui.colored_label(
+                    Color32::GRAY,
+                    &format!(
+                        "Capacity headroom: {:.2} Mbps",
+                        n.capacity_headroom_bps / 1e6
+                    ),
+                );
+                ui.colored_label(
+                    Color32::GRAY,
+                    &format!("Binding constraint: {:?}", n.binding_constraint),
+                );
                 maybe_label(
                     ui,
                     "Decoder latency limiter",
@@ -659,7 +723,7 @@ impl StatisticsTab {
                 maybe_label(
                     ui,
                     "Actual recorded",
-                    Some(stats.actual_bitrate_bps),
+                    Some(Bitrate::from_bps(stats.actual_bitrate_bps)),
                     theme::FG,
                 );
             },
@@ -680,7 +744,17 @@ impl StatisticsTab {
             ui[1].label(&format!("{} MB", statistics.video_mbytes_total));
 
             ui[0].label("Bitrate:");
-            ui[1].label(&format!("{:.1} Mbps", statistics.video_mbits_per_sec));
+            ui[1].label(&format!(
+                "{:.1} Mbps (peak: {:.1} Mbps)",
+                statistics.video_mbits_per_sec, statistics.video_mbits_per_sec_peak
+            ));
+
+            ui[0].label("Average frame size:");
+            ui[1].label(&format!(
+                "{:.1} KB (IDR: {:.1} KB)",
+                statistics.avg_delta_bytes / 1000.0,
+                statistics.avg_idr_bytes / 1000.0
+            ));
 
             ui[0].label("Throughput:");
             ui[1].label(&format!(
@@ -751,6 +825,9 @@ impl StatisticsTab {
             ui[0].label("Streamer FPS:");
             ui[1].label(&format!("{} FPS", statistics.server_fps));
 
+            ui[0].label("Effective FPS:");
+            ui[1].label(&format!("{} FPS", statistics.effective_fps));
+
             ui[0].label("Headset battery");
             ui[1].label(&format!(
                 "{}% ({})",
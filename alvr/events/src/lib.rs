@@ -1,15 +1,22 @@
-use alvr_common::{info, DeviceMotion, LogEntry, Pose};
+use alvr_common::{info, Bitrate, DeviceMotion, LogEntry, Pose};
 use alvr_packets::{AudioDevicesList, ButtonValue};
 use alvr_session::SessionConfig;
 use serde::{Deserialize, Serialize};
 use std::{path::PathBuf, time::Duration};
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct StatisticsSummary {
-    pub video_packets_total: usize,
+    pub video_packets_total: u64,
     pub video_packets_per_sec: usize,
 
-    pub video_mbytes_total: usize,
+    pub video_mbytes_total: u64,
     pub video_mbits_per_sec: f32,
+    // Highest bitrate of a single encoded frame within the summary interval, the frame size over the
+    // time since the previous frame
+    pub video_mbits_per_sec_peak: f32,
+
+    // Average encoded frame sizes, keyframes are kept apart since they are much larger
+    pub avg_idr_bytes: f32,
+    pub avg_delta_bytes: f32,
 
     pub video_throughput_mbits_per_sec: f32,
 
@@ -23,44 +30,115 @@ pub struct StatisticsSummary {
     pub client_compositor_average_ms: f32,
     pub vsync_queue_delay_average_ms: f32,
 
-    pub packets_dropped_total: usize,
+    pub packets_dropped_total: u64,
     pub packets_dropped_per_sec: usize,
 
-    pub packets_skipped_total: usize,
+    pub packets_skipped_total: u64,
     pub packets_skipped_per_sec: usize,
 
     pub frame_jitter_ms: f32,
 
     pub client_fps: f32,
     pub server_fps: f32,
+    pub effective_fps: f32,
 
     pub battery_hmd: u32,
     pub hmd_plugged: bool,
+
+    pub qoe_score: f32,
+
+    pub stall_detected: bool,
+    pub stall_count_total: usize,
+    pub stall_duration_total_s: f32,
+
+    pub network_latency_underflow_count: usize,
+    pub network_latency_underflow_total_ms: f32,
+
+    pub server_frame_pacing_cv: f32,
+
+    // Frames presented with an offset reaching back before their tracking was received
+    pub frame_present_offset_clamp_count: usize,
+
+    // Client clock minus server clock, as estimated from the one-way delay
+    pub estimated_clock_skew_s: f32,
+
+    // Encoded video bitrate over the last requested bitrate. Persistently low values point to an
+    // encoder that cannot reach the target rather than to a network limit
+    pub bitrate_achievement_ratio: f32,
+}
+
+// Limit that determined the bitrate sent to the encoder. None if the decision of the bitrate mode
+// went through unchanged
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingConstraint {
+    #[default]
+    None,
+    Capacity,
+    PacketLoss,
+    NetworkLatency,
+    EncoderLatency,
+    DecoderLatency,
+    ManualMax,
+    ManualMin,
+    QoeFloor,
+    Slew,
 }
 
 // Bitrate statistics minus the empirical output value
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct NominalBitrateStats {
-    pub scaled_calculated_bps: Option<f32>,
-    pub decoder_latency_limiter_bps: Option<f32>,
-    pub network_latency_limiter_bps: Option<f32>,
-    pub encoder_latency_limiter_bps: Option<f32>,
-    pub manual_max_bps: Option<f32>,
-    pub manual_min_bps: Option<f32>,
-    pub requested_bps: f32,
+    pub scaled_calculated_bps: Option<Bitrate>,
+    pub decoder_latency_limiter_bps: Option<Bitrate>,
+    pub network_latency_limiter_bps: Option<Bitrate>,
+    pub encoder_latency_limiter_bps: Option<Bitrate>,
+    pub packet_loss_limiter_bps: Option<Bitrate>,
+    pub capacity_limiter_bps: Option<Bitrate>,
+    // Signed bitrate change decided by the heuristic before the limits are applied
+    pub heuristic_step_bps: Option<f32>,
+    pub buffer_based_bps: Option<Bitrate>,
+    pub content_fill_ratio: Option<f32>,
+    pub bottleneck_bandwidth_bps: Option<Bitrate>,
+    pub bbr_probing: Option<bool>,
+    pub aimd_congestion: Option<bool>,
+    pub manual_max_bps: Option<Bitrate>,
+    pub manual_min_bps: Option<Bitrate>,
+    // Set while the QoE floor holds the bitrate above the decision of the mode
+    pub qoe_floor_bps: Option<Bitrate>,
+    pub requested_bps: Bitrate,
+    pub update_interval_s: f32,
+    pub capacity_headroom_bps: f32,
+    pub tfrc_rate_bps: Option<Bitrate>,
+    // Average number of packets between loss events, the inverse of the loss event rate
+    pub loss_event_interval: Option<f32>,
+    // Decisions of the two controllers of the blend mode, before weighting
+    pub blend_adaptive_bps: Option<Bitrate>,
+    pub blend_heuristic_bps: Option<Bitrate>,
+    pub binding_constraint: BindingConstraint,
 }
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct GraphStatistics {
+    pub client_id: u64,
+
+    // Target timestamp relative to the first frame with graph statistics, for the time axis
+    pub graph_time_ns: u64,
+
     pub frame_index: i32,
     pub is_idr: bool,
 
     pub frames_dropped: u32,
+    pub reordered_shard_counter: u32,
+
+    pub shard_loss_ratio: f32,
+    pub frame_loss_ratio: f32,
 
     pub total_pipeline_latency_s: f32,
     pub game_time_s: f32,
     pub server_compositor_s: f32,
     pub encoder_s: f32,
     pub network_s: f32,
+    pub network_latency_min_s: f32,
+    pub network_latency_max_s: f32,
     pub decoder_s: f32,
     pub decoder_queue_s: f32,
     pub client_compositor_s: f32,
@@ -70,6 +148,27 @@ pub struct GraphStatistics {
     //pub server_fps: f32,
     pub nominal_bitrate: NominalBitrateStats,
     pub actual_bitrate_bps: f32,
+    pub actual_bitrate_min_bps: f32,
+    pub actual_bitrate_max_bps: f32,
+
+    // Received throughput over the last summary interval, including any probe traffic
+    pub capacity_estimate_bps: f32,
+
+    // RFC 3550 interarrival jitter computed by the server from the send times and the client
+    // measured arrival times of the frames, to cross-check the jitter reported by the client
+    pub server_computed_jitter_s: f32,
+
+    // Network latency from the one-way delay measured by the client instead of the subtraction of
+    // the other stages from the total latency. Zero until the clock skew is estimated
+    pub network_s_owd_based: f32,
+
+    // The client statistics of the frame carried no measurement (all fields defaulted), so the
+    // client latencies of this row are zero rather than measured. Plots should skip it
+    pub client_stats_defaulted: bool,
+
+    // Received throughput over the peak throughput average, the capacity estimate also used by the
+    // bitrate heuristic. Near 1 the link is saturated. Zero until the peak throughput is measured
+    pub link_utilization: f32,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -98,6 +197,8 @@ pub struct GraphNetworkStatistics {
 
     pub instant_network_throughput_bps: f32,
     pub peak_network_throughput_bps: f32,
+    pub peak_goodput_bps: f32,
+    pub protocol_overhead_ratio: f32,
 
     pub nominal_bitrate: NominalBitrateStats,
 
@@ -118,9 +219,46 @@ pub struct HeuristicStats {
     pub threshold_rtt_s: f32,
     pub threshold_u: f32,
 
+    pub duplicate_ratio: f32,
+    pub increase_suppressed: bool,
+
+    // Capacity estimate after the staleness decay
+    pub effective_capacity_bps: f32,
+    // The capacity was injected by an external estimator instead of measured
+    pub external_capacity_used: bool,
+    // Capacity estimate minus the target bitrate, clamped at zero
+    pub capacity_headroom_bps: f32,
+    // Consecutive increases clamped by the capacity limit, and updates left in which increases are
+    // skipped because of them
+    pub capacity_binding_streak: u32,
+    pub capacity_backoff_updates: u32,
+
     pub requested_bitrate_bps: f32,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LatencyStage {
+    GameTime,
+    ServerCompositor,
+    Encoder,
+    Network,
+    Decoder,
+    DecoderQueue,
+    Rendering,
+    VsyncQueue,
+}
+
+// Emitted when a stage of a frame exceeds its configured budget, at most once per throttle
+// interval for each stage
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LatencyBudgetAlarm {
+    pub client_id: u64,
+    pub frame_index: i32,
+    pub stage: LatencyStage,
+    pub measured_ms: f32,
+    pub budget_ms: f32,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TrackingEvent {
     pub head_motion: Option<DeviceMotion>,
@@ -155,6 +293,7 @@ pub enum EventType {
     GraphStatistics(GraphStatistics),
     GraphNetworkStatistics(GraphNetworkStatistics),
     HeuristicStats(HeuristicStats),
+    LatencyBudgetAlarm(LatencyBudgetAlarm),
     Tracking(Box<TrackingEvent>),
     Buttons(Vec<ButtonEvent>),
     Haptics(HapticsEvent),
@@ -231,6 +231,7 @@ pub struct ClientStatistics {
     pub total_pipeline_latency: Duration,
 
     pub frames_dropped: u32,
+    pub reordered_shard_counter: u32,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
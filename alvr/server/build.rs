@@ -155,6 +155,9 @@ fn main() {
         println!("cargo:rustc-cdylib-link-arg=-Wl,--no-undefined");
     }
 
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/statistics.proto").unwrap();
+
     for path in cpp_paths {
         println!("cargo:rerun-if-changed={}", path.to_string_lossy());
     }
@@ -74,7 +74,10 @@ struct FfiButtonValue {
 struct FfiDynamicEncoderParams {
     unsigned int updated;
     unsigned long long bitrate_bps;
+    unsigned long long bitrate_bps_left;
+    unsigned long long bitrate_bps_right;
     float framerate;
+    float fec_overhead;
 };
 
 extern "C" const unsigned char *FRAME_RENDER_VS_CSO_PTR;
@@ -1,36 +1,371 @@
-use crate::FfiDynamicEncoderParams;
-use alvr_common::{warn, SlidingWindowAverage};
-use alvr_events::{EventType, HeuristicStats, NominalBitrateStats};
+use crate::{
+    clock::{Clock, SystemClock},
+    stats_sink::{EventsSink, StatsSink},
+    FfiDynamicEncoderParams,
+};
+use alvr_common::{warn, Bitrate, LogEntry, LogSeverity, SlidingWindowAverage, SlidingWindowMax};
+use alvr_events::{BindingConstraint, EventType, HeuristicStats, NominalBitrateStats};
 use alvr_session::{
-    settings_schema::Switch, BitrateAdaptiveFramerateConfig, BitrateConfig, BitrateMode,
+    settings_schema::Switch, AdaptiveBitrateConfig, AdaptiveUpdateIntervalConfig,
+    BitrateAdaptiveFramerateConfig, BitrateConfig, BitrateMode, CapacityStalenessConfig,
+    EncoderLatencyReferenceInterval, FecOverheadConfig, IdleResetConfig, PeakThroughputEstimator,
+    QoeFloorConfig, QoeWeightsConfig, SimpleHeuristicConfig, ThresholdAnnealingConfig,
 };
 use std::{
     collections::VecDeque,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use rand::distributions::Uniform;
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+mod capacity;
+#[cfg(any(test, feature = "bitrate-replay"))]
+mod replay;
+mod snapshot;
+
+use capacity::CapacityEstimator;
 
 const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+// Lower bound of the frame interval standard deviation, relative to the mean. Avoids flagging every
+// frame as outlier when the framerate is perfectly regular
+const MIN_FRAME_INTERVAL_RELATIVE_STD: f32 = 0.01;
+const DEFAULT_PACKET_SIZE: usize = 1400;
+const MIN_INITIAL_FRAMERATE: f32 = 1.0;
+const MAX_INITIAL_FRAMERATE: f32 = 1000.0;
+// Avoids an unbounded packet loss ceiling when the RTT is not measured yet
+const MIN_PACKET_LOSS_LIMITER_RTT: Duration = Duration::from_millis(1);
+// Weights of the last loss intervals, most recent first (RFC 5348, section 5.4)
+const TFRC_LOSS_INTERVAL_WEIGHTS: [f32; 8] = [1.0, 1.0, 1.0, 1.0, 0.8, 0.6, 0.4, 0.2];
+
+// Exponentially weighted mean and variance, updated in constant time for every sample
+#[derive(Serialize, Deserialize, Clone)]
+struct IncrementalVariance {
+    mean: f32,
+    variance: f32,
+    alpha: f32,
+}
+
+impl IncrementalVariance {
+    fn new(initial_mean: f32, history_size: usize) -> Self {
+        Self {
+            mean: initial_mean,
+            variance: 0.0,
+            alpha: 2.0 / (history_size as f32 + 1.0),
+        }
+    }
+
+    fn submit_sample(&mut self, sample: f32) {
+        let diff = sample - self.mean;
+        let increment = self.alpha * diff;
+        self.mean += increment;
+        self.variance = (1.0 - self.alpha) * (self.variance + diff * increment);
+    }
+
+    fn get_std(&self) -> f32 {
+        self.variance.sqrt()
+    }
+}
+
+// Exponential decay from start_value to end_value
+fn annealed_threshold(config: &ThresholdAnnealingConfig, elapsed: Duration) -> f32 {
+    let decay = (-elapsed.as_secs_f32() / config.decay_time_constant_s.max(f32::EPSILON)).exp();
+
+    config.end_value + (config.start_value - config.end_value) * decay
+}
+
+// Exponential decay from the raw estimate to the floor once the samples are older than the window
+fn decayed_capacity(config: &CapacityStalenessConfig, raw_bps: f32, age: Duration) -> f32 {
+    let stale_secs = age.as_secs_f32() - config.staleness_window_s;
+    if stale_secs <= 0.0 {
+        return raw_bps;
+    }
+
+    // Never raise an estimate that is already below the floor
+    let floor_bps = f32::min(Bitrate::from_mbps(config.floor_mbps).as_bps(), raw_bps);
+    let decay = (-stale_secs / config.decay_time_constant_s.max(f32::EPSILON)).exp();
+
+    floor_bps + (raw_bps - floor_bps) * decay
+}
+
+// Zero when there are too few samples or the mean is not positive
+fn coefficient_of_variation(samples: &[f32]) -> f32 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / samples.len() as f32;
+
+    variance.sqrt() / mean
+}
+
+// Linear from the maximum interval at zero volatility to the minimum at the reference volatility
+fn adaptive_update_interval(config: &AdaptiveUpdateIntervalConfig, volatility: f32) -> Duration {
+    let t = (volatility / config.reference_volatility.max(f32::EPSILON)).clamp(0.0, 1.0);
+    let max_interval_s = f32::max(config.max_interval_s, config.min_interval_s);
+
+    Duration::from_secs_f32(
+        (max_interval_s - (max_interval_s - config.min_interval_s) * t).max(0.0),
+    )
+}
+
+// Prediction model of the MPC mode, held constant over the horizon
+struct MpcModel<'a> {
+    capacity_mbps: f32,
+    step_s: f32,
+    weights: &'a QoeWeightsConfig,
+}
+
+impl MpcModel<'_> {
+    // Returns the QoE of one step and the decoder queue at its end. The queue grows when the
+    // bitrate exceeds the capacity and drains otherwise. Rebuffering is the queue in milliseconds
+    fn step(&self, queue_s: f32, prev_mbps: f32, mbps: f32) -> (f32, f32) {
+        let next_queue_s = f32::max(
+            queue_s + self.step_s * (mbps - self.capacity_mbps) / self.capacity_mbps,
+            0.0,
+        );
+
+        let qoe = self.weights.bitrate * mbps
+            - self.weights.rebuffering * next_queue_s * 1000.0
+            - self.weights.bitrate_switch * (mbps - prev_mbps).abs();
+
+        (qoe, next_queue_s)
+    }
+
+    // Best QoE over the remaining steps, enumerating every candidate sequence
+    fn best_qoe(&self, candidates: &[f32], steps: usize, queue_s: f32, prev_mbps: f32) -> f32 {
+        if steps == 0 {
+            return 0.0;
+        }
+
+        candidates
+            .iter()
+            .map(|&mbps| {
+                let (qoe, next_queue_s) = self.step(queue_s, prev_mbps, mbps);
+                qoe + self.best_qoe(candidates, steps - 1, next_queue_s, mbps)
+            })
+            .fold(f32::MIN, f32::max)
+    }
+
+    // First step of the best sequence. On ties the lowest bitrate is kept
+    fn plan(&self, candidates: &[f32], horizon: usize, queue_s: f32, prev_mbps: f32) -> f32 {
+        let mut best = (f32::MIN, prev_mbps);
+        for &mbps in candidates {
+            let (qoe, next_queue_s) = self.step(queue_s, prev_mbps, mbps);
+            let qoe =
+                qoe + self.best_qoe(candidates, horizon.saturating_sub(1), next_queue_s, mbps);
+            if qoe > best.0 {
+                best = (qoe, mbps);
+            }
+        }
+
+        best.1
+    }
+}
+
+// Bitrates spaced by step_mbps around the current one, clamped to the limits and sorted
+fn mpc_candidates(current_mbps: f32, steps: usize, step_mbps: f32, min: f32, max: f32) -> Vec<f32> {
+    let steps = steps as i32;
+    let mut candidates = (-steps..=steps)
+        .map(|k| (current_mbps + k as f32 * step_mbps).clamp(min, max))
+        .collect::<Vec<_>>();
+    candidates.dedup();
+
+    candidates
+}
+
+// TCP throughput equation (RFC 5348, section 3.1) with b = 1 and t_RTO = 4 * RTT
+fn tfrc_rate_bps(packet_size_bits: f32, rtt_s: f32, loss_event_rate: f32) -> f32 {
+    let p = loss_event_rate;
+
+    packet_size_bits
+        / (rtt_s * (2.0 * p / 3.0).sqrt()
+            + 4.0 * rtt_s * 3.0 * (3.0 * p / 8.0).sqrt() * p * (1.0 + 32.0 * p * p))
+}
+
+// Weighted average of the closed loss intervals, most recent first. The open interval since the
+// last loss event is included only if it raises the average. None before the first loss event
+fn average_loss_interval(intervals: &VecDeque<u64>, open_interval: u64) -> Option<f32> {
+    if intervals.is_empty() {
+        return None;
+    }
+
+    let weighted_average = |intervals: &[u64]| {
+        let (sum, weights) = intervals.iter().zip(TFRC_LOSS_INTERVAL_WEIGHTS).fold(
+            (0.0, 0.0),
+            |(sum, weights), (&interval, weight)| {
+                (sum + interval as f32 * weight, weights + weight)
+            },
+        );
+        sum / weights
+    };
+
+    let closed = intervals.iter().copied().collect::<Vec<_>>();
+    let with_open = [open_interval]
+        .into_iter()
+        .chain(intervals.iter().copied())
+        .collect::<Vec<_>>();
+
+    Some(f32::max(
+        weighted_average(&closed),
+        weighted_average(&with_open),
+    ))
+}
+
+// Logged for every bitrate update when the decision log is enabled
+#[derive(Serialize)]
+struct BitrateDecision<'a> {
+    mode: &'static str,
+    rtt_ms: f32,
+    server_fps: f32,
+    capacity_bps: f32,
+    loss_ratio: f32,
+    // Decision of the mode before the limits are applied
+    raw_bps: Option<f32>,
+    requested_bps: f32,
+    binding_constraint: BindingConstraint,
+    nominal: &'a NominalBitrateStats,
+    heuristic: Option<&'a HeuristicStats>,
+}
+
+fn mode_name(mode: &BitrateMode) -> &'static str {
+    match mode {
+        BitrateMode::ConstantMbps(_) => "constant",
+        BitrateMode::Adaptive(_) => "adaptive",
+        BitrateMode::SimpleHeuristic(_) => "simple_heuristic",
+        BitrateMode::BufferBased { .. } => "buffer_based",
+        BitrateMode::Bbr { .. } => "bbr",
+        BitrateMode::Mpc { .. } => "mpc",
+        BitrateMode::Tfrc { .. } => "tfrc",
+        BitrateMode::Aimd { .. } => "aimd",
+        BitrateMode::Blend { .. } => "blend",
+    }
+}
+
+fn raw_decision_bps(stats: &NominalBitrateStats) -> Option<f32> {
+    stats
+        .scaled_calculated_bps
+        .or(stats.buffer_based_bps)
+        .or(stats.tfrc_rate_bps)
+        .or(stats.bottleneck_bandwidth_bps)
+        .map(Bitrate::as_bps)
+}
+
+// The tightest limit that moved the decision, if any. The slew rate is applied later, by
+// get_encoder_params()
+fn binding_constraint(stats: &NominalBitrateStats) -> BindingConstraint {
+    if stats.qoe_floor_bps.is_some() {
+        return BindingConstraint::QoeFloor;
+    }
+
+    let Some(raw_bps) = raw_decision_bps(stats) else {
+        return BindingConstraint::None;
+    };
+
+    if stats
+        .manual_min_bps
+        .is_some_and(|min| raw_bps < min.as_bps())
+    {
+        return BindingConstraint::ManualMin;
+    }
+
+    [
+        (BindingConstraint::Capacity, stats.capacity_limiter_bps),
+        (BindingConstraint::PacketLoss, stats.packet_loss_limiter_bps),
+        (
+            BindingConstraint::NetworkLatency,
+            stats.network_latency_limiter_bps,
+        ),
+        (
+            BindingConstraint::DecoderLatency,
+            stats.decoder_latency_limiter_bps,
+        ),
+        (
+            BindingConstraint::EncoderLatency,
+            stats.encoder_latency_limiter_bps,
+        ),
+        (BindingConstraint::ManualMax, stats.manual_max_bps),
+    ]
+    .into_iter()
+    .filter_map(|(constraint, limit)| Some((constraint, limit?.as_bps())))
+    .filter(|&(_, limit_bps)| limit_bps < raw_bps)
+    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    .map(|(constraint, _)| constraint)
+    .unwrap_or_default()
+}
+
+// Current values of the internal averages and counters, for diagnostics
+#[derive(Serialize, Clone, Debug)]
+pub struct BitrateDiagnostics {
+    pub frame_interval: Duration,
+    pub frame_interarrival_s: f32,
+    pub rtt: Duration,
+    pub encoder_latency: Duration,
+    pub network_latency: Duration,
+    pub decoder_queue_latency: Duration,
+    pub bitrate_average_bps: f32,
+    pub content_fill_ratio: f32,
+    pub peak_throughput_average_bps: f32,
+    pub peak_goodput_average_bps: f32,
+    pub duplicate_ratio: f32,
+    pub packet_loss_ratio: f32,
+    pub dynamic_max_bitrate_bps: f32,
+    pub network_latency_ceiling_bps: Option<f32>,
+    pub last_target_bitrate: Bitrate,
+    pub frame_interval_outlier_count: usize,
+    pub decoder_latency_overstep_count: usize,
+    // Framerate computed at the last update, before and after the maximum framerate step
+    pub raw_framerate: Option<f32>,
+    pub emitted_framerate: Option<f32>,
+}
+
+type BitrateChangeCallback = Box<dyn FnMut(f32, &NominalBitrateStats) + Send>;
 
 pub struct BitrateManager {
     nominal_frame_interval: Duration,
     frame_interval_average: SlidingWindowAverage<Duration>,
+    frame_interval_variance: IncrementalVariance,
+    frame_interval_outlier_count: usize,
     // note: why packet_sizes_bits_history is a queue and not a sliding average? Because some
     // network samples will be dropped but not any packet size sample
     packet_sizes_bits_history: VecDeque<(Duration, usize)>,
     encoder_latency_average: SlidingWindowAverage<Duration>,
     network_latency_average: SlidingWindowAverage<Duration>,
+    decoder_queue_latency_average: SlidingWindowAverage<Duration>,
     bitrate_average: SlidingWindowAverage<f32>,
+    // Ratio between the encoded frame size and the size requested by the target bitrate. Low values
+    // mean the bitrate is content-limited (simple scene) rather than network-limited
+    content_fill_average: SlidingWindowAverage<f32>,
     decoder_latency_overstep_count: usize,
+    // Last frame above the decoder latency threshold, or last raise of the cap
+    last_decoder_latency_overstep_instant: Instant,
     last_frame_instant: Instant,
     last_update_instant: Instant,
+    creation_instant: Instant,
     dynamic_max_bitrate: f32,
+    network_latency_ceiling: Option<f32>,
     previous_config: Option<BitrateConfig>,
     update_needed: bool,
 
-    last_target_bitrate: f32,
+    last_target_bitrate: Bitrate,
+    // Start of the current dip of the decided bitrate below the QoE floor
+    below_qoe_floor_since: Option<Instant>,
+    // Last decisions of the blended controllers, each one steps from its own
+    blend_adaptive_bps: Option<f32>,
+    blend_heuristic_bps: Option<f32>,
+    // Set on the first congestion signal or at the end of the cold start duration
+    cold_start_ended: bool,
+    // Bitrate sent to the encoder, differs from last_target_bitrate while the slew rate limiter is
+    // catching up
+    last_emitted_bitrate: Option<Bitrate>,
+    last_raw_framerate: Option<f32>,
+    last_emitted_framerate: Option<f32>,
 
     frame_interarrival_avg: f32,
 
@@ -39,17 +374,83 @@ pub struct BitrateManager {
 
     heur_stats: HeuristicStats,
     peak_throughput_average: SlidingWindowAverage<f32>, 
+    peak_throughput_max: SlidingWindowMax<f32>,
+    peak_goodput_average: SlidingWindowAverage<f32>,
+    peak_goodput_max: SlidingWindowMax<f32>,
+    // Estimators used by the capacity limit of the simple heuristic, rebuilt when
+    // capacity_estimation changes unless a custom estimator was set
+    throughput_capacity_estimator: Box<dyn CapacityEstimator>,
+    goodput_capacity_estimator: Box<dyn CapacityEstimator>,
+    capacity_estimator_kind: Option<PeakThroughputEstimator>,
+    custom_capacity_estimator: bool,
+    // Samples actually received, the windows start filled with their initialization value
+    peak_throughput_sample_count: usize,
+    peak_goodput_sample_count: usize,
+    // (capacity, expiry instant)
+    external_capacity: Option<(f32, Instant)>,
+    // Backoff of the simple heuristic increases clamped by the capacity limit. It ends early when
+    // the capacity estimate rises above the one that clamped the last increase
+    capacity_binding_streak: u32,
+    capacity_backoff_updates: u32,
+    capacity_backoff_capacity_bps: f32,
+    duplicate_ratio_average: SlidingWindowAverage<f32>,
+    last_throughput_sample_instant: Instant,
+    packet_loss_average: SlidingWindowAverage<f32>,
+    packet_size_bits: f32,
+    // Packets between consecutive loss events, most recent first, and packets since the last one
+    loss_intervals: VecDeque<u64>,
+    packets_since_loss_event: u64,
+
+    // Used by the heuristic. Seeded to make experiments reproducible
+    rng: StdRng,
+
+    // Timestamped RTT samples used to track the minimum RTT over a time window
+    rtt_history: VecDeque<(Instant, Duration)>,
+    max_history_size: usize,
+    last_probe_instant: Instant,
+
+    on_bitrate_change: Option<BitrateChangeCallback>,
+
+    sink: Arc<dyn StatsSink>,
+    clock: Arc<dyn Clock>,
 
     // last_random_prob_heuristic: f32,
 }
 impl BitrateManager {
-    pub fn new(max_history_size: usize, initial_framerate: f32) -> Self {
+    // When seed is None, the RNG is seeded from entropy. A history size of zero is raised to 1 and
+    // the initial framerate is clamped to 1..1000 Hz, NaN counts as 1 Hz
+    pub fn new(max_history_size: usize, initial_framerate: f32, seed: Option<u64>) -> Self {
+        Self::with_clock(
+            max_history_size,
+            initial_framerate,
+            seed,
+            Arc::new(SystemClock),
+        )
+    }
+
+    pub fn with_clock(
+        max_history_size: usize,
+        initial_framerate: f32,
+        seed: Option<u64>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let now = clock.now();
+
+        let max_history_size = max_history_size.max(1);
+        let initial_framerate = if initial_framerate.is_nan() {
+            MIN_INITIAL_FRAMERATE
+        } else {
+            initial_framerate.clamp(MIN_INITIAL_FRAMERATE, MAX_INITIAL_FRAMERATE)
+        };
+
         Self {
             nominal_frame_interval: Duration::from_secs_f32(1. / initial_framerate),
             frame_interval_average: SlidingWindowAverage::new(
                 Duration::from_millis(16),
                 max_history_size,
             ),
+            frame_interval_variance: IncrementalVariance::new(0.016, max_history_size),
+            frame_interval_outlier_count: 0,
             packet_sizes_bits_history: VecDeque::new(),
             encoder_latency_average: SlidingWindowAverage::new(
                 Duration::from_millis(5),
@@ -59,15 +460,30 @@ impl BitrateManager {
                 Duration::from_millis(5),
                 max_history_size,
             ),
+            decoder_queue_latency_average: SlidingWindowAverage::new(
+                Duration::ZERO,
+                max_history_size,
+            ),
             bitrate_average: SlidingWindowAverage::new(30_000_000.0, max_history_size),
+            content_fill_average: SlidingWindowAverage::new(1.0, max_history_size),
             decoder_latency_overstep_count: 0,
-            last_frame_instant: Instant::now(),
-            last_update_instant: Instant::now(),
+            last_decoder_latency_overstep_instant: now,
+            last_frame_instant: now,
+            last_update_instant: now,
+            creation_instant: now,
             dynamic_max_bitrate: f32::MAX,
+            network_latency_ceiling: None,
             previous_config: None,
             update_needed: true,
 
-            last_target_bitrate: 30_000_000.0,
+            last_target_bitrate: Bitrate::from_mbps(30.0),
+            below_qoe_floor_since: None,
+            blend_adaptive_bps: None,
+            blend_heuristic_bps: None,
+            cold_start_ended: false,
+            last_emitted_bitrate: None,
+            last_raw_framerate: None,
+            last_emitted_framerate: None,
 
             frame_interarrival_avg: 0.011,
             rtt_average: SlidingWindowAverage::new(Duration::from_millis(5), max_history_size),
@@ -76,31 +492,123 @@ impl BitrateManager {
                 ..Default::default()
             },
             peak_throughput_average: SlidingWindowAverage::new(300E6, max_history_size), 
-
+            peak_throughput_max: SlidingWindowMax::new(300E6, max_history_size),
+            peak_goodput_average: SlidingWindowAverage::new(300E6, max_history_size),
+            peak_goodput_max: SlidingWindowMax::new(300E6, max_history_size),
+            throughput_capacity_estimator: Box::new(SlidingWindowAverage::new(
+                300E6,
+                max_history_size,
+            )),
+            goodput_capacity_estimator: Box::new(SlidingWindowAverage::new(
+                300E6,
+                max_history_size,
+            )),
+            capacity_estimator_kind: None,
+            custom_capacity_estimator: false,
+            peak_throughput_sample_count: 0,
+            peak_goodput_sample_count: 0,
+            external_capacity: None,
+            capacity_binding_streak: 0,
+            capacity_backoff_updates: 0,
+            capacity_backoff_capacity_bps: 0.0,
+            duplicate_ratio_average: SlidingWindowAverage::new(0.0, max_history_size),
+            last_throughput_sample_instant: now,
+            packet_loss_average: SlidingWindowAverage::new(0.0, max_history_size),
+            packet_size_bits: (DEFAULT_PACKET_SIZE * 8) as f32,
+            loss_intervals: VecDeque::new(),
+            packets_since_loss_event: 0,
+
+            rng: seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+
+            rtt_history: VecDeque::new(),
+            max_history_size,
+            last_probe_instant: now,
+
+            on_bitrate_change: None,
+
+            sink: Arc::new(EventsSink),
+            clock,
         }
     }
 
     // Note: This is used to calculate the framerate/frame interval. The frame present is the most
     // accurate event for this use.
-    pub fn report_frame_present(&mut self, config: &Switch<BitrateAdaptiveFramerateConfig>) {
-        let now = Instant::now();
+    pub fn report_frame_present(
+        &mut self,
+        config: &Switch<BitrateAdaptiveFramerateConfig>,
+        idle_reset: &Switch<IdleResetConfig>,
+    ) {
+        let now = self.clock.now();
 
         let interval = now - self.last_frame_instant;
         self.last_frame_instant = now;
 
+        if let Switch::Enabled(idle_reset) = idle_reset {
+            // The gap is a pause, not a frame interval. Resume without a framerate reset
+            if interval.as_secs_f32() > idle_reset.timeout_s {
+                if idle_reset.reset_frame_interval_average {
+                    // Keep only the most recent samples before the pause
+                    self.frame_interval_average.retain(5);
+                    self.frame_interval_outlier_count = 0;
+                }
+
+                return;
+            }
+        }
+
+        self.report_frame_interval(interval, config);
+    }
+
+    fn report_frame_interval(
+        &mut self,
+        interval: Duration,
+        config: &Switch<BitrateAdaptiveFramerateConfig>,
+    ) {
         self.frame_interval_average.submit_sample(interval);
 
-        if let Some(config) = config.as_option() {
-            let interval_ratio =
-                interval.as_secs_f32() / self.frame_interval_average.get_average().as_secs_f32();
+        let Some(config) = config.as_option() else {
+            return;
+        };
 
-            if interval_ratio > config.framerate_reset_threshold_multiplier
-                || interval_ratio < 1.0 / config.framerate_reset_threshold_multiplier
-            {
-                // Clear most of the samples, keep some for stability
-                self.frame_interval_average.retain(5);
-                self.update_needed = true;
+        let interval_s = interval.as_secs_f32();
+
+        let reset_needed = match &config.sustained_outlier_detection {
+            Switch::Enabled(outlier_config) => {
+                let mean = self.frame_interval_variance.mean;
+                let tolerance = outlier_config.std_multiplier
+                    * f32::max(
+                        self.frame_interval_variance.get_std(),
+                        mean * MIN_FRAME_INTERVAL_RELATIVE_STD,
+                    );
+
+                // A single hiccup does not trigger a reset, only a sustained framerate change
+                if (interval_s - mean).abs() > tolerance {
+                    self.frame_interval_outlier_count += 1;
+                } else {
+                    self.frame_interval_outlier_count = 0;
+                }
+
+                // Outliers are clamped so they cannot inflate the variance and hide themselves
+                self.frame_interval_variance
+                    .submit_sample(interval_s.clamp(mean - tolerance, mean + tolerance));
+
+                self.frame_interval_outlier_count >= outlier_config.consecutive_frames
+            }
+            Switch::Disabled => {
+                let interval_ratio =
+                    interval_s / self.frame_interval_average.get_average().as_secs_f32();
+
+                interval_ratio > config.framerate_reset_threshold_multiplier
+                    || interval_ratio < 1.0 / config.framerate_reset_threshold_multiplier
             }
+        };
+
+        if reset_needed {
+            // Clear most of the samples, keep some for stability
+            self.frame_interval_average.retain(5);
+            self.frame_interval_outlier_count = 0;
+            self.frame_interval_variance.mean = interval_s;
+            self.update_needed = true;
         }
     }
 
@@ -112,6 +620,13 @@ impl BitrateManager {
     ) {
         self.encoder_latency_average.submit_sample(encoder_latency);
 
+        let requested_frame_bits = self.last_target_bitrate.as_bps()
+            * self.frame_interval_average.get_average().as_secs_f32();
+        if requested_frame_bits > 0.0 {
+            self.content_fill_average
+                .submit_sample((size_bytes * 8) as f32 / requested_frame_bits);
+        }
+
         self.packet_sizes_bits_history
             .push_back((timestamp, size_bytes * 8));
     }
@@ -119,13 +634,142 @@ impl BitrateManager {
     // decoder_latency is used to learn a suitable maximum bitrate bound to avoid decoder runaway
     // latency
 
-    pub fn report_network_rtt(&mut self, network_rtt: Duration, peak_throughput: f32) -> HeuristicStats {
+    // duplicate_ratio is the number of duplicated shards over the number of received shards
+    pub fn report_network_rtt(
+        &mut self,
+        network_rtt: Duration,
+        peak_throughput: f32,
+        duplicate_ratio: f32,
+    ) -> HeuristicStats {
         self.rtt_average.submit_sample(network_rtt);
         self.peak_throughput_average.submit_sample(peak_throughput); 
+        self.peak_throughput_max.submit_sample(peak_throughput);
+        self.throughput_capacity_estimator.submit(peak_throughput);
+        self.peak_throughput_sample_count += 1;
+        self.duplicate_ratio_average.submit_sample(duplicate_ratio);
+        let now = self.clock.now();
+        self.last_throughput_sample_instant = now;
+
+        // A zero RTT means the sent instant of the frame was not found
+        if !network_rtt.is_zero() {
+            self.rtt_history.push_back((now, network_rtt));
+            if self.rtt_history.len() > self.max_history_size {
+                self.rtt_history.pop_front();
+            }
+        }
+
+        self.heur_stats.capacity_headroom_bps = self.capacity_headroom_bps();
 
         return self.heur_stats.clone();
     }
 
+    // The larger of the RTT and peak throughput coefficients of variation
+    fn network_volatility(&self) -> f32 {
+        let rtt_samples = self
+            .rtt_average
+            .samples()
+            .map(|rtt| rtt.as_secs_f32())
+            .collect::<Vec<_>>();
+        let throughput_samples = self
+            .peak_throughput_average
+            .samples()
+            .copied()
+            .collect::<Vec<_>>();
+
+        f32::max(
+            coefficient_of_variation(&rtt_samples),
+            coefficient_of_variation(&throughput_samples),
+        )
+    }
+
+    fn base_update_interval(&self, config: &Switch<AdaptiveUpdateIntervalConfig>) -> Duration {
+        if let Switch::Enabled(config) = config {
+            adaptive_update_interval(config, self.network_volatility())
+        } else {
+            UPDATE_INTERVAL
+        }
+    }
+
+    // Peak throughput of the unique video payload, see statistics::peak_goodput_bps()
+    pub fn report_peak_goodput(&mut self, peak_goodput: f32) {
+        self.peak_goodput_average.submit_sample(peak_goodput);
+        self.peak_goodput_max.submit_sample(peak_goodput);
+        self.goodput_capacity_estimator.submit(peak_goodput);
+        self.peak_goodput_sample_count += 1;
+    }
+
+    // Replaces the capacity estimators of the simple heuristic, for throughput and goodput. The
+    // capacity_estimation setting is ignored from now on
+    #[allow(dead_code)]
+    pub fn set_capacity_estimator(
+        &mut self,
+        new_estimator: impl Fn() -> Box<dyn CapacityEstimator>,
+    ) {
+        self.throughput_capacity_estimator = new_estimator();
+        self.goodput_capacity_estimator = new_estimator();
+        self.capacity_estimator_kind = None;
+        self.custom_capacity_estimator = true;
+    }
+
+    fn select_capacity_estimator(&mut self, kind: PeakThroughputEstimator) {
+        if self.custom_capacity_estimator || self.capacity_estimator_kind == Some(kind) {
+            return;
+        }
+
+        self.throughput_capacity_estimator = capacity::new_capacity_estimator(
+            kind,
+            &self.peak_throughput_average,
+            &self.peak_throughput_max,
+            self.max_history_size,
+        );
+        self.goodput_capacity_estimator = capacity::new_capacity_estimator(
+            kind,
+            &self.peak_goodput_average,
+            &self.peak_goodput_max,
+            self.max_history_size,
+        );
+        self.capacity_estimator_kind = Some(kind);
+    }
+
+    // The injected capacity takes precedence over the internal estimate of the heuristic until
+    // valid_for has elapsed
+    #[allow(dead_code)]
+    pub fn set_external_capacity(&mut self, bps: f32, valid_for: Duration) {
+        self.external_capacity = Some((bps, self.clock.now() + valid_for));
+    }
+
+    fn valid_external_capacity(&self, now: Instant) -> Option<f32> {
+        self.external_capacity
+            .and_then(|(bps, expiry)| (now < expiry).then_some(bps))
+    }
+
+    fn effective_capacity(
+        &self,
+        raw_bps: f32,
+        config: &Switch<CapacityStalenessConfig>,
+        now: Instant,
+    ) -> f32 {
+        if let Switch::Enabled(config) = config {
+            let age = now.saturating_duration_since(self.last_throughput_sample_instant);
+            decayed_capacity(config, raw_bps, age)
+        } else {
+            raw_bps
+        }
+    }
+
+    fn min_rtt(&mut self, window: Duration) -> Option<Duration> {
+        let now = self.clock.now();
+        while let Some(&(instant, _)) = self.rtt_history.front() {
+            if now.saturating_duration_since(instant) > window {
+                self.rtt_history.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        self.rtt_history.iter().map(|(_, rtt)| *rtt).min()
+    }
+
     pub fn report_frame_latencies(
         &mut self,
         config: &BitrateMode,
@@ -135,7 +779,11 @@ impl BitrateManager {
 
         frame_interarrival_avg: f32,
     ) {
+        // The decoder latency is measured by the client independently of the network latency and
+        // is still valid without a network latency sample
         if network_latency.is_zero() {
+            self.report_decoder_latency(config, decoder_latency);
+
             return;
         }
         self.frame_interarrival_avg = frame_interarrival_avg;
@@ -155,12 +803,43 @@ impl BitrateManager {
             }
         }
 
-        if let BitrateMode::Adaptive {
+        self.report_decoder_latency(config, decoder_latency);
+    }
+
+    // Already called by report_frame_latencies(), do not report the same frame twice
+    pub fn report_decoder_latency(&mut self, config: &BitrateMode, decoder_latency: Duration) {
+        let adaptive_config = match config {
+            BitrateMode::Adaptive(adaptive_config)
+            | BitrateMode::Blend {
+                adaptive_config, ..
+            } => Some(adaptive_config),
+            _ => None,
+        };
+        if let Some(AdaptiveBitrateConfig {
             decoder_latency_limiter: Switch::Enabled(config),
             ..
-        } = &config
+        }) = adaptive_config
         {
-            if decoder_latency > Duration::from_millis(config.max_decoder_latency_ms) {
+            let panic_needed = matches!(
+                config.panic_decoder_latency_ms,
+                Switch::Enabled(panic_ms) if decoder_latency > Duration::from_millis(panic_ms)
+            );
+
+            let now = self.clock.now();
+
+            if panic_needed {
+                self.last_decoder_latency_overstep_instant = now;
+
+                // A decode stall is not the time to wait for consecutive oversteps
+                self.dynamic_max_bitrate =
+                    f32::min(self.bitrate_average.get_average(), self.dynamic_max_bitrate) * 0.5;
+
+                self.update_needed = true;
+
+                self.decoder_latency_overstep_count = 0;
+            } else if decoder_latency > Duration::from_millis(config.max_decoder_latency_ms) {
+                self.last_decoder_latency_overstep_instant = now;
+
                 self.decoder_latency_overstep_count += 1;
 
                 if self.decoder_latency_overstep_count == config.latency_overstep_frames {
@@ -174,254 +853,2428 @@ impl BitrateManager {
                 }
             } else {
                 self.decoder_latency_overstep_count = 0;
+
+                if let Switch::Enabled(recovery) = &config.recovery {
+                    if self.dynamic_max_bitrate < f32::MAX
+                        && now
+                            >= self.last_decoder_latency_overstep_instant
+                                + Duration::from_secs_f32(recovery.interval_s)
+                    {
+                        self.dynamic_max_bitrate =
+                            f32::min(self.dynamic_max_bitrate * recovery.multiplier, f32::MAX);
+
+                        self.update_needed = true;
+
+                        self.last_decoder_latency_overstep_instant = now;
+                    }
+                }
             }
         }
     }
 
-    pub fn report_heuristic_event(&mut self, heur: HeuristicStats) {
-        alvr_events::send_event(EventType::HeuristicStats(heur.clone()));
+    // The decoder queue latency is the analog of the playback buffer in buffer-based adaptation
+    pub fn report_decoder_queue_latency(&mut self, decoder_queue_latency: Duration) {
+        self.decoder_queue_latency_average
+            .submit_sample(decoder_queue_latency);
     }
 
-    pub fn get_encoder_params(
-        &mut self,
-        config: &BitrateConfig,
-    ) -> (FfiDynamicEncoderParams, Option<NominalBitrateStats>) {
-        let now = Instant::now();
+    // loss_ratio is the fraction of shards lost since the previous report
+    pub fn report_packet_loss(&mut self, loss_ratio: f32) {
+        self.packet_loss_average.submit_sample(loss_ratio);
+    }
 
-        if let BitrateMode::SimpleHeuristic {
-            update_interval_heuristic,
-            ..
-        } = &config.mode
-        {
-            if let Switch::Enabled(time_update) = update_interval_heuristic {
-                self.update_interval_setting = Duration::from_secs_f32(*time_update);
-            } else {
-                self.update_interval_setting = UPDATE_INTERVAL;
-            }
-        } else {
-            self.update_interval_setting = UPDATE_INTERVAL;
+    // Shards received and lost since the previous report. A report with any loss counts as one loss
+    // event
+    pub fn report_shard_counters(&mut self, shards_received: usize, shards_lost: usize) {
+        self.packets_since_loss_event += (shards_received + shards_lost) as u64;
+
+        if shards_lost > 0 {
+            self.loss_intervals
+                .push_front(self.packets_since_loss_event);
+            self.loss_intervals
+                .truncate(TFRC_LOSS_INTERVAL_WEIGHTS.len());
+            self.packets_since_loss_event = 0;
         }
+    }
 
-        if self
-            .previous_config
-            .as_ref()
-            .map(|prev| config != prev)
-            .unwrap_or(true)
-        {
-            self.previous_config = Some(config.clone());
-            // Continue method. Always update bitrate in this case
-        } else if !self.update_needed
-            && (now < (self.last_update_instant + self.update_interval_setting)
-                || matches!(config.mode, BitrateMode::ConstantMbps(_)))
-        {
-            return (
-                FfiDynamicEncoderParams {
-                    updated: 0,
-                    bitrate_bps: 0,
-                    framerate: 0.0,
-                },
-                None,
-            );
-        }
+    // Used by the packet loss limiter. Should match the packet size of the stream socket
+    pub fn set_packet_size(&mut self, packet_size: usize) {
+        self.packet_size_bits = (packet_size * 8) as f32;
+    }
 
-        self.last_update_instant = now;
-        self.update_needed = false;
+    #[allow(dead_code)]
+    pub fn set_sink(&mut self, sink: Arc<dyn StatsSink>) {
+        self.sink = sink;
+    }
 
-        let mut stats = NominalBitrateStats::default();
+    // The callback receives the new target bitrate in bps. It is called synchronously by
+    // get_encoder_params only when the target changes
+    #[allow(dead_code)]
+    pub fn set_on_bitrate_change(
+        &mut self,
+        callback: impl FnMut(f32, &NominalBitrateStats) + Send + 'static,
+    ) {
+        self.on_bitrate_change = Some(Box::new(callback));
+    }
 
-        let bitrate_bps = match &config.mode {
-            BitrateMode::ConstantMbps(bitrate_mbps) => *bitrate_mbps as f32 * 1e6,
-            BitrateMode::SimpleHeuristic {
-                max_bitrate_mbps,
-                min_bitrate_mbps,
-                steps_mbps,
-                threshold_random_uniform,
-                multiplier_rtt_threshold,
-                fps_threshold_multiplier,
-                ..
-            } => {
+    #[allow(dead_code)]
+    pub fn current_target_bitrate(&self) -> Bitrate {
+        self.last_target_bitrate
+    }
 
-                fn round_down_to_nearest_multiple(value: f32, step: f32) -> f32 {
-                    (value / step).floor() * step
-                }
+    #[allow(dead_code)]
+    pub fn diagnostics(&self) -> BitrateDiagnostics {
+        BitrateDiagnostics {
+            frame_interval: self.frame_interval_average.get_average(),
+            frame_interarrival_s: self.frame_interarrival_avg,
+            rtt: self.rtt_average.get_average(),
+            encoder_latency: self.encoder_latency_average.get_average(),
+            network_latency: self.network_latency_average.get_average(),
+            decoder_queue_latency: self.decoder_queue_latency_average.get_average(),
+            bitrate_average_bps: self.bitrate_average.get_average(),
+            content_fill_ratio: self.content_fill_average.get_average(),
+            peak_throughput_average_bps: self.peak_throughput_average.get_average(),
+            peak_goodput_average_bps: self.peak_goodput_average.get_average(),
+            duplicate_ratio: self.duplicate_ratio_average.get_average(),
+            packet_loss_ratio: self.packet_loss_average.get_average(),
+            dynamic_max_bitrate_bps: self.dynamic_max_bitrate,
+            network_latency_ceiling_bps: self.network_latency_ceiling,
+            last_target_bitrate: self.last_target_bitrate,
+            frame_interval_outlier_count: self.frame_interval_outlier_count,
+            decoder_latency_overstep_count: self.decoder_latency_overstep_count,
+            raw_framerate: self.last_raw_framerate,
+            emitted_framerate: self.last_emitted_framerate,
+        }
+    }
 
-                //define generator and sample from uniform dist. for heuristic
-                let mut rng = thread_rng();
-                let uniform_dist = Uniform::new(0.0, 1.0);
+    // Holds the bitrate at the floor while the dip is shorter than the grace period
+    fn apply_qoe_floor(
+        &mut self,
+        config: &Switch<QoeFloorConfig>,
+        bitrate_bps: f32,
+        now: Instant,
+    ) -> (f32, bool) {
+        let Switch::Enabled(config) = config else {
+            self.below_qoe_floor_since = None;
+            return (bitrate_bps, false);
+        };
 
+        let floor_bps = Bitrate::from_mbps(config.floor_mbps).as_bps();
+        if bitrate_bps >= floor_bps {
+            self.below_qoe_floor_since = None;
+            return (bitrate_bps, false);
+        }
 
+        let since = *self.below_qoe_floor_since.get_or_insert(now);
+        if now.saturating_duration_since(since) < Duration::from_secs_f32(config.grace_period_s) {
+            (floor_bps, true)
+        } else {
+            (bitrate_bps, false)
+        }
+    }
 
-                fn minmax_bitrate(
-                    bitrate_bps: f32,
-                    max_bitrate_mbps: &Switch<f32>,
-                    min_bitrate_mbps: &Switch<f32>,
-                ) -> f32 {
-                    // local function to just minmax after every change from heuristic to avoid blot code
-                    let mut bitrate = bitrate_bps;
-                    if let Switch::Enabled(max) = max_bitrate_mbps {
-                        let max = *max as f32 * 1e6;
-                        bitrate = f32::min(bitrate, max);
-                    }
-                    if let Switch::Enabled(min) = min_bitrate_mbps {
-                        let min = *min as f32 * 1e6;
-                        bitrate = f32::max(bitrate, min);
-                    }
-                    bitrate
-                }
-                let initial_bitrate = self.last_target_bitrate;
-                let mut bitrate_bps: f32 = initial_bitrate;
-
-                let frame_interval = self.frame_interval_average.get_average();
-                let server_fps = 1.0 / frame_interval.as_secs_f32().min(1.0);
-                let rtt_avg_heur = self.rtt_average.get_average().as_secs_f32();
-                let fps_heur = 1.0 / self.frame_interarrival_avg;
-                let random_prob = rng.sample(uniform_dist);
-
-                let capacity_estimation_peak = self.peak_throughput_average.get_average(); 
-
-                
-
-                if let Switch::Enabled(rtt_threshold_mult) = *multiplier_rtt_threshold {
-                    if let Switch::Enabled(threshold_u) = *threshold_random_uniform {
-                        if let Switch::Enabled(steps) = *steps_mbps {
-                            if let Switch::Enabled(fps_mult) = *fps_threshold_multiplier {
-                                let steps_bps = steps * 1E6;
-
-                                // Calculate thresholds
-                                let threshold_fps = fps_mult * server_fps;
-                                let threshold_rtt =
-                                    frame_interval.as_secs_f32() * rtt_threshold_mult;
-
-                                if fps_heur >= threshold_fps {
-                                    if rtt_avg_heur > threshold_rtt {
-                                        if random_prob >= threshold_u {
-                                            bitrate_bps -= steps_bps; // decrease bitrate by 1 step
-                                        }
-                                    } else {
-                                        if random_prob <= threshold_u {
-                                            bitrate_bps += steps_bps; // increase bitrate by 1 step
-                                        }
-                                    }
-                                } else {
-                                    bitrate_bps -= steps_bps; // decrease bitrate by 1 step
-                                }
+    // FEC overhead, as a fraction of the bitrate, suggested to the encoder for the recent shard loss
+    fn fec_overhead(&self, config: &Switch<FecOverheadConfig>) -> f32 {
+        let Switch::Enabled(config) = config else {
+            return 0.0;
+        };
 
-                                // Ensure bitrate is within allowed range
-                                bitrate_bps =
-                                    minmax_bitrate(bitrate_bps, max_bitrate_mbps, min_bitrate_mbps);
-                                
-                                let limit = 0.9 * capacity_estimation_peak;
-                                if capacity_estimation_peak <= 100E6 {
-                                    bitrate_bps = round_down_to_nearest_multiple(f32::min(bitrate_bps, limit), steps_bps); // Make sure that we're under the capacity estimation's limit and in a step
-                                }
+        (self.packet_loss_average.get_average() * config.loss_multiplier)
+            .clamp(0.0, config.max_overhead)
+    }
 
-                                // bitrate_bps = f32::min(bitrate_bps, 0.9 * capacity_estimation_peak); // Make sure that we're under the capacity estimation's limit
-                                // Update heuristic stats
-                                let heur_stats = HeuristicStats {
-                                    frame_interval_s: frame_interval.as_secs_f32(),
-                                    server_fps: server_fps,
-                                    steps_bps: steps_bps,
-
-                                    network_heur_fps: fps_heur,
-                                    rtt_avg_heur_s: rtt_avg_heur,
-                                    random_prob: random_prob,
-
-                                    threshold_fps: threshold_fps,
-                                    threshold_rtt_s: threshold_rtt,
-                                    threshold_u: threshold_u,
-
-                                    requested_bitrate_bps: bitrate_bps,
-                                };
-                                // warn!("Heuristic Stats reported:  {:?}", heur_stats);
-                                self.heur_stats = heur_stats.clone();
-                            }
-                        }
-                    }
-                }
+    // Capacity estimate left unused by the current target bitrate
+    pub fn capacity_headroom_bps(&self) -> f32 {
+        f32::max(
+            self.peak_throughput_average.get_average() - self.last_target_bitrate.as_bps(),
+            0.0,
+        )
+    }
 
-                self.last_target_bitrate = bitrate_bps;
-                if let Switch::Enabled(max) = max_bitrate_mbps {
-                    let maxi = *max as f32 * 1e6;
-                    stats.manual_max_bps = Some(maxi);
-                }
-                if let Switch::Enabled(min) = min_bitrate_mbps {
-                    let mini = *min as f32 * 1e6;
-                    stats.manual_min_bps = Some(mini);
-                }
-                bitrate_bps
-            }
-            BitrateMode::Adaptive {
-                saturation_multiplier,
-                max_bitrate_mbps,
-                min_bitrate_mbps,
-                max_network_latency_ms,
-                encoder_latency_limiter,
-                ..
-            } => {
-                let initial_bitrate_average_bps = self.bitrate_average.get_average();
-                // let initial_bitrate_average_bps = self.last_target_bitrate;
+    pub fn report_heuristic_event(&mut self, heur: HeuristicStats) {
+        self.sink.emit(EventType::HeuristicStats(heur.clone()));
+    }
 
-                let mut bitrate_bps = initial_bitrate_average_bps * saturation_multiplier;
-                stats.scaled_calculated_bps = Some(bitrate_bps);
+    // Steps from previous_bps, the last bitrate decided by this controller
+    fn simple_heuristic_bitrate(
+        &mut self,
+        config: &SimpleHeuristicConfig,
+        previous_bps: f32,
+        stats: &mut NominalBitrateStats,
+        now: Instant,
+    ) -> f32 {
+        let SimpleHeuristicConfig {
+            max_bitrate_mbps,
+            min_bitrate_mbps,
+            steps_mbps,
+            threshold_random_uniform,
+            threshold_annealing,
+            multiplier_rtt_threshold,
+            fps_threshold_multiplier,
+            capacity_estimation,
+            capacity_from_goodput,
+            capacity_staleness,
+            max_duplicate_ratio,
+            capacity_warmup_samples,
+            capacity_binding_backoff,
+            ..
+        } = config;
 
-                bitrate_bps = f32::min(bitrate_bps, self.dynamic_max_bitrate);
-                stats.decoder_latency_limiter_bps = Some(self.dynamic_max_bitrate);
+        fn round_down_to_nearest_multiple(value: f32, step: f32) -> f32 {
+            (value / step).floor() * step
+        }
 
-                if let Switch::Enabled(max_ms) = max_network_latency_ms {
-                    let max = initial_bitrate_average_bps * (*max_ms as f32 / 1000.0)
-                        / self.network_latency_average.get_average().as_secs_f32();
-                    bitrate_bps = f32::min(bitrate_bps, max);
+        //define generator and sample from uniform dist. for heuristic
+        let uniform_dist = Uniform::new(0.0, 1.0);
 
-                    stats.network_latency_limiter_bps = Some(max);
-                }
 
-                if let Switch::Enabled(config) = encoder_latency_limiter {
-                    let saturation = self.encoder_latency_average.get_average().as_secs_f32()
-                        / self.nominal_frame_interval.as_secs_f32();
-                    let max =
-                        initial_bitrate_average_bps * config.max_saturation_multiplier / saturation;
-                    stats.encoder_latency_limiter_bps = Some(max);
-
-                    if saturation > config.max_saturation_multiplier {
-                        // Note: this assumes linear relationship between bitrate and encoder
-                        // latency but this may not be the case
-                        bitrate_bps = f32::min(bitrate_bps, max);
+
+        fn minmax_bitrate(
+            bitrate_bps: f32,
+            max_bitrate_mbps: &Switch<f32>,
+            min_bitrate_mbps: &Switch<f32>,
+        ) -> f32 {
+            // local function to just minmax after every change from heuristic to avoid blot code
+            let mut bitrate = bitrate_bps;
+            if let Switch::Enabled(max) = max_bitrate_mbps {
+                let max = Bitrate::from_mbps(*max).as_bps();
+                bitrate = f32::min(bitrate, max);
+            }
+            if let Switch::Enabled(min) = min_bitrate_mbps {
+                let min = Bitrate::from_mbps(*min).as_bps();
+                bitrate = f32::max(bitrate, min);
+            }
+            bitrate
+        }
+        let initial_bitrate = previous_bps;
+        let mut bitrate_bps: f32 = initial_bitrate;
+
+        let frame_interval = self.frame_interval_average.get_average();
+        let server_fps = 1.0 / frame_interval.as_secs_f32().min(1.0);
+        let rtt_avg_heur = self.rtt_average.get_average().as_secs_f32();
+        let fps_heur = 1.0 / self.frame_interarrival_avg;
+        let random_prob = self.rng.sample(uniform_dist);
+        let duplicate_ratio = self.duplicate_ratio_average.get_average();
+        // A high duplicate ratio means the sender is over-transmitting or the network is
+        // reordering packets
+        let duplicates_exceeded = matches!(
+            max_duplicate_ratio,
+            Switch::Enabled(max) if duplicate_ratio > *max
+        );
+
+        self.select_capacity_estimator(*capacity_estimation);
+        let (capacity_estimator, capacity_sample_count) = if *capacity_from_goodput {
+            (
+                &self.goodput_capacity_estimator,
+                self.peak_goodput_sample_count,
+            )
+        } else {
+            (
+                &self.throughput_capacity_estimator,
+                self.peak_throughput_sample_count,
+            )
+        };
+        let capacity_estimation_raw = capacity_estimator.estimate();
+        let external_capacity = self.valid_external_capacity(now);
+        let capacity_estimation_peak = external_capacity.unwrap_or_else(|| {
+            self.effective_capacity(capacity_estimation_raw, capacity_staleness, now)
+        });
+        // Before enough samples the estimate is mostly the initialization value of the window
+        let capacity_unknown = external_capacity.is_none()
+            && matches!(
+                capacity_warmup_samples,
+                Switch::Enabled(min) if capacity_sample_count < *min
+            );
+
+        
+
+        if let Switch::Enabled(rtt_threshold_mult) = *multiplier_rtt_threshold {
+            if let Switch::Enabled(threshold_u) = *threshold_random_uniform {
+                let threshold_u = if let Switch::Enabled(annealing) = threshold_annealing {
+                    annealed_threshold(annealing, now - self.creation_instant)
+                } else {
+                    threshold_u
+                };
+
+                if let Switch::Enabled(steps) = *steps_mbps {
+                    if let Switch::Enabled(fps_mult) = *fps_threshold_multiplier {
+                        let steps_bps = Bitrate::from_mbps(steps).as_bps();
+
+                        // Calculate thresholds
+                        let threshold_fps = fps_mult * server_fps;
+                        let threshold_rtt = frame_interval.as_secs_f32() * rtt_threshold_mult;
+
+                        let mut increase_suppressed = false;
+                        let mut increase_attempted = false;
+
+                        if capacity_estimation_peak > self.capacity_backoff_capacity_bps {
+                            self.capacity_binding_streak = 0;
+                            self.capacity_backoff_updates = 0;
+                        }
+                        let backing_off = self.capacity_backoff_updates > 0;
+                        if backing_off {
+                            self.capacity_backoff_updates -= 1;
+                        }
+
+                        if fps_heur >= threshold_fps {
+                            if rtt_avg_heur > threshold_rtt {
+                                if random_prob >= threshold_u {
+                                    bitrate_bps -= steps_bps; // decrease bitrate by 1 step
+                                }
+                            } else {
+                                if random_prob <= threshold_u {
+                                    if duplicates_exceeded || backing_off {
+                                        increase_suppressed = true;
+                                    } else {
+                                        bitrate_bps += steps_bps; // increase bitrate by 1 step
+                                        increase_attempted = true;
+                                    }
+                                }
+                            }
+                        } else {
+                            bitrate_bps -= steps_bps; // decrease bitrate by 1 step
+                        }
+                        stats.scaled_calculated_bps = Some(Bitrate::from_bps(bitrate_bps));
+                        stats.heuristic_step_bps = Some(bitrate_bps - initial_bitrate);
+
+                        // Ensure bitrate is within allowed range
+                        bitrate_bps =
+                            minmax_bitrate(bitrate_bps, max_bitrate_mbps, min_bitrate_mbps);
+                        
+                        let limit = 0.9 * capacity_estimation_peak;
+                        if capacity_unknown {
+                            stats.capacity_limiter_bps = Some(Bitrate::from_bps(initial_bitrate));
+                            bitrate_bps = f32::min(bitrate_bps, initial_bitrate);
+                        } else if capacity_estimation_peak <= Bitrate::from_mbps(100.0).as_bps() {
+                            stats.capacity_limiter_bps = Some(Bitrate::from_bps(limit));
+
+                            // Each further clamped increase doubles the number of skipped updates
+                            if let Switch::Enabled(max_backoff) = capacity_binding_backoff {
+                                if increase_attempted && bitrate_bps > limit {
+                                    self.capacity_binding_streak += 1;
+                                    self.capacity_backoff_updates = u32::min(
+                                        (1_u32 << self.capacity_binding_streak.min(31)) - 1,
+                                        *max_backoff,
+                                    );
+                                    self.capacity_backoff_capacity_bps = capacity_estimation_peak;
+                                } else if increase_attempted {
+                                    self.capacity_binding_streak = 0;
+                                }
+                            }
+
+                            bitrate_bps = round_down_to_nearest_multiple(f32::min(bitrate_bps, limit), steps_bps); // Make sure that we're under the capacity estimation's limit and in a step
+                        }
+
+                        // bitrate_bps = f32::min(bitrate_bps, 0.9 * capacity_estimation_peak); // Make sure that we're under the capacity estimation's limit
+                        // Update heuristic stats
+                        let heur_stats = HeuristicStats {
+                            frame_interval_s: frame_interval.as_secs_f32(),
+                            server_fps: server_fps,
+                            steps_bps: steps_bps,
+
+                            network_heur_fps: fps_heur,
+                            rtt_avg_heur_s: rtt_avg_heur,
+                            random_prob: random_prob,
+
+                            threshold_fps: threshold_fps,
+                            threshold_rtt_s: threshold_rtt,
+                            threshold_u: threshold_u,
+
+                            duplicate_ratio,
+                            increase_suppressed,
+
+                            effective_capacity_bps: capacity_estimation_peak,
+                            external_capacity_used: external_capacity.is_some(),
+                            capacity_headroom_bps: f32::max(
+                                self.peak_throughput_average.get_average() - bitrate_bps,
+                                0.0,
+                            ),
+                            capacity_binding_streak: self.capacity_binding_streak,
+                            capacity_backoff_updates: self.capacity_backoff_updates,
+
+                            requested_bitrate_bps: bitrate_bps,
+                        };
+                        // warn!("Heuristic Stats reported:  {:?}", heur_stats);
+                        self.heur_stats = heur_stats.clone();
                     }
                 }
+            }
+        }
+
+        if let Switch::Enabled(max) = max_bitrate_mbps {
+            stats.manual_max_bps = Some(Bitrate::from_mbps(*max));
+        }
+        if let Switch::Enabled(min) = min_bitrate_mbps {
+            stats.manual_min_bps = Some(Bitrate::from_mbps(*min));
+        }
+        bitrate_bps
+    }
+
+    // previous_bps is the last bitrate decided by this controller, kept for content-limited scenes.
+    // frame_interval follows the framerate adaptation setting
+    fn adaptive_bitrate(
+        &mut self,
+        config: &AdaptiveBitrateConfig,
+        previous_bps: f32,
+        frame_interval: Duration,
+        stats: &mut NominalBitrateStats,
+    ) -> f32 {
+        let AdaptiveBitrateConfig {
+            saturation_multiplier,
+            max_bitrate_mbps,
+            min_bitrate_mbps,
+            max_network_latency_ms,
+            network_latency_limiter_max_drop,
+            encoder_latency_limiter,
+            packet_loss_limiter,
+            content_limited_fill_ratio,
+            ..
+        } = config;
+
+        let initial_bitrate_average_bps = self.bitrate_average.get_average();
+        // let initial_bitrate_average_bps = self.last_target_bitrate;
+
+        let mut bitrate_bps = initial_bitrate_average_bps * saturation_multiplier;
+        stats.scaled_calculated_bps = Some(Bitrate::from_bps(bitrate_bps));
+
+        let content_fill_ratio = self.content_fill_average.get_average();
+        stats.content_fill_ratio = Some(content_fill_ratio);
+
+        // Small frames lower the measured bitrate even if the network is not under
+        // pressure. The limiters below still apply
+        if let Switch::Enabled(fill_ratio) = content_limited_fill_ratio {
+            if content_fill_ratio < *fill_ratio {
+                bitrate_bps = f32::max(bitrate_bps, previous_bps);
+            }
+        }
+
+        bitrate_bps = f32::min(bitrate_bps, self.dynamic_max_bitrate);
+        stats.decoder_latency_limiter_bps = Some(Bitrate::from_bps(self.dynamic_max_bitrate));
+
+        if let Switch::Enabled(max_ms) = max_network_latency_ms {
+            let mut max = initial_bitrate_average_bps * (*max_ms as f32 / 1000.0)
+                / self.network_latency_average.get_average().as_secs_f32();
+
+            // The ceiling can rise freely but it can only drop by a bounded fraction
+            if let (Switch::Enabled(max_drop), Some(ceiling)) = (
+                network_latency_limiter_max_drop,
+                self.network_latency_ceiling,
+            ) {
+                max = f32::max(max, ceiling * (1.0 - max_drop));
+            }
+            self.network_latency_ceiling = Some(max);
+
+            bitrate_bps = f32::min(bitrate_bps, max);
+
+            stats.network_latency_limiter_bps = Some(Bitrate::from_bps(max));
+        } else {
+            self.network_latency_ceiling = None;
+        }
+
+        // TCP-friendly rate (Mathis et al.). With no recent loss there is no ceiling
+        if let Switch::Enabled(config) = packet_loss_limiter {
+            let loss_ratio = self.packet_loss_average.get_average();
+            if loss_ratio > 0.0 {
+                let rtt = self
+                    .rtt_average
+                    .get_average()
+                    .max(MIN_PACKET_LOSS_LIMITER_RTT)
+                    .as_secs_f32();
+                let max = config.tcp_friendly_constant * self.packet_size_bits
+                    / (rtt * loss_ratio.sqrt());
+                bitrate_bps = f32::min(bitrate_bps, max);
+
+                stats.packet_loss_limiter_bps = Some(Bitrate::from_bps(max));
+            }
+        }
+
+        if let Switch::Enabled(config) = encoder_latency_limiter {
+            let encoder_latency = if let Switch::Enabled(percentile) = config.latency_percentile {
+                self.encoder_latency_average.get_percentile(percentile)
+            } else {
+                self.encoder_latency_average.get_average()
+            };
+            let reference_interval = match config.reference_interval {
+                EncoderLatencyReferenceInterval::Automatic => frame_interval,
+                EncoderLatencyReferenceInterval::Nominal => self.nominal_frame_interval,
+                EncoderLatencyReferenceInterval::Measured => {
+                    self.frame_interval_average.get_average()
+                }
+            };
+            let saturation = encoder_latency.as_secs_f32() / reference_interval.as_secs_f32();
+            let max = initial_bitrate_average_bps * config.max_saturation_multiplier / saturation;
+            stats.encoder_latency_limiter_bps = Some(Bitrate::from_bps(max));
+
+            if saturation > config.max_saturation_multiplier {
+                // Note: this assumes linear relationship between bitrate and encoder
+                // latency but this may not be the case
+                bitrate_bps = f32::min(bitrate_bps, max);
+            }
+        }
+
+        if let Switch::Enabled(max) = max_bitrate_mbps {
+            let max = Bitrate::from_mbps(*max as f32);
+            bitrate_bps = f32::min(bitrate_bps, max.as_bps());
+
+            stats.manual_max_bps = Some(max);
+        }
+        if let Switch::Enabled(min) = min_bitrate_mbps {
+            let min = Bitrate::from_mbps(*min as f32);
+            bitrate_bps = f32::max(bitrate_bps, min.as_bps());
+
+            stats.manual_min_bps = Some(min);
+        }
+
+        bitrate_bps
+    }
+
+    // Slow start: the averages still hold their initialization values, so the bitrate grows
+    // multiplicatively from the previous target instead. Returns None once the phase is over
+    fn cold_start_bitrate(
+        &mut self,
+        config: &AdaptiveBitrateConfig,
+        previous_bps: f32,
+        now: Instant,
+    ) -> Option<f32> {
+        let Switch::Enabled(cold_start) = &config.cold_start else {
+            return None;
+        };
+        if self.cold_start_ended {
+            return None;
+        }
+
+        let latency_exceeded = matches!(
+            config.max_network_latency_ms,
+            Switch::Enabled(max_ms)
+                if self.network_latency_average.get_average() > Duration::from_millis(max_ms)
+        );
+        let congestion = self.packet_loss_average.get_average() > 0.0 || latency_exceeded;
+        let expired = now.saturating_duration_since(self.creation_instant)
+            >= Duration::from_secs_f32(cold_start.duration_s);
+        if congestion || expired {
+            self.cold_start_ended = true;
+
+            return None;
+        }
+
+        let mut bitrate_bps = f32::min(
+            previous_bps * cold_start.growth_factor,
+            self.dynamic_max_bitrate,
+        );
+        if let Switch::Enabled(max) = config.max_bitrate_mbps {
+            bitrate_bps = f32::min(bitrate_bps, Bitrate::from_mbps(max as f32).as_bps());
+        }
+        if let Switch::Enabled(min) = config.min_bitrate_mbps {
+            bitrate_bps = f32::max(bitrate_bps, Bitrate::from_mbps(min as f32).as_bps());
+        }
+
+        Some(bitrate_bps)
+    }
+
+    pub fn get_encoder_params(
+        &mut self,
+        config: &BitrateConfig,
+    ) -> (FfiDynamicEncoderParams, Option<NominalBitrateStats>) {
+        let now = self.clock.now();
+
+        if let BitrateMode::SimpleHeuristic(SimpleHeuristicConfig {
+            update_interval_heuristic,
+            ..
+        }) = &config.mode
+        {
+            if let Switch::Enabled(time_update) = update_interval_heuristic {
+                self.update_interval_setting = Duration::from_secs_f32(*time_update);
+            } else {
+                self.update_interval_setting =
+                    self.base_update_interval(&config.adaptive_update_interval);
+            }
+        } else {
+            self.update_interval_setting =
+                self.base_update_interval(&config.adaptive_update_interval);
+        }
+
+        if self
+            .previous_config
+            .as_ref()
+            .map(|prev| config != prev)
+            .unwrap_or(true)
+        {
+            self.previous_config = Some(config.clone());
+            // Continue method. Always update bitrate in this case
+        } else if !self.update_needed
+            && (now < (self.last_update_instant + self.update_interval_setting)
+                || (matches!(config.mode, BitrateMode::ConstantMbps(_))
+                    && self.last_emitted_bitrate == Some(self.last_target_bitrate)))
+        {
+            return (
+                FfiDynamicEncoderParams {
+                    updated: 0,
+                    bitrate_bps: 0,
+                    bitrate_bps_left: 0,
+                    bitrate_bps_right: 0,
+                    framerate: 0.0,
+                    fec_overhead: 0.0,
+                },
+                None,
+            );
+        }
+
+        let elapsed_since_update = now.saturating_duration_since(self.last_update_instant);
+        self.last_update_instant = now;
+        self.update_needed = false;
+
+        let mut stats = NominalBitrateStats {
+            update_interval_s: self.update_interval_setting.as_secs_f32(),
+            ..Default::default()
+        };
+
+        let previous_bitrate = self.last_target_bitrate;
+
+        let frame_interval = if config.adapt_to_framerate.enabled() {
+            self.frame_interval_average.get_average()
+        } else {
+            self.nominal_frame_interval
+        };
+
+        let bitrate_bps = match &config.mode {
+            BitrateMode::ConstantMbps(bitrate_mbps) => {
+                Bitrate::from_mbps(*bitrate_mbps as f32).as_bps()
+            }
+            BitrateMode::SimpleHeuristic(heuristic_config) => self.simple_heuristic_bitrate(
+                heuristic_config,
+                previous_bitrate.as_bps(),
+                &mut stats,
+                now,
+            ),
+            BitrateMode::Adaptive(adaptive_config) => {
+                // The controller still runs during the cold start to keep its limiter state
+                let bitrate_bps = self.adaptive_bitrate(
+                    adaptive_config,
+                    previous_bitrate.as_bps(),
+                    frame_interval,
+                    &mut stats,
+                );
+
+                self.cold_start_bitrate(adaptive_config, previous_bitrate.as_bps(), now)
+                    .unwrap_or(bitrate_bps)
+            }
+            BitrateMode::Aimd {
+                increase_mbps,
+                decrease_factor,
+                rtt_inflation_threshold,
+                loss_threshold,
+                max_bitrate_mbps,
+                min_bitrate_mbps,
+            } => {
+                let min_bps = Bitrate::from_mbps(*min_bitrate_mbps as f32).as_bps();
+                let max_bps = f32::max(
+                    Bitrate::from_mbps(*max_bitrate_mbps as f32).as_bps(),
+                    min_bps,
+                );
+
+                let rtt_threshold = self
+                    .frame_interval_average
+                    .get_average()
+                    .mul_f32(*rtt_inflation_threshold);
+                let congestion = self.rtt_average.get_average() > rtt_threshold
+                    || self.packet_loss_average.get_average() > *loss_threshold;
+                stats.aimd_congestion = Some(congestion);
+
+                let bitrate_bps = if congestion {
+                    previous_bitrate.as_bps() * decrease_factor
+                } else {
+                    previous_bitrate.as_bps() + Bitrate::from_mbps(*increase_mbps).as_bps()
+                };
+                stats.scaled_calculated_bps = Some(Bitrate::from_bps(bitrate_bps));
+                stats.manual_max_bps = Some(Bitrate::from_bps(max_bps));
+                stats.manual_min_bps = Some(Bitrate::from_bps(min_bps));
+
+                bitrate_bps.clamp(min_bps, max_bps)
+            }
+            BitrateMode::Blend {
+                weight,
+                adaptive_config,
+                heuristic_config,
+            } => {
+                // Each controller continues from its own previous decision, not from the blend.
+                // Their limiter statistics would overlap, only the two decisions are reported
+                let previous_bps = previous_bitrate.as_bps();
+                let adaptive_bps = self.adaptive_bitrate(
+                    adaptive_config,
+                    self.blend_adaptive_bps.unwrap_or(previous_bps),
+                    frame_interval,
+                    &mut NominalBitrateStats::default(),
+                );
+                let heuristic_bps = self.simple_heuristic_bitrate(
+                    heuristic_config,
+                    self.blend_heuristic_bps.unwrap_or(previous_bps),
+                    &mut NominalBitrateStats::default(),
+                    now,
+                );
+                self.blend_adaptive_bps = Some(adaptive_bps);
+                self.blend_heuristic_bps = Some(heuristic_bps);
+
+                stats.blend_adaptive_bps = Some(Bitrate::from_bps(adaptive_bps));
+                stats.blend_heuristic_bps = Some(Bitrate::from_bps(heuristic_bps));
+
+                let weight = weight.clamp(0.0, 1.0);
+                weight * adaptive_bps + (1.0 - weight) * heuristic_bps
+            }
+            BitrateMode::BufferBased {
+                reservoir_ms,
+                cushion_ms,
+                max_bitrate_mbps,
+                min_bitrate_mbps,
+            } => {
+                let min = Bitrate::from_mbps(*min_bitrate_mbps as f32).as_bps();
+                let max = Bitrate::from_mbps(*max_bitrate_mbps as f32).as_bps();
+
+                let reservoir = *reservoir_ms as f32 / 1000.0;
+                let cushion = *cushion_ms as f32 / 1000.0;
+                let queue = self
+                    .decoder_queue_latency_average
+                    .get_average()
+                    .as_secs_f32();
+
+                // Piecewise-linear map: min below the reservoir, max above the cushion
+                let bitrate_bps = if queue <= reservoir {
+                    min
+                } else if queue >= cushion {
+                    max
+                } else {
+                    min + (max - min) * (queue - reservoir) / (cushion - reservoir)
+                };
+
+                stats.buffer_based_bps = Some(Bitrate::from_bps(bitrate_bps));
+                stats.manual_max_bps = Some(Bitrate::from_bps(max));
+                stats.manual_min_bps = Some(Bitrate::from_bps(min));
+
+                bitrate_bps
+            }
+            BitrateMode::Bbr {
+                probe_interval_s,
+                probe_gain,
+                min_rtt_window_s,
+                max_rtt_inflation,
+                max_bitrate_mbps,
+                min_bitrate_mbps,
+            } => {
+                let bottleneck_bandwidth_bps = self.peak_throughput_average.get_average();
+                stats.bottleneck_bandwidth_bps = Some(Bitrate::from_bps(bottleneck_bandwidth_bps));
+
+                let rtt = self.rtt_average.get_average().as_secs_f32();
+                let min_rtt = self
+                    .min_rtt(Duration::from_secs_f32(*min_rtt_window_s))
+                    .map(|rtt| rtt.as_secs_f32())
+                    .unwrap_or(rtt);
+
+                let probe_due =
+                    now >= self.last_probe_instant + Duration::from_secs_f32(*probe_interval_s);
+
+                let mut bitrate_bps = bottleneck_bandwidth_bps;
+                let mut probing = false;
+                if rtt > min_rtt * *max_rtt_inflation {
+                    // A queue is building up, reduce the bitrate to drain it
+                    bitrate_bps *= min_rtt / rtt;
+                } else if probe_due {
+                    bitrate_bps *= *probe_gain;
+                    probing = true;
+
+                    self.last_probe_instant = now;
+                }
+                stats.bbr_probing = Some(probing);
 
                 if let Switch::Enabled(max) = max_bitrate_mbps {
-                    let max = *max as f32 * 1e6;
-                    bitrate_bps = f32::min(bitrate_bps, max);
+                    let max = Bitrate::from_mbps(*max as f32);
+                    bitrate_bps = f32::min(bitrate_bps, max.as_bps());
 
                     stats.manual_max_bps = Some(max);
                 }
                 if let Switch::Enabled(min) = min_bitrate_mbps {
-                    let min = *min as f32 * 1e6;
-                    bitrate_bps = f32::max(bitrate_bps, min);
+                    let min = Bitrate::from_mbps(*min as f32);
+                    bitrate_bps = f32::max(bitrate_bps, min.as_bps());
 
                     stats.manual_min_bps = Some(min);
                 }
 
                 bitrate_bps
             }
+            BitrateMode::Mpc {
+                horizon,
+                candidate_steps,
+                candidate_step_mbps,
+                qoe_weights,
+                max_bitrate_mbps,
+                min_bitrate_mbps,
+            } => {
+                let min_mbps = *min_bitrate_mbps as f32;
+                let max_mbps = f32::max(*max_bitrate_mbps as f32, min_mbps);
+
+                let capacity_bps = self.peak_throughput_average.get_average();
+                stats.bottleneck_bandwidth_bps = Some(Bitrate::from_bps(capacity_bps));
+
+                let model = MpcModel {
+                    capacity_mbps: f32::max(
+                        Bitrate::from_bps(capacity_bps).as_mbps(),
+                        f32::EPSILON,
+                    ),
+                    step_s: self.update_interval_setting.as_secs_f32(),
+                    weights: qoe_weights,
+                };
+                let current_mbps = previous_bitrate.as_mbps();
+                let candidates = mpc_candidates(
+                    current_mbps,
+                    *candidate_steps,
+                    *candidate_step_mbps,
+                    min_mbps,
+                    max_mbps,
+                );
+                let queue_s = self
+                    .decoder_queue_latency_average
+                    .get_average()
+                    .as_secs_f32();
+
+                stats.manual_max_bps = Some(Bitrate::from_mbps(max_mbps));
+                stats.manual_min_bps = Some(Bitrate::from_mbps(min_mbps));
+
+                Bitrate::from_mbps(model.plan(&candidates, *horizon, queue_s, current_mbps))
+                    .as_bps()
+            }
+            BitrateMode::Tfrc {
+                max_bitrate_mbps,
+                min_bitrate_mbps,
+            } => {
+                let min_bps = Bitrate::from_mbps(*min_bitrate_mbps as f32).as_bps();
+                let max_bps = f32::max(
+                    Bitrate::from_mbps(*max_bitrate_mbps as f32).as_bps(),
+                    min_bps,
+                );
+
+                stats.manual_max_bps = Some(Bitrate::from_bps(max_bps));
+                stats.manual_min_bps = Some(Bitrate::from_bps(min_bps));
+
+                // The equation has no bound before the first loss event
+                if let Some(loss_interval) =
+                    average_loss_interval(&self.loss_intervals, self.packets_since_loss_event)
+                {
+                    let rtt = self
+                        .rtt_average
+                        .get_average()
+                        .max(MIN_PACKET_LOSS_LIMITER_RTT)
+                        .as_secs_f32();
+                    let rate_bps = tfrc_rate_bps(self.packet_size_bits, rtt, 1.0 / loss_interval);
+
+                    stats.tfrc_rate_bps = Some(Bitrate::from_bps(rate_bps));
+                    stats.loss_event_interval = Some(loss_interval);
+
+                    rate_bps.clamp(min_bps, max_bps)
+                } else {
+                    max_bps
+                }
+            }
         };
 
-        stats.requested_bps = bitrate_bps;
+        let (bitrate_bps, floor_held) = self.apply_qoe_floor(&config.qoe_floor, bitrate_bps, now);
+        if floor_held {
+            stats.qoe_floor_bps = Some(Bitrate::from_bps(bitrate_bps));
+        }
 
-        let frame_interval = if config.adapt_to_framerate.enabled() {
-            self.frame_interval_average.get_average()
-        } else {
-            self.nominal_frame_interval
+        let bitrate = Bitrate::from_bps(bitrate_bps);
+        stats.requested_bps = bitrate;
+        stats.binding_constraint = binding_constraint(&stats);
+
+        // The slew rate applies to the output of every mode. The decided bitrate is still the one
+        // reported in the statistics, the binding constraint is updated before it is logged
+        let emitted_bitrate = match (&config.max_slew_rate_mbps_per_s, self.last_emitted_bitrate) {
+            (Switch::Enabled(slew_rate), Some(last_emitted)) => {
+                let max_change =
+                    Bitrate::from_mbps(slew_rate * elapsed_since_update.as_secs_f32()).as_bps();
+                Bitrate::from_bps(bitrate.as_bps().clamp(
+                    last_emitted.as_bps() - max_change,
+                    last_emitted.as_bps() + max_change,
+                ))
+            }
+            _ => bitrate,
         };
-        self.last_target_bitrate = bitrate_bps;
+        self.last_emitted_bitrate = Some(emitted_bitrate);
+        if emitted_bitrate != bitrate {
+            stats.binding_constraint = BindingConstraint::Slew;
+        }
+
+        self.last_target_bitrate = bitrate;
+        stats.capacity_headroom_bps = self.capacity_headroom_bps();
+
+        if config.decision_log {
+            let decision = BitrateDecision {
+                mode: mode_name(&config.mode),
+                rtt_ms: self.rtt_average.get_average().as_secs_f32() * 1000.0,
+                server_fps: 1.0 / self.frame_interval_average.get_average().as_secs_f32(),
+                capacity_bps: self.peak_throughput_average.get_average(),
+                loss_ratio: self.packet_loss_average.get_average(),
+                raw_bps: raw_decision_bps(&stats),
+                requested_bps: bitrate.as_bps(),
+                binding_constraint: stats.binding_constraint,
+                nominal: &stats,
+                heuristic: matches!(
+                    config.mode,
+                    BitrateMode::SimpleHeuristic(_) | BitrateMode::Blend { .. }
+                )
+                .then_some(&self.heur_stats),
+            };
+            self.sink.emit(EventType::Log(LogEntry {
+                severity: LogSeverity::Info,
+                content: serde_json::to_string(&decision).unwrap(),
+            }));
+        }
+
+        if bitrate != previous_bitrate {
+            if let Some(callback) = &mut self.on_bitrate_change {
+                callback(bitrate.as_bps(), &stats);
+            }
+        }
+
+        let raw_framerate = 1.0 / frame_interval.as_secs_f32().min(1.0);
+        let framerate = match (&config.adapt_to_framerate, self.last_emitted_framerate) {
+            (
+                Switch::Enabled(BitrateAdaptiveFramerateConfig {
+                    max_framerate_step: Switch::Enabled(max_step),
+                    ..
+                }),
+                Some(last_emitted),
+            ) => raw_framerate.clamp(last_emitted - max_step, last_emitted + max_step),
+            _ => raw_framerate,
+        };
+        self.last_raw_framerate = Some(raw_framerate);
+        self.last_emitted_framerate = Some(framerate);
+
+        // The split is applied after all limits so that the two eyes add up to the target
+        let bitrate_bps = emitted_bitrate.as_bps() as u64;
+        let bitrate_bps_left =
+            (bitrate_bps as f64 * config.left_eye_bitrate_ratio.clamp(0.0, 1.0) as f64) as u64;
 
         (
             FfiDynamicEncoderParams {
                 updated: 1,
-                bitrate_bps: bitrate_bps as u64,
-                framerate: 1.0 / frame_interval.as_secs_f32().min(1.0),
+                bitrate_bps,
+                bitrate_bps_left,
+                bitrate_bps_right: bitrate_bps - bitrate_bps_left,
+                framerate,
+                fec_overhead: self.fec_overhead(&config.fec_overhead),
             },
             Some(stats),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::tests::MockClock, stats_sink::tests::CapturingSink};
+    use alvr_session::{
+        ColdStartConfig, DecoderLatencyLimiter, DecoderLatencyRecoveryConfig,
+        EncoderLatencyLimiter, PacketLossLimiter, SustainedOutlierConfig,
+    };
+
+    pub fn bitrate_config(mode: BitrateMode) -> BitrateConfig {
+        BitrateConfig {
+            mode,
+            adapt_to_framerate: Switch::Disabled,
+            history_size: 1,
+            image_corruption_fix: false,
+            left_eye_bitrate_ratio: 0.5,
+            max_slew_rate_mbps_per_s: Switch::Disabled,
+            adaptive_update_interval: Switch::Disabled,
+            idle_reset: Switch::Disabled,
+            qoe_floor: Switch::Disabled,
+            fec_overhead: Switch::Disabled,
+            decision_log: false,
+        }
+    }
+
+    // Configured so that, with the default statistics, the heuristic always increases the bitrate
+    pub fn simple_heuristic_mode() -> BitrateMode {
+        BitrateMode::SimpleHeuristic(simple_heuristic_config())
+    }
+
+    fn simple_heuristic_config() -> SimpleHeuristicConfig {
+        SimpleHeuristicConfig {
+            max_bitrate_mbps: Switch::Enabled(100.0),
+            min_bitrate_mbps: Switch::Enabled(10.0),
+            steps_mbps: Switch::Enabled(10.0),
+            threshold_random_uniform: Switch::Enabled(1.0),
+            threshold_annealing: Switch::Disabled,
+            update_interval_heuristic: Switch::Enabled(1.0),
+            multiplier_rtt_threshold: Switch::Enabled(2.0),
+            fps_threshold_multiplier: Switch::Enabled(0.95),
+            capacity_estimation: PeakThroughputEstimator::Average,
+            capacity_from_goodput: false,
+            capacity_staleness: Switch::Disabled,
+            max_duplicate_ratio: Switch::Enabled(0.1),
+            capacity_warmup_samples: Switch::Disabled,
+            capacity_binding_backoff: Switch::Disabled,
+        }
+    }
+
+    fn adaptive_mode() -> BitrateMode {
+        BitrateMode::Adaptive(adaptive_config())
+    }
+
+    fn adaptive_config() -> AdaptiveBitrateConfig {
+        AdaptiveBitrateConfig {
+            saturation_multiplier: 0.95,
+            max_bitrate_mbps: Switch::Disabled,
+            min_bitrate_mbps: Switch::Disabled,
+            max_network_latency_ms: Switch::Disabled,
+            network_latency_limiter_max_drop: Switch::Disabled,
+            encoder_latency_limiter: Switch::Disabled,
+            packet_loss_limiter: Switch::Disabled,
+            decoder_latency_limiter: Switch::Disabled,
+            content_limited_fill_ratio: Switch::Enabled(0.5),
+            cold_start: Switch::Disabled,
+        }
+    }
+
+    #[test]
+    fn test_simple_scene_does_not_decrease_bitrate() {
+        let config = bitrate_config(adaptive_mode());
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        let requested = manager.current_target_bitrate();
+
+        // 1 KB frames are far below what 30 Mbps allows
+        let timestamp = Duration::from_millis(10);
+        manager.report_frame_encoded(timestamp, Duration::from_millis(2), 1000);
+        manager.report_frame_latencies(
+            &config.mode,
+            timestamp,
+            Duration::from_millis(5),
+            Duration::from_millis(2),
+            0.011,
+        );
+
+        let (params, stats) = manager.get_encoder_params(&config);
+        let stats = stats.unwrap();
+        assert!(stats.content_fill_ratio.unwrap() < 0.5);
+        assert!(stats.scaled_calculated_bps.unwrap() < requested);
+        assert_eq!(params.bitrate_bps, requested.as_bps() as u64);
+    }
+
+    #[test]
+    fn test_annealed_threshold_decays() {
+        let config = ThresholdAnnealingConfig {
+            start_value: 0.5,
+            end_value: 0.1,
+            decay_time_constant_s: 10.0,
+        };
+
+        assert!((annealed_threshold(&config, Duration::ZERO) - 0.5).abs() < 1e-6);
+
+        let mut previous = f32::MAX;
+        for secs in (0..120).step_by(5) {
+            let threshold = annealed_threshold(&config, Duration::from_secs(secs));
+            assert!(threshold < previous);
+            assert!(threshold >= config.end_value);
+            previous = threshold;
+        }
+
+        assert!((previous - config.end_value).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_seeded_random_prob_is_reproducible() {
+        let config = bitrate_config(simple_heuristic_mode());
+
+        let random_probs = || {
+            let mut manager = BitrateManager::new(1, 90.0, Some(1234));
+
+            (0..10)
+                .map(|_| {
+                    manager.update_needed = true;
+                    manager.get_encoder_params(&config);
+
+                    manager.heur_stats.random_prob
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(random_probs(), random_probs());
+    }
+
+    #[test]
+    fn test_duplicates_suppress_increase() {
+        let config = bitrate_config(simple_heuristic_mode());
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(5), 300E6, 0.0);
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 40_000_000);
+        assert!(!manager.heur_stats.increase_suppressed);
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(5), 300E6, 0.5);
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 30_000_000);
+        assert!(manager.heur_stats.increase_suppressed);
+    }
+
+    fn bbr_mode(probe_interval_s: f32) -> BitrateMode {
+        BitrateMode::Bbr {
+            probe_interval_s,
+            probe_gain: 1.25,
+            min_rtt_window_s: 10.0,
+            max_rtt_inflation: 2.0,
+            max_bitrate_mbps: Switch::Disabled,
+            min_bitrate_mbps: Switch::Disabled,
+        }
+    }
+
+    #[test]
+    fn test_bbr_steady_state_matches_bandwidth() {
+        let config = bitrate_config(bbr_mode(10.0));
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(5), 50e6, 0.0);
+
+        let (params, stats) = manager.get_encoder_params(&config);
+        let stats = stats.unwrap();
+        assert_eq!(stats.bbr_probing, Some(false));
+        assert_eq!(params.bitrate_bps, 50_000_000);
+    }
+
+    #[test]
+    fn test_bbr_probe_cycle() {
+        let config = bitrate_config(bbr_mode(0.05));
+
+        let clock = Arc::new(MockClock::default());
+        let mut manager = BitrateManager::with_clock(1, 90.0, None, Arc::clone(&clock) as _);
+        manager.report_network_rtt(Duration::from_millis(5), 50e6, 0.0);
+
+        let mut next_bitrate = || {
+            manager.update_needed = true;
+            let (params, stats) = manager.get_encoder_params(&config);
+
+            (params.bitrate_bps, stats.unwrap().bbr_probing.unwrap())
+        };
+
+        clock.advance(Duration::from_millis(49));
+        assert_eq!(next_bitrate(), (50_000_000, false));
+        clock.advance(Duration::from_millis(2));
+        assert_eq!(next_bitrate(), (62_500_000, true));
+        assert_eq!(next_bitrate(), (50_000_000, false));
+    }
+
+    #[test]
+    fn test_mpc_selects_highest_feasible_candidate() {
+        let config = bitrate_config(BitrateMode::Mpc {
+            horizon: 3,
+            candidate_steps: 2,
+            candidate_step_mbps: 5.0,
+            qoe_weights: QoeWeightsConfig {
+                bitrate: 1.0,
+                rebuffering: 1.0,
+                bitrate_switch: 0.5,
+            },
+            max_bitrate_mbps: 100,
+            min_bitrate_mbps: 10,
+        });
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(5), 50e6, 0.0);
+
+        let bitrates = (0..3)
+            .map(|_| {
+                manager.update_needed = true;
+                manager.get_encoder_params(&config).0.bitrate_bps
+            })
+            .collect::<Vec<_>>();
+
+        // Starting from 30 Mbps, the candidates are at most 10 Mbps away. Above the 50 Mbps
+        // capacity the predicted decoder queue outweighs the bitrate gain
+        assert_eq!(bitrates, [40_000_000, 50_000_000, 50_000_000]);
+    }
+
+    #[test]
+    fn test_adaptive_update_interval_follows_volatility() {
+        let mut config = bitrate_config(BitrateMode::ConstantMbps(50));
+        config.adaptive_update_interval = Switch::Enabled(AdaptiveUpdateIntervalConfig {
+            min_interval_s: 0.25,
+            max_interval_s: 2.0,
+            reference_volatility: 0.5,
+        });
+
+        let update_interval_s = |rtts_ms: [u64; 4], throughputs_bps: [f32; 4]| {
+            let mut manager = BitrateManager::new(4, 90.0, None);
+            for (rtt_ms, throughput_bps) in rtts_ms.into_iter().zip(throughputs_bps) {
+                manager.report_network_rtt(Duration::from_millis(rtt_ms), throughput_bps, 0.0);
+            }
+
+            let (_, stats) = manager.get_encoder_params(&config);
+            stats.unwrap().update_interval_s
+        };
+
+        let stable = update_interval_s([5; 4], [50e6; 4]);
+        assert!((stable - 2.0).abs() < 1e-6);
+
+        let volatile = update_interval_s([5, 50, 5, 50], [10e6, 100e6, 10e6, 100e6]);
+        assert!((volatile - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_update_interval_boundary_with_mock_clock() {
+        let config = bitrate_config(BitrateMode::BufferBased {
+            reservoir_ms: 2,
+            cushion_ms: 10,
+            max_bitrate_mbps: 100,
+            min_bitrate_mbps: 20,
+        });
+
+        let clock = Arc::new(MockClock::default());
+        let mut manager = BitrateManager::with_clock(1, 90.0, None, Arc::clone(&clock) as _);
+
+        let mut updated = || manager.get_encoder_params(&config).0.updated;
+
+        // The first call always updates because the config is new
+        assert_eq!(updated(), 1);
+        assert_eq!(updated(), 0);
+
+        clock.advance(Duration::from_millis(999));
+        assert_eq!(updated(), 0);
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(updated(), 1);
+        assert_eq!(updated(), 0);
+    }
+
+    #[test]
+    fn test_buffer_based_regions() {
+        let config = bitrate_config(BitrateMode::BufferBased {
+            reservoir_ms: 2,
+            cushion_ms: 10,
+            max_bitrate_mbps: 100,
+            min_bitrate_mbps: 20,
+        });
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+
+        let mut requested_bps = |queue_ms| {
+            manager.report_decoder_queue_latency(Duration::from_millis(queue_ms));
+            manager.update_needed = true;
+
+            let (params, stats) = manager.get_encoder_params(&config);
+            let stats = stats.unwrap();
+            assert_eq!(stats.buffer_based_bps, Some(stats.requested_bps));
+            assert_eq!(params.bitrate_bps, stats.requested_bps.as_bps() as u64);
+
+            stats.requested_bps.as_bps()
+        };
+
+        // Below the reservoir
+        assert_eq!(requested_bps(1), 20e6);
+        // Halfway through the ramp
+        assert!((requested_bps(6) - 60e6).abs() < 1e3);
+        // Above the cushion
+        assert_eq!(requested_bps(15), 100e6);
+    }
+
+    #[test]
+    fn test_constant_mbps_config_bitrate() {
+        let config = bitrate_config(BitrateMode::ConstantMbps(150));
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        let (params, stats) = manager.get_encoder_params(&config);
+
+        assert_eq!(params.bitrate_bps, 150_000_000);
+        assert_eq!(stats.unwrap().requested_bps, Bitrate::from_mbps(150.0));
+        assert_eq!(manager.current_target_bitrate().as_mbps(), 150.0);
+    }
+
+    #[test]
+    fn test_bitrate_change_callback() {
+        use std::sync::{Arc, Mutex};
+
+        let calls = Arc::new(Mutex::new(vec![]));
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.set_on_bitrate_change({
+            let calls = Arc::clone(&calls);
+            move |bitrate_bps, stats| {
+                assert_eq!(stats.requested_bps.as_bps(), bitrate_bps);
+                calls.lock().unwrap().push(bitrate_bps);
+            }
+        });
+
+        let config_50 = bitrate_config(BitrateMode::ConstantMbps(50));
+        let config_80 = bitrate_config(BitrateMode::ConstantMbps(80));
+
+        // Config change: 30 -> 50 Mbps
+        manager.get_encoder_params(&config_50);
+        // No-op ticks take the early return path
+        manager.get_encoder_params(&config_50);
+        manager.get_encoder_params(&config_50);
+        // Forced update with the same target
+        manager.update_needed = true;
+        let (params, _) = manager.get_encoder_params(&config_50);
+        assert_eq!(params.updated, 1);
+        // Config change: 50 -> 80 Mbps
+        manager.get_encoder_params(&config_80);
+
+        assert_eq!(*calls.lock().unwrap(), vec![50e6, 80e6]);
+    }
+
+    #[test]
+    fn test_network_latency_spike_drop_is_bounded() {
+        let mut config = bitrate_config(adaptive_mode());
+        if let BitrateMode::Adaptive(AdaptiveBitrateConfig {
+            max_network_latency_ms,
+            network_latency_limiter_max_drop,
+            ..
+        }) = &mut config.mode
+        {
+            *max_network_latency_ms = Switch::Enabled(4);
+            *network_latency_limiter_max_drop = Switch::Enabled(0.1);
+        }
+
+        let mut manager = BitrateManager::new(4, 90.0, None);
+
+        // 50 KB frames delivered in 5 ms: 80 Mbps
+        for i in 0..4 {
+            let timestamp = Duration::from_millis(i * 11);
+            manager.report_frame_encoded(timestamp, Duration::from_millis(2), 50_000);
+            manager.report_frame_latencies(
+                &config.mode,
+                timestamp,
+                Duration::from_millis(5),
+                Duration::from_millis(2),
+                0.011,
+            );
+        }
+        let (params, stats) = manager.get_encoder_params(&config);
+        let ceiling = stats.unwrap().network_latency_limiter_bps.unwrap().as_bps();
+        assert!((ceiling - 64e6).abs() < 1e3);
+        assert_eq!(params.bitrate_bps, ceiling as u64);
+
+        // A single latency spike, not associated with a frame size sample
+        manager.report_frame_latencies(
+            &config.mode,
+            Duration::from_secs(1),
+            Duration::from_millis(200),
+            Duration::from_millis(2),
+            0.011,
+        );
+        manager.update_needed = true;
+        let (params, stats) = manager.get_encoder_params(&config);
+
+        let bounded_ceiling = stats.unwrap().network_latency_limiter_bps.unwrap().as_bps();
+        assert!((bounded_ceiling - 0.9 * ceiling).abs() < 1e3);
+        assert_eq!(params.bitrate_bps, bounded_ceiling as u64);
+    }
+
+    #[test]
+    fn test_sustained_framerate_change_resets_frame_interval() {
+        let config = Switch::Enabled(BitrateAdaptiveFramerateConfig {
+            framerate_reset_threshold_multiplier: 2.0,
+            sustained_outlier_detection: Switch::Enabled(SustainedOutlierConfig {
+                std_multiplier: 3.0,
+                consecutive_frames: 10,
+            }),
+            max_framerate_step: Switch::Disabled,
+        });
+
+        let mut manager = BitrateManager::new(256, 90.0, None);
+
+        // Settle on 90 Hz with some jitter
+        for i in 0..1000 {
+            let jitter_us = if i % 2 == 0 { 300 } else { -300 };
+            let interval = Duration::from_micros((11_111 + jitter_us) as u64);
+            manager.report_frame_interval(interval, &config);
+        }
+        manager.update_needed = false;
+        let history_len = manager.frame_interval_average.history_buffer_len();
+
+        // A single hiccup
+        manager.report_frame_interval(Duration::from_millis(40), &config);
+        manager.report_frame_interval(Duration::from_micros(11_111), &config);
+        assert!(!manager.update_needed);
+        assert_eq!(
+            manager.frame_interval_average.history_buffer_len(),
+            history_len
+        );
+
+        // Sustained switch to 45 Hz
+        for _ in 0..10 {
+            manager.report_frame_interval(Duration::from_micros(22_222), &config);
+        }
+        assert!(manager.update_needed);
+        assert!(manager.frame_interval_average.history_buffer_len() <= 5);
+    }
+
+    #[test]
+    fn test_stale_capacity_estimate_decays() {
+        let config = Switch::Enabled(CapacityStalenessConfig {
+            staleness_window_s: 2.0,
+            decay_time_constant_s: 5.0,
+            floor_mbps: 10.0,
+        });
+
+        let mut manager = BitrateManager::new(4, 90.0, None);
+        for _ in 0..4 {
+            manager.report_network_rtt(Duration::from_millis(5), 80e6, 0.0);
+        }
+        let raw_bps = manager.peak_throughput_average.get_average();
+
+        // Instead of sleeping, evaluate the estimate at instants after the last sample
+        let last_sample = manager.last_throughput_sample_instant;
+        let fresh =
+            manager.effective_capacity(raw_bps, &config, last_sample + Duration::from_secs(1));
+        let stale =
+            manager.effective_capacity(raw_bps, &config, last_sample + Duration::from_secs(7));
+        let very_stale =
+            manager.effective_capacity(raw_bps, &config, last_sample + Duration::from_secs(600));
+
+        assert_eq!(manager.peak_throughput_average.get_average(), raw_bps);
+        assert_eq!(fresh, raw_bps);
+        assert!(stale < raw_bps);
+        assert!(stale > 10e6);
+        assert!((very_stale - 10e6).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_external_capacity_until_expiry() {
+        let config = bitrate_config(simple_heuristic_mode());
+
+        let clock = Arc::new(MockClock::default());
+        let mut manager = BitrateManager::with_clock(1, 90.0, Some(0), Arc::clone(&clock) as _);
+        manager.report_network_rtt(Duration::from_millis(5), 80e6, 0.0);
+        manager.set_external_capacity(20e6, Duration::from_secs(2));
+
+        // 90% of the injected 20 Mbps, rounded down to a 10 Mbps step
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 10_000_000);
+        assert!(manager.heur_stats.external_capacity_used);
+        assert_eq!(manager.heur_stats.effective_capacity_bps, 20e6);
+
+        // After expiry the internal 80 Mbps estimate lets the bitrate increase again
+        clock.advance(Duration::from_secs(3));
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 20_000_000);
+        assert!(!manager.heur_stats.external_capacity_used);
+        assert_eq!(manager.heur_stats.effective_capacity_bps, 80e6);
+    }
+
+    #[test]
+    fn test_framerate_step_is_limited() {
+        let mut config = bitrate_config(BitrateMode::ConstantMbps(30));
+        config.history_size = 8;
+        config.adapt_to_framerate = Switch::Enabled(BitrateAdaptiveFramerateConfig {
+            framerate_reset_threshold_multiplier: 2.0,
+            sustained_outlier_detection: Switch::Disabled,
+            max_framerate_step: Switch::Enabled(10.0),
+        });
+
+        let mut manager = BitrateManager::new(8, 90.0, None);
+        for _ in 0..8 {
+            manager
+                .frame_interval_average
+                .submit_sample(Duration::from_secs_f32(1.0 / 90.0));
+        }
+        let (params, _) = manager.get_encoder_params(&config);
+        assert!((params.framerate - 90.0).abs() < 0.01);
+
+        // The game drops to 45 Hz at once
+        for _ in 0..8 {
+            manager
+                .frame_interval_average
+                .submit_sample(Duration::from_secs_f32(1.0 / 45.0));
+        }
+        for expected_framerate in [80.0, 70.0, 60.0, 50.0, 45.0, 45.0] {
+            manager.update_needed = true;
+            let (params, _) = manager.get_encoder_params(&config);
+            assert!((params.framerate - expected_framerate).abs() < 0.01);
+
+            let diagnostics = manager.diagnostics();
+            assert!((diagnostics.raw_framerate.unwrap() - 45.0).abs() < 0.01);
+            assert_eq!(diagnostics.emitted_framerate, Some(params.framerate));
+        }
+    }
+
+    #[test]
+    fn test_idle_gap_does_not_reset_framerate() {
+        let config = Switch::Enabled(BitrateAdaptiveFramerateConfig {
+            framerate_reset_threshold_multiplier: 2.0,
+            sustained_outlier_detection: Switch::Disabled,
+            max_framerate_step: Switch::Disabled,
+        });
+        let idle_reset = Switch::Enabled(IdleResetConfig {
+            timeout_s: 1.0,
+            reset_frame_interval_average: true,
+        });
+
+        let clock = Arc::new(MockClock::default());
+        let mut manager = BitrateManager::with_clock(8, 90.0, None, Arc::clone(&clock) as _);
+        for _ in 0..20 {
+            clock.advance(Duration::from_millis(11));
+            manager.report_frame_present(&config, &idle_reset);
+        }
+        manager.update_needed = false;
+
+        // Headset set down for 10 seconds
+        clock.advance(Duration::from_secs(10));
+        manager.report_frame_present(&config, &idle_reset);
+        clock.advance(Duration::from_millis(11));
+        manager.report_frame_present(&config, &idle_reset);
+
+        assert!(!manager.update_needed);
+        assert_eq!(
+            manager.frame_interval_average.get_average(),
+            Duration::from_millis(11)
+        );
+    }
+
+    #[test]
+    fn test_heuristic_capacity_limit_is_recorded() {
+        let config = bitrate_config(simple_heuristic_mode());
+
+        let mut manager = BitrateManager::new(1, 90.0, Some(0));
+        manager.report_network_rtt(Duration::from_millis(5), 20e6, 0.0);
+
+        let (_, stats) = manager.get_encoder_params(&config);
+        let stats = stats.unwrap();
+
+        // The heuristic steps up from 30 to 40 Mbps, then the 90% of the 20 Mbps capacity binds
+        assert_eq!(stats.heuristic_step_bps, Some(10e6));
+        assert_eq!(stats.scaled_calculated_bps, Some(Bitrate::from_mbps(40.0)));
+        assert_eq!(stats.capacity_limiter_bps, Some(Bitrate::from_bps(18e6)));
+        assert_eq!(stats.manual_max_bps, Some(Bitrate::from_mbps(100.0)));
+        assert_eq!(stats.requested_bps, Bitrate::from_mbps(10.0));
+        assert_eq!(stats.binding_constraint, BindingConstraint::Capacity);
+    }
+
+    #[test]
+    fn test_binding_constraint_of_each_limit() {
+        let limit = |mbps| Some(Bitrate::from_mbps(mbps));
+        let constraint = |decision_mbps, limits: NominalBitrateStats| {
+            binding_constraint(&NominalBitrateStats {
+                scaled_calculated_bps: limit(decision_mbps),
+                manual_max_bps: limit(100.0),
+                manual_min_bps: limit(10.0),
+                ..limits
+            })
+        };
+
+        assert_eq!(
+            constraint(50.0, NominalBitrateStats::default()),
+            BindingConstraint::None
+        );
+        // Limits above the decision don't bind
+        assert_eq!(
+            constraint(
+                50.0,
+                NominalBitrateStats {
+                    capacity_limiter_bps: limit(60.0),
+                    ..Default::default()
+                }
+            ),
+            BindingConstraint::None
+        );
+        assert_eq!(
+            constraint(150.0, NominalBitrateStats::default()),
+            BindingConstraint::ManualMax
+        );
+        assert_eq!(
+            constraint(5.0, NominalBitrateStats::default()),
+            BindingConstraint::ManualMin
+        );
+
+        for (limits, expected) in [
+            (
+                NominalBitrateStats {
+                    capacity_limiter_bps: limit(40.0),
+                    ..Default::default()
+                },
+                BindingConstraint::Capacity,
+            ),
+            (
+                NominalBitrateStats {
+                    packet_loss_limiter_bps: limit(40.0),
+                    ..Default::default()
+                },
+                BindingConstraint::PacketLoss,
+            ),
+            (
+                NominalBitrateStats {
+                    network_latency_limiter_bps: limit(40.0),
+                    ..Default::default()
+                },
+                BindingConstraint::NetworkLatency,
+            ),
+            (
+                NominalBitrateStats {
+                    encoder_latency_limiter_bps: limit(40.0),
+                    ..Default::default()
+                },
+                BindingConstraint::EncoderLatency,
+            ),
+            (
+                NominalBitrateStats {
+                    decoder_latency_limiter_bps: limit(40.0),
+                    ..Default::default()
+                },
+                BindingConstraint::DecoderLatency,
+            ),
+            // The tightest limit binds
+            (
+                NominalBitrateStats {
+                    capacity_limiter_bps: limit(40.0),
+                    network_latency_limiter_bps: limit(30.0),
+                    ..Default::default()
+                },
+                BindingConstraint::NetworkLatency,
+            ),
+            (
+                NominalBitrateStats {
+                    capacity_limiter_bps: limit(5.0),
+                    qoe_floor_bps: limit(20.0),
+                    ..Default::default()
+                },
+                BindingConstraint::QoeFloor,
+            ),
+        ] {
+            assert_eq!(constraint(50.0, limits), expected);
+        }
+    }
+
+    #[test]
+    fn test_capacity_binding_backs_off_increases() {
+        let mut heuristic_config = simple_heuristic_config();
+        heuristic_config.capacity_binding_backoff = Switch::Enabled(8);
+        let config = bitrate_config(BitrateMode::SimpleHeuristic(heuristic_config));
+
+        // Every increase is clamped by the 90% of the 20 Mbps capacity
+        let mut manager = BitrateManager::new(1, 90.0, Some(0));
+        manager.report_network_rtt(Duration::from_millis(5), 20e6, 0.0);
+
+        let increase_attempted = |manager: &mut BitrateManager| {
+            manager.update_needed = true;
+            let (_, stats) = manager.get_encoder_params(&config);
+
+            stats.unwrap().heuristic_step_bps.unwrap() > 0.0
+        };
+
+        // Attempts after 0, 1, 3 and 7 skipped updates, then every 8
+        let attempts = (0..30)
+            .map(|_| increase_attempted(&mut manager))
+            .collect::<Vec<_>>();
+        let attempts_count = |range: std::ops::Range<usize>| {
+            attempts[range]
+                .iter()
+                .filter(|&&attempted| attempted)
+                .count()
+        };
+        assert_eq!(attempts_count(0..15), 4);
+        assert_eq!(attempts_count(15..30), 1);
+        assert!(manager.heur_stats.capacity_binding_streak >= 5);
+        assert!(manager.heur_stats.capacity_backoff_updates > 0);
+
+        // A higher capacity estimate ends the backoff
+        manager.report_network_rtt(Duration::from_millis(5), 50e6, 0.0);
+        assert!(increase_attempted(&mut manager));
+    }
+
+    #[test]
+    fn test_custom_capacity_estimator_drives_capacity_limit() {
+        struct FixedEstimator(f32);
+
+        impl CapacityEstimator for FixedEstimator {
+            fn submit(&mut self, _: f32) {}
+
+            fn estimate(&self) -> f32 {
+                self.0
+            }
+        }
+
+        let config = bitrate_config(simple_heuristic_mode());
+
+        let mut manager = BitrateManager::new(1, 90.0, Some(0));
+        manager.set_capacity_estimator(|| Box::new(FixedEstimator(20e6)));
+        // Ignored by the custom estimator
+        manager.report_network_rtt(Duration::from_millis(5), 300e6, 0.0);
+
+        let (_, stats) = manager.get_encoder_params(&config);
+
+        assert_eq!(
+            stats.unwrap().capacity_limiter_bps,
+            Some(Bitrate::from_bps(18e6))
+        );
+    }
+
+    #[test]
+    fn test_qoe_floor_yields_to_sustained_dip() {
+        let mut config = bitrate_config(BitrateMode::Bbr {
+            probe_interval_s: 1000.0,
+            probe_gain: 1.25,
+            min_rtt_window_s: 10.0,
+            max_rtt_inflation: 1.5,
+            max_bitrate_mbps: Switch::Disabled,
+            min_bitrate_mbps: Switch::Disabled,
+        });
+        config.qoe_floor = Switch::Enabled(QoeFloorConfig {
+            floor_mbps: 20.0,
+            grace_period_s: 2.0,
+        });
+
+        let clock = Arc::new(MockClock::default());
+        let mut manager = BitrateManager::with_clock(1, 90.0, None, Arc::clone(&clock) as _);
+        let requested_mbps = |manager: &mut BitrateManager, capacity_bps: f32| {
+            manager.report_network_rtt(Duration::from_millis(5), capacity_bps, 0.0);
+            manager.update_needed = true;
+            let stats = manager.get_encoder_params(&config).1.unwrap();
+            (stats.requested_bps.as_mbps(), stats.qoe_floor_bps.is_some())
+        };
+
+        assert_eq!(requested_mbps(&mut manager, 50e6), (50.0, false));
+
+        // Transient dip, shorter than the grace period
+        assert_eq!(requested_mbps(&mut manager, 10e6), (20.0, true));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(requested_mbps(&mut manager, 10e6), (20.0, true));
+        assert_eq!(requested_mbps(&mut manager, 50e6), (50.0, false));
+
+        // Sustained dip, the grace period restarts
+        assert_eq!(requested_mbps(&mut manager, 10e6), (20.0, true));
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(requested_mbps(&mut manager, 10e6), (20.0, true));
+        clock.advance(Duration::from_millis(1000));
+        assert_eq!(requested_mbps(&mut manager, 10e6), (10.0, false));
+    }
+
+    #[test]
+    fn test_fec_overhead_follows_loss() {
+        let mut config = bitrate_config(BitrateMode::ConstantMbps(30));
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        let fec_overhead = |manager: &mut BitrateManager, config: &BitrateConfig, loss| {
+            manager.report_packet_loss(loss);
+            manager.update_needed = true;
+            manager.get_encoder_params(config).0.fec_overhead
+        };
+
+        // Disabled by default
+        assert_eq!(fec_overhead(&mut manager, &config, 0.05), 0.0);
+
+        config.fec_overhead = Switch::Enabled(FecOverheadConfig {
+            loss_multiplier: 2.0,
+            max_overhead: 0.25,
+        });
+        assert!((fec_overhead(&mut manager, &config, 0.05) - 0.1).abs() < 1e-6);
+
+        // Clamped at the ceiling
+        assert_eq!(fec_overhead(&mut manager, &config, 0.2), 0.25);
+    }
+
+    #[test]
+    fn test_decision_log_records_binding_constraint() {
+        let mut config = bitrate_config(simple_heuristic_mode());
+        config.decision_log = true;
+
+        let sink = Arc::new(CapturingSink::default());
+        let mut manager = BitrateManager::new(1, 90.0, Some(0));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+        manager.report_network_rtt(Duration::from_millis(5), 20e6, 0.0);
+
+        // Limited by the capacity, as in test_heuristic_capacity_limit_is_recorded
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.updated, 1);
+
+        // No-op tick before the update interval elapses
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.updated, 0);
+
+        let decisions = sink
+            .events
+            .lock()
+            .iter()
+            .filter_map(|event| match event {
+                EventType::Log(entry) => {
+                    Some(serde_json::from_str::<serde_json::Value>(&entry.content).unwrap())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0]["mode"], "simple_heuristic");
+        assert_eq!(decisions[0]["binding_constraint"], "capacity");
+        assert_eq!(decisions[0]["raw_bps"], 40e6);
+        assert_eq!(decisions[0]["requested_bps"], 10e6);
+    }
+
+    #[test]
+    fn test_per_eye_bitrate_split() {
+        let mut config = bitrate_config(BitrateMode::Adaptive(AdaptiveBitrateConfig {
+            saturation_multiplier: 0.95,
+            max_bitrate_mbps: Switch::Enabled(25),
+            min_bitrate_mbps: Switch::Enabled(20),
+            max_network_latency_ms: Switch::Disabled,
+            network_latency_limiter_max_drop: Switch::Disabled,
+            encoder_latency_limiter: Switch::Disabled,
+            packet_loss_limiter: Switch::Disabled,
+            decoder_latency_limiter: Switch::Disabled,
+            content_limited_fill_ratio: Switch::Disabled,
+            cold_start: Switch::Disabled,
+        }));
+        config.left_eye_bitrate_ratio = 0.6;
+
+        // The default bitrate average scaled by the saturation multiplier is above the maximum
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        let (params, _) = manager.get_encoder_params(&config);
+
+        assert_eq!(params.bitrate_bps, 25_000_000);
+        assert_eq!(
+            params.bitrate_bps_left + params.bitrate_bps_right,
+            params.bitrate_bps
+        );
+        assert_eq!(params.bitrate_bps_left, 15_000_000);
+        assert_eq!(params.bitrate_bps_right, 10_000_000);
+
+        config.left_eye_bitrate_ratio = 0.5;
+        manager.update_needed = true;
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps_left, params.bitrate_bps / 2);
+        assert_eq!(params.bitrate_bps_right, params.bitrate_bps / 2);
+    }
+
+    #[test]
+    fn test_encoder_latency_percentile_gating() {
+        let requested_and_limit = |latency_percentile| {
+            let mut mode = adaptive_mode();
+            if let BitrateMode::Adaptive(AdaptiveBitrateConfig {
+                encoder_latency_limiter,
+                content_limited_fill_ratio,
+                ..
+            }) = &mut mode
+            {
+                *encoder_latency_limiter = Switch::Enabled(EncoderLatencyLimiter {
+                    max_saturation_multiplier: 0.9,
+                    latency_percentile,
+                    reference_interval: EncoderLatencyReferenceInterval::Automatic,
+                });
+                *content_limited_fill_ratio = Switch::Disabled;
+            }
+
+            // Mean of 2.9 ms, p95 of 20 ms, against an 11 ms frame interval
+            let mut manager = BitrateManager::new(20, 90.0, None);
+            for i in 0..20 {
+                let encoder_latency = Duration::from_millis(if i % 10 == 0 { 20 } else { 1 });
+                manager.report_frame_encoded(Duration::ZERO, encoder_latency, 1000);
+            }
+
+            let stats = manager.get_encoder_params(&bitrate_config(mode)).1.unwrap();
+            (
+                stats.requested_bps.as_bps(),
+                stats.encoder_latency_limiter_bps.unwrap().as_bps(),
+                stats.binding_constraint,
+            )
+        };
+
+        let (requested_bps, limit_bps, constraint) = requested_and_limit(Switch::Disabled);
+        assert!(requested_bps < limit_bps);
+        assert_ne!(constraint, BindingConstraint::EncoderLatency);
+
+        let (requested_bps, limit_bps, constraint) = requested_and_limit(Switch::Enabled(95.0));
+        assert_eq!(requested_bps, limit_bps);
+        assert_eq!(constraint, BindingConstraint::EncoderLatency);
+    }
+
+    #[test]
+    fn test_encoder_latency_reference_interval() {
+        let limit_mbps = |adapt_to_framerate, reference_interval| {
+            let mut mode = adaptive_mode();
+            if let BitrateMode::Adaptive(AdaptiveBitrateConfig {
+                encoder_latency_limiter,
+                content_limited_fill_ratio,
+                ..
+            }) = &mut mode
+            {
+                *encoder_latency_limiter = Switch::Enabled(EncoderLatencyLimiter {
+                    max_saturation_multiplier: 0.9,
+                    latency_percentile: Switch::Disabled,
+                    reference_interval,
+                });
+                *content_limited_fill_ratio = Switch::Disabled;
+            }
+            let mut config = bitrate_config(mode);
+            if adapt_to_framerate {
+                config.adapt_to_framerate = Switch::Enabled(BitrateAdaptiveFramerateConfig {
+                    framerate_reset_threshold_multiplier: 2.0,
+                    sustained_outlier_detection: Switch::Disabled,
+                    max_framerate_step: Switch::Disabled,
+                });
+            }
+
+            // Configured for 90 Hz but running at 45 Hz, with a 10 ms encoder latency
+            let mut manager = BitrateManager::new(20, 90.0, None);
+            for _ in 0..20 {
+                manager.report_frame_interval(Duration::from_micros(22_222), &Switch::Disabled);
+                manager.report_frame_encoded(Duration::ZERO, Duration::from_millis(10), 1000);
+            }
+
+            let stats = manager.get_encoder_params(&config).1.unwrap();
+            stats.encoder_latency_limiter_bps.unwrap().as_mbps()
+        };
+
+        let nominal_mbps = limit_mbps(false, EncoderLatencyReferenceInterval::Nominal);
+        let measured_mbps = limit_mbps(false, EncoderLatencyReferenceInterval::Measured);
+
+        // Half the saturation against the doubled frame interval, twice the ceiling
+        assert!((measured_mbps / nominal_mbps - 2.0).abs() < 1e-3);
+
+        // Automatic follows the framerate adaptation
+        assert_eq!(
+            limit_mbps(false, EncoderLatencyReferenceInterval::Automatic),
+            nominal_mbps
+        );
+        assert_eq!(
+            limit_mbps(true, EncoderLatencyReferenceInterval::Automatic),
+            measured_mbps
+        );
+        assert_eq!(
+            limit_mbps(true, EncoderLatencyReferenceInterval::Nominal),
+            nominal_mbps
+        );
+    }
+
+    #[test]
+    fn test_packet_loss_limiter() {
+        let mut mode = adaptive_mode();
+        if let BitrateMode::Adaptive(AdaptiveBitrateConfig {
+            packet_loss_limiter,
+            content_limited_fill_ratio,
+            ..
+        }) = &mut mode
+        {
+            *packet_loss_limiter = Switch::Enabled(PacketLossLimiter {
+                tcp_friendly_constant: 1.22,
+            });
+            *content_limited_fill_ratio = Switch::Disabled;
+        }
+        let config = bitrate_config(mode);
+
+        let unconstrained_bps = {
+            let mut manager = BitrateManager::new(1, 90.0, None);
+            let (params, _) = manager.get_encoder_params(&bitrate_config(adaptive_mode()));
+            params.bitrate_bps
+        };
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_packet_loss(0.0);
+        let (params, stats) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, unconstrained_bps);
+        assert!(stats.unwrap().packet_loss_limiter_bps.is_none());
+
+        // 2% loss with the initial 5 ms RTT and 1400 byte packets allows about 19 Mbps
+        manager.report_packet_loss(0.02);
+        manager.update_needed = true;
+        let (params, stats) = manager.get_encoder_params(&config);
+        let stats = stats.unwrap();
+        let ceiling = stats.packet_loss_limiter_bps.unwrap();
+        assert!(ceiling.as_bps() < unconstrained_bps as f32);
+        assert_eq!(params.bitrate_bps, ceiling.as_bps() as u64);
+        assert_eq!(stats.binding_constraint, BindingConstraint::PacketLoss);
+    }
+
+    #[test]
+    fn test_slew_rate_limits_bitrate_jump() {
+        let mut config = bitrate_config(BitrateMode::ConstantMbps(10));
+        config.max_slew_rate_mbps_per_s = Switch::Enabled(30.0);
+        config.decision_log = true;
+
+        let sink = Arc::new(CapturingSink::default());
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 10_000_000);
+
+        // One update per second, simulated by moving the last update instant back
+        config.mode = BitrateMode::ConstantMbps(100);
+        let mut emitted = vec![];
+        let mut constraints = vec![];
+        for _ in 0..3 {
+            manager.last_update_instant -= Duration::from_secs(1);
+            let (params, stats) = manager.get_encoder_params(&config);
+            let stats = stats.unwrap();
+            assert_eq!(params.updated, 1);
+            assert_eq!(stats.requested_bps, Bitrate::from_mbps(100.0));
+
+            emitted.push(params.bitrate_bps as f32 / 1e6);
+            constraints.push(stats.binding_constraint);
+        }
+        assert_eq!(
+            constraints,
+            [
+                BindingConstraint::Slew,
+                BindingConstraint::Slew,
+                BindingConstraint::None
+            ]
+        );
+
+        // The decision log agrees with the statistics
+        let logged_constraints = sink
+            .events
+            .lock()
+            .iter()
+            .filter_map(|event| match event {
+                EventType::Log(entry) => Some(
+                    serde_json::from_str::<serde_json::Value>(&entry.content).unwrap()
+                        ["binding_constraint"]
+                        .clone(),
+                ),
+                _ => None,
+            })
+            .skip(1)
+            .map(|constraint| serde_json::from_value::<BindingConstraint>(constraint).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(logged_constraints, constraints);
+
+        let mut previous = 10.0;
+        for bitrate_mbps in &emitted {
+            assert!(bitrate_mbps - previous <= 30.01);
+            previous = *bitrate_mbps;
+        }
+        assert!(emitted[0] > 39.99 && emitted[1] > 69.99);
+        assert_eq!(emitted[2], 100.0);
+
+        // Once the target is reached, constant mode stops updating
+        manager.last_update_instant -= Duration::from_secs(1);
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.updated, 0);
+    }
+
+    #[test]
+    fn test_decoder_latency_panic_halves_bitrate() {
+        let mode = BitrateMode::Adaptive(AdaptiveBitrateConfig {
+            saturation_multiplier: 0.95,
+            max_bitrate_mbps: Switch::Disabled,
+            min_bitrate_mbps: Switch::Disabled,
+            max_network_latency_ms: Switch::Disabled,
+            network_latency_limiter_max_drop: Switch::Disabled,
+            encoder_latency_limiter: Switch::Disabled,
+            packet_loss_limiter: Switch::Disabled,
+            decoder_latency_limiter: Switch::Enabled(DecoderLatencyLimiter {
+                max_decoder_latency_ms: 30,
+                latency_overstep_frames: 90,
+                latency_overstep_multiplier: 0.99,
+                panic_decoder_latency_ms: Switch::Enabled(100),
+                recovery: Switch::Disabled,
+            }),
+            content_limited_fill_ratio: Switch::Disabled,
+            cold_start: Switch::Disabled,
+        });
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.get_encoder_params(&bitrate_config(mode.clone()));
+        let bitrate_average = manager.bitrate_average.get_average();
+
+        // A mild overstep only counts towards the gradual limiter
+        manager.report_frame_latencies(
+            &mode,
+            Duration::ZERO,
+            Duration::from_millis(5),
+            Duration::from_millis(50),
+            0.011,
+        );
+        assert_eq!(manager.dynamic_max_bitrate, f32::MAX);
+        assert_eq!(manager.decoder_latency_overstep_count, 1);
+
+        manager.report_frame_latencies(
+            &mode,
+            Duration::ZERO,
+            Duration::from_millis(5),
+            Duration::from_millis(500),
+            0.011,
+        );
+        assert_eq!(manager.dynamic_max_bitrate, bitrate_average * 0.5);
+        assert_eq!(manager.decoder_latency_overstep_count, 0);
+        assert!(manager.update_needed);
+    }
+
+    #[test]
+    fn test_decoder_latency_limiter_without_network_latency() {
+        let mode = BitrateMode::Adaptive(AdaptiveBitrateConfig {
+            saturation_multiplier: 0.95,
+            max_bitrate_mbps: Switch::Disabled,
+            min_bitrate_mbps: Switch::Disabled,
+            max_network_latency_ms: Switch::Disabled,
+            network_latency_limiter_max_drop: Switch::Disabled,
+            encoder_latency_limiter: Switch::Disabled,
+            packet_loss_limiter: Switch::Disabled,
+            decoder_latency_limiter: Switch::Enabled(DecoderLatencyLimiter {
+                max_decoder_latency_ms: 30,
+                latency_overstep_frames: 2,
+                latency_overstep_multiplier: 0.5,
+                panic_decoder_latency_ms: Switch::Disabled,
+                recovery: Switch::Disabled,
+            }),
+            content_limited_fill_ratio: Switch::Disabled,
+            cold_start: Switch::Disabled,
+        });
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        let bitrate_average = manager.bitrate_average.get_average();
+
+        for _ in 0..2 {
+            manager.report_frame_latencies(
+                &mode,
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::from_millis(50),
+                0.011,
+            );
+        }
+
+        assert_eq!(manager.dynamic_max_bitrate, bitrate_average * 0.5);
+        // The network latency average is untouched
+        assert_eq!(manager.network_latency_average.history_buffer_len(), 1);
+    }
+
+    #[test]
+    fn test_decoder_latency_cap_recovers() {
+        let mode = BitrateMode::Adaptive(AdaptiveBitrateConfig {
+            saturation_multiplier: 0.95,
+            max_bitrate_mbps: Switch::Disabled,
+            min_bitrate_mbps: Switch::Disabled,
+            max_network_latency_ms: Switch::Disabled,
+            network_latency_limiter_max_drop: Switch::Disabled,
+            encoder_latency_limiter: Switch::Disabled,
+            packet_loss_limiter: Switch::Disabled,
+            decoder_latency_limiter: Switch::Enabled(DecoderLatencyLimiter {
+                max_decoder_latency_ms: 30,
+                latency_overstep_frames: 1,
+                latency_overstep_multiplier: 0.5,
+                panic_decoder_latency_ms: Switch::Disabled,
+                recovery: Switch::Enabled(DecoderLatencyRecoveryConfig {
+                    interval_s: 1.0,
+                    multiplier: 1.5,
+                }),
+            }),
+            content_limited_fill_ratio: Switch::Disabled,
+            cold_start: Switch::Disabled,
+        });
+
+        let clock = Arc::new(MockClock::default());
+        let mut manager = BitrateManager::with_clock(1, 90.0, None, Arc::clone(&clock) as _);
+        let report_decoder_latency = |manager: &mut BitrateManager, decoder_latency_ms| {
+            manager.report_frame_latencies(
+                &mode,
+                Duration::ZERO,
+                Duration::from_millis(5),
+                Duration::from_millis(decoder_latency_ms),
+                0.011,
+            )
+        };
+
+        report_decoder_latency(&mut manager, 50);
+        let lowered_cap = manager.dynamic_max_bitrate;
+        assert!(lowered_cap < f32::MAX);
+
+        // Low latency frames within the recovery interval leave the cap as is
+        clock.advance(Duration::from_millis(500));
+        report_decoder_latency(&mut manager, 5);
+        assert_eq!(manager.dynamic_max_bitrate, lowered_cap);
+
+        // Each further interval without oversteps raises the cap by the multiplier
+        let mut expected_cap = lowered_cap;
+        for _ in 0..3 {
+            clock.advance(Duration::from_millis(500));
+            report_decoder_latency(&mut manager, 5);
+            clock.advance(Duration::from_millis(500));
+            report_decoder_latency(&mut manager, 5);
+
+            expected_cap *= 1.5;
+            assert_eq!(manager.dynamic_max_bitrate, expected_cap);
+        }
+
+        // An overstep restarts the interval
+        report_decoder_latency(&mut manager, 50);
+        let lowered_cap = manager.dynamic_max_bitrate;
+        assert!(lowered_cap < expected_cap);
+        clock.advance(Duration::from_millis(900));
+        report_decoder_latency(&mut manager, 5);
+        assert_eq!(manager.dynamic_max_bitrate, lowered_cap);
+    }
+
+    #[test]
+    fn test_constructor_clamps_invalid_parameters() {
+        for (initial_framerate, expected_interval) in [
+            (0.0, Duration::from_secs(1)),
+            (-90.0, Duration::from_secs(1)),
+            (f32::NAN, Duration::from_secs(1)),
+            (f32::INFINITY, Duration::from_millis(1)),
+        ] {
+            let mut manager = BitrateManager::new(0, initial_framerate, None);
+            assert_eq!(manager.max_history_size, 1);
+            assert_eq!(manager.nominal_frame_interval, expected_interval);
+
+            // The averages hold a sample and the encoder parameters stay finite
+            manager.report_frame_present(&Switch::Disabled, &Switch::Disabled);
+            let (params, _) =
+                manager.get_encoder_params(&bitrate_config(BitrateMode::ConstantMbps(30)));
+            assert!(params.framerate.is_finite());
+            assert_eq!(params.bitrate_bps, 30_000_000);
+        }
+    }
+
+    #[test]
+    fn test_capacity_headroom() {
+        let config = bitrate_config(BitrateMode::ConstantMbps(30));
+        let mut manager = BitrateManager::new(1, 90.0, None);
+
+        manager.report_network_rtt(Duration::from_millis(10), 80e6, 0.0);
+        let (_, stats) = manager.get_encoder_params(&config);
+        assert_eq!(stats.unwrap().capacity_headroom_bps, 50e6);
+        assert_eq!(manager.capacity_headroom_bps(), 50e6);
+
+        let heur_stats = manager.report_network_rtt(Duration::from_millis(10), 80e6, 0.0);
+        assert_eq!(heur_stats.capacity_headroom_bps, 50e6);
+
+        // Capacity below the target leaves no headroom
+        let heur_stats = manager.report_network_rtt(Duration::from_millis(10), 20e6, 0.0);
+        assert_eq!(heur_stats.capacity_headroom_bps, 0.0);
+        assert_eq!(manager.capacity_headroom_bps(), 0.0);
+    }
+
+    #[test]
+    fn test_blend_weight_extremes_reproduce_each_controller() {
+        let blend_mode = |weight| BitrateMode::Blend {
+            weight,
+            adaptive_config: adaptive_config(),
+            heuristic_config: simple_heuristic_config(),
+        };
+
+        // Both controllers are given the same statistics and the same random draws
+        let bitrates = |mode: BitrateMode| {
+            let config = bitrate_config(mode);
+            let clock = Arc::new(MockClock::default());
+            let mut manager = BitrateManager::with_clock(4, 90.0, Some(7), Arc::clone(&clock) as _);
+
+            (0..10)
+                .map(|i| {
+                    let timestamp = Duration::from_millis(i * 11);
+                    manager.report_frame_encoded(
+                        timestamp,
+                        Duration::from_millis(2),
+                        40_000 + (i as usize % 3) * 20_000,
+                    );
+                    manager.report_network_rtt(Duration::from_millis(4 + i % 4), 80e6, 0.0);
+                    manager.report_frame_latencies(
+                        &config.mode,
+                        timestamp,
+                        Duration::from_millis(5),
+                        Duration::from_millis(2),
+                        0.011,
+                    );
+                    clock.advance(Duration::from_secs(2));
+
+                    let (params, stats) = manager.get_encoder_params(&config);
+                    let stats = stats.unwrap();
+                    if matches!(config.mode, BitrateMode::Blend { .. }) {
+                        assert!(stats.blend_adaptive_bps.is_some());
+                        assert!(stats.blend_heuristic_bps.is_some());
+                    }
+
+                    params.bitrate_bps
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(bitrates(blend_mode(0.0)), bitrates(simple_heuristic_mode()));
+        assert_eq!(bitrates(blend_mode(1.0)), bitrates(adaptive_mode()));
+    }
+
+    #[test]
+    fn test_diagnostics_reflect_samples() {
+        let mut manager = BitrateManager::new(2, 90.0, None);
+
+        manager.report_network_rtt(Duration::from_millis(4), 100E6, 0.1);
+        manager.report_network_rtt(Duration::from_millis(8), 200E6, 0.3);
+        manager.report_packet_loss(0.02);
+        manager.report_packet_loss(0.04);
+
+        // 50 KB frames delivered in 5 ms: 80 Mbps
+        for i in 0..2 {
+            let timestamp = Duration::from_millis(i * 11);
+            manager.report_frame_encoded(timestamp, Duration::from_millis(3), 50_000);
+            manager.report_frame_latencies(
+                &BitrateMode::ConstantMbps(30),
+                timestamp,
+                Duration::from_millis(5),
+                Duration::from_millis(2),
+                0.012,
+            );
+        }
+
+        let diagnostics = manager.diagnostics();
+        assert_eq!(diagnostics.rtt, Duration::from_millis(6));
+        assert_eq!(diagnostics.peak_throughput_average_bps, 150E6);
+        assert!((diagnostics.duplicate_ratio - 0.2).abs() < 1e-6);
+        assert!((diagnostics.packet_loss_ratio - 0.03).abs() < 1e-6);
+        assert_eq!(diagnostics.encoder_latency, Duration::from_millis(3));
+        assert_eq!(diagnostics.network_latency, Duration::from_millis(5));
+        assert!((diagnostics.bitrate_average_bps - 80E6).abs() < 1E3);
+        assert_eq!(diagnostics.frame_interarrival_s, 0.012);
+        assert_eq!(diagnostics.dynamic_max_bitrate_bps, f32::MAX);
+        assert_eq!(diagnostics.last_target_bitrate, Bitrate::from_mbps(30.0));
+    }
+
+    fn aimd_mode() -> BitrateMode {
+        BitrateMode::Aimd {
+            increase_mbps: 5.0,
+            decrease_factor: 0.5,
+            rtt_inflation_threshold: 2.0,
+            loss_threshold: 0.02,
+            max_bitrate_mbps: 50,
+            min_bitrate_mbps: 10,
+        }
+    }
+
+    #[test]
+    fn test_cold_start_ramps_until_congestion() {
+        let mut config = adaptive_config();
+        config.cold_start = Switch::Enabled(ColdStartConfig {
+            duration_s: 60.0,
+            growth_factor: 1.5,
+        });
+        let config = bitrate_config(BitrateMode::Adaptive(config));
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+
+        // Exponential growth from the initial 30 Mbps
+        let mut expected_bps = 30E6;
+        for _ in 0..4 {
+            expected_bps *= 1.5;
+            manager.update_needed = true;
+            let (params, _) = manager.get_encoder_params(&config);
+            assert!((params.bitrate_bps as f32 - expected_bps).abs() < 1.0);
+        }
+        assert!(!manager.cold_start_ended);
+
+        // The first loss hands off to the controller, 0.95 of the 30 Mbps average
+        manager.report_packet_loss(0.1);
+        manager.update_needed = true;
+        let (params, _) = manager.get_encoder_params(&config);
+        assert!(manager.cold_start_ended);
+        assert_eq!(params.bitrate_bps, 28_500_000);
+
+        // The ramp does not resume once the loss is gone
+        manager.report_packet_loss(0.0);
+        manager.update_needed = true;
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 28_500_000);
+    }
+
+    #[test]
+    fn test_unwarmed_capacity_holds_bitrate() {
+        let mut heuristic_config = simple_heuristic_config();
+        heuristic_config.capacity_warmup_samples = Switch::Enabled(3);
+        let config = bitrate_config(BitrateMode::SimpleHeuristic(heuristic_config));
+
+        // The windows hold their 300 Mbps initialization value, which would not limit the bitrate
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        for _ in 0..2 {
+            manager.report_network_rtt(Duration::from_millis(5), 300E6, 0.0);
+            manager.update_needed = true;
+            let (params, stats) = manager.get_encoder_params(&config);
+            assert_eq!(params.bitrate_bps, 30_000_000);
+            assert_eq!(
+                stats.unwrap().capacity_limiter_bps,
+                Some(Bitrate::from_mbps(30.0))
+            );
+        }
+
+        manager.report_network_rtt(Duration::from_millis(5), 300E6, 0.0);
+        manager.update_needed = true;
+        let (params, _) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 40_000_000);
+    }
+
+    #[test]
+    fn test_aimd_additive_increase() {
+        let config = bitrate_config(aimd_mode());
+
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(5), 300E6, 0.0);
+
+        // From 30 Mbps up to the maximum, then held there
+        for (expected_mbps, expected_constraint) in [
+            (35, BindingConstraint::None),
+            (40, BindingConstraint::None),
+            (45, BindingConstraint::None),
+            (50, BindingConstraint::None),
+            (50, BindingConstraint::ManualMax),
+        ] {
+            manager.update_needed = true;
+            let (params, stats) = manager.get_encoder_params(&config);
+            let stats = stats.unwrap();
+            assert_eq!(params.bitrate_bps, expected_mbps * 1_000_000);
+            assert_eq!(stats.aimd_congestion, Some(false));
+            assert_eq!(stats.binding_constraint, expected_constraint);
+        }
+    }
+
+    #[test]
+    fn test_aimd_multiplicative_decrease() {
+        let config = bitrate_config(aimd_mode());
+
+        // Loss above the threshold
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(5), 300E6, 0.0);
+        manager.report_packet_loss(0.05);
+        let (params, stats) = manager.get_encoder_params(&config);
+        assert_eq!(params.bitrate_bps, 15_000_000);
+        assert_eq!(stats.unwrap().aimd_congestion, Some(true));
+
+        // RTT above twice the 16 ms frame interval, down to the minimum
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(40), 300E6, 0.0);
+        for expected_mbps in [15, 10] {
+            manager.update_needed = true;
+            let (params, _) = manager.get_encoder_params(&config);
+            assert_eq!(params.bitrate_bps, expected_mbps * 1_000_000);
+        }
+    }
+
+    #[test]
+    fn test_tfrc_equation() {
+        let config = bitrate_config(BitrateMode::Tfrc {
+            max_bitrate_mbps: 100,
+            min_bitrate_mbps: 1,
+        });
+        let mut manager = BitrateManager::new(1, 90.0, None);
+        manager.report_network_rtt(Duration::from_millis(50), 80e6, 0.0);
+
+        // No loss event yet
+        let (_, stats) = manager.get_encoder_params(&config);
+        assert_eq!(stats.unwrap().requested_bps, Bitrate::from_mbps(100.0));
+
+        // One lost packet every 100, p = 0.01
+        for _ in 0..4 {
+            manager.report_shard_counters(99, 1);
+        }
+        manager.update_needed = true;
+        let stats = manager.get_encoder_params(&config).1.unwrap();
+
+        let (s, r, p) = (1400.0 * 8.0, 0.05_f32, 0.01_f32);
+        let expected_bps = s
+            / (r * (2.0 * p / 3.0).sqrt()
+                + 4.0 * r * (3.0 * (3.0 * p / 8.0).sqrt()) * p * (1.0 + 32.0 * p.powi(2)));
+        assert!((2.5e6..2.55e6).contains(&expected_bps));
+
+        assert_eq!(stats.loss_event_interval, Some(100.0));
+        let rate_bps = stats.tfrc_rate_bps.unwrap().as_bps();
+        assert!((rate_bps - expected_bps).abs() / expected_bps < 1e-4);
+        assert_eq!(stats.requested_bps.as_bps(), rate_bps);
+
+        // A long loss-free run raises the average loss interval
+        manager.report_shard_counters(500, 0);
+        manager.update_needed = true;
+        let stats = manager.get_encoder_params(&config).1.unwrap();
+        assert!((stats.loss_event_interval.unwrap() - 880.0 / 4.8).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,93 @@
+// Estimators of the link capacity from the peak throughput (or goodput) samples, used by the
+// capacity limit of the simple heuristic.
+
+use alvr_common::{SlidingWindowAverage, SlidingWindowMax};
+use alvr_session::PeakThroughputEstimator;
+
+// Initialization value of the built-in estimators, high enough not to limit the bitrate before
+// the first samples
+const INITIAL_CAPACITY_BPS: f32 = 300E6;
+
+pub trait CapacityEstimator: Send {
+    fn submit(&mut self, sample_bps: f32);
+    fn estimate(&self) -> f32;
+}
+
+impl CapacityEstimator for SlidingWindowAverage<f32> {
+    fn submit(&mut self, sample_bps: f32) {
+        self.submit_sample(sample_bps);
+    }
+
+    fn estimate(&self) -> f32 {
+        self.get_average()
+    }
+}
+
+impl CapacityEstimator for SlidingWindowMax<f32> {
+    fn submit(&mut self, sample_bps: f32) {
+        self.submit_sample(sample_bps);
+    }
+
+    fn estimate(&self) -> f32 {
+        self.get_max()
+    }
+}
+
+// Dominated by the low samples, so a few bursts don't inflate the estimate. Samples of zero are
+// ignored
+pub struct HarmonicMean {
+    reciprocals: SlidingWindowAverage<f32>,
+}
+
+impl HarmonicMean {
+    pub fn new(initial_value: f32, max_history_size: usize) -> Self {
+        Self {
+            reciprocals: SlidingWindowAverage::new(1.0 / initial_value, max_history_size),
+        }
+    }
+}
+
+impl CapacityEstimator for HarmonicMean {
+    fn submit(&mut self, sample_bps: f32) {
+        if sample_bps > 0.0 {
+            self.reciprocals.submit_sample(1.0 / sample_bps);
+        }
+    }
+
+    fn estimate(&self) -> f32 {
+        1.0 / self.reciprocals.get_average()
+    }
+}
+
+// The average and max estimators start from a copy of the windows kept by BitrateManager, so
+// switching between them (or restoring a snapshot) doesn't discard the history
+pub fn new_capacity_estimator(
+    kind: PeakThroughputEstimator,
+    average: &SlidingWindowAverage<f32>,
+    max: &SlidingWindowMax<f32>,
+    max_history_size: usize,
+) -> Box<dyn CapacityEstimator> {
+    match kind {
+        PeakThroughputEstimator::Average => Box::new(average.clone()),
+        PeakThroughputEstimator::Max => Box::new(max.clone()),
+        PeakThroughputEstimator::HarmonicMean => {
+            Box::new(HarmonicMean::new(INITIAL_CAPACITY_BPS, max_history_size))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harmonic_mean() {
+        let mut estimator = HarmonicMean::new(INITIAL_CAPACITY_BPS, 2);
+        estimator.submit(0.0);
+        estimator.submit(100E6);
+        estimator.submit(25E6);
+
+        // 2 / (1 / 100 + 1 / 25) = 40
+        assert!((estimator.estimate() - 40E6).abs() < 1.0);
+    }
+}
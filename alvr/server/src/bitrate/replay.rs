@@ -0,0 +1,345 @@
+// Deterministic replay of recorded per-frame traces through BitrateManager, used to validate
+// bitrate algorithm changes offline.
+
+use super::BitrateManager;
+use crate::clock::Clock;
+use alvr_common::{
+    anyhow::{anyhow, bail, Result},
+    parking_lot::Mutex,
+};
+use alvr_session::BitrateConfig;
+use serde::Deserialize;
+use std::{
+    ffi::{c_char, CStr},
+    fs,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Deserialize)]
+pub struct ReplayFrame {
+    pub frame_interval_s: f32,
+    pub network_rtt_s: f32,
+    pub peak_throughput_bps: f32,
+    pub frame_interarrival_s: f32,
+    pub encoder_latency_s: f32,
+    pub network_latency_s: f32,
+    pub decoder_latency_s: f32,
+    pub size_bytes: usize,
+}
+
+// Time of the trace, moved forward by the interval of every replayed frame
+struct TraceClock(Mutex<Instant>);
+
+impl TraceClock {
+    fn advance(&self, duration: Duration) {
+        *self.0.lock() += duration;
+    }
+}
+
+impl Clock for TraceClock {
+    fn now(&self) -> Instant {
+        *self.0.lock()
+    }
+}
+
+impl ReplayFrame {
+    // Durations must be finite and non-negative, otherwise the conversion to Duration panics
+    fn validate(&self) -> Result<()> {
+        let durations_s = [
+            ("frame_interval_s", self.frame_interval_s),
+            ("network_rtt_s", self.network_rtt_s),
+            ("encoder_latency_s", self.encoder_latency_s),
+            ("network_latency_s", self.network_latency_s),
+            ("decoder_latency_s", self.decoder_latency_s),
+        ];
+        for (name, value) in durations_s {
+            if !value.is_finite() || value < 0.0 {
+                bail!("Invalid {name}: {value}");
+            }
+        }
+        if self.frame_interval_s == 0.0 {
+            bail!("Invalid frame_interval_s: 0");
+        }
+        if !self.peak_throughput_bps.is_finite() || !self.frame_interarrival_s.is_finite() {
+            bail!("Non finite throughput or interarrival");
+        }
+
+        Ok(())
+    }
+}
+
+// A .csv trace has a header row with the ReplayFrame field names, in any order. Any other trace is
+// a JSON array of ReplayFrame
+pub fn load_trace(path: &Path) -> Result<Vec<ReplayFrame>> {
+    let contents = fs::read_to_string(path)?;
+
+    let trace: Vec<ReplayFrame> = if path.extension().is_some_and(|extension| extension == "csv") {
+        parse_csv_trace(&contents)?
+    } else {
+        serde_json::from_str(&contents)?
+    };
+
+    for (index, frame) in trace.iter().enumerate() {
+        frame
+            .validate()
+            .map_err(|e| anyhow!("Frame {index} of the trace: {e}"))?;
+    }
+
+    Ok(trace)
+}
+
+fn parse_csv_trace(contents: &str) -> Result<Vec<ReplayFrame>> {
+    let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+
+    let header = lines
+        .next()
+        .ok_or_else(|| anyhow!("Missing CSV header"))?
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    lines
+        .enumerate()
+        .map(|(row, line)| {
+            let values = line.split(',').map(str::trim).collect::<Vec<_>>();
+            if values.len() != header.len() {
+                bail!(
+                    "Row {} has {} values, expected {}",
+                    row + 1,
+                    values.len(),
+                    header.len()
+                );
+            }
+
+            let value = |name| csv_value(&header, &values, name);
+
+            Ok(ReplayFrame {
+                frame_interval_s: value("frame_interval_s")?.parse()?,
+                network_rtt_s: value("network_rtt_s")?.parse()?,
+                peak_throughput_bps: value("peak_throughput_bps")?.parse()?,
+                frame_interarrival_s: value("frame_interarrival_s")?.parse()?,
+                encoder_latency_s: value("encoder_latency_s")?.parse()?,
+                network_latency_s: value("network_latency_s")?.parse()?,
+                decoder_latency_s: value("decoder_latency_s")?.parse()?,
+                size_bytes: value("size_bytes")?.parse()?,
+            })
+        })
+        .collect()
+}
+
+fn csv_value<'a>(header: &[&str], values: &[&'a str], name: &str) -> Result<&'a str> {
+    header
+        .iter()
+        .position(|&column| column == name)
+        .map(|index| values[index])
+        .ok_or_else(|| anyhow!("Missing CSV column {name}"))
+}
+
+// Returns the bitrate in effect after every frame. Time is simulated: the manager clock advances by
+// the interval of each frame, so decisions follow the configured update interval. The frames must
+// be valid, as returned by load_trace()
+pub fn replay(trace: &[ReplayFrame], config: &BitrateConfig, seed: u64) -> Vec<u64> {
+    let initial_framerate = trace
+        .first()
+        .map(|frame| 1.0 / frame.frame_interval_s)
+        .unwrap_or(90.0);
+    let clock = Arc::new(TraceClock(Mutex::new(Instant::now())));
+    let mut manager = BitrateManager::with_clock(
+        config.history_size,
+        initial_framerate,
+        Some(seed),
+        Arc::clone(&clock) as _,
+    );
+
+    let mut timestamp = Duration::ZERO;
+    let mut bitrate_bps = 0;
+    let mut bitrates = vec![];
+    for frame in trace {
+        let frame_interval = Duration::from_secs_f32(frame.frame_interval_s);
+        timestamp += frame_interval;
+        clock.advance(frame_interval);
+
+        manager.report_frame_present(&config.adapt_to_framerate, &config.idle_reset);
+        manager.report_frame_encoded(
+            timestamp,
+            Duration::from_secs_f32(frame.encoder_latency_s),
+            frame.size_bytes,
+        );
+        manager.report_network_rtt(
+            Duration::from_secs_f32(frame.network_rtt_s),
+            frame.peak_throughput_bps,
+            0.0,
+        );
+        manager.report_frame_latencies(
+            &config.mode,
+            timestamp,
+            Duration::from_secs_f32(frame.network_latency_s),
+            Duration::from_secs_f32(frame.decoder_latency_s),
+            frame.frame_interarrival_s,
+        );
+
+        let (params, _) = manager.get_encoder_params(config);
+        if params.updated != 0 {
+            bitrate_bps = params.bitrate_bps;
+        }
+        bitrates.push(bitrate_bps);
+    }
+
+    bitrates
+}
+
+// Replays the trace at trace_path with the BitrateConfig serialized as JSON in config_json, then
+// writes the bitrate after every frame to out_path, one per line. Returns false on failure
+#[no_mangle]
+pub unsafe extern "C" fn alvr_replay_bitrate_trace(
+    trace_path: *const c_char,
+    config_json: *const c_char,
+    seed: u64,
+    out_path: *const c_char,
+) -> bool {
+    let result = (|| -> Result<()> {
+        let trace = load_trace(Path::new(CStr::from_ptr(trace_path).to_str()?))?;
+        let config = serde_json::from_str(CStr::from_ptr(config_json).to_str()?)?;
+
+        let bitrates = replay(&trace, &config, seed)
+            .iter()
+            .map(|bitrate_bps| format!("{bitrate_bps}\n"))
+            .collect::<String>();
+        fs::write(CStr::from_ptr(out_path).to_str()?, bitrates)?;
+
+        Ok(())
+    })();
+
+    if let Err(e) = &result {
+        alvr_common::warn!("Bitrate trace replay failed: {e}");
+    }
+
+    result.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitrate::tests::{bitrate_config, simple_heuristic_mode};
+    use alvr_session::{settings_schema::Switch, BitrateMode, SimpleHeuristicConfig};
+    use std::ffi::CString;
+
+    const CSV_HEADER: &str = "frame_interval_s,network_rtt_s,peak_throughput_bps,\
+        frame_interarrival_s,encoder_latency_s,network_latency_s,decoder_latency_s,size_bytes";
+
+    fn randomized_config() -> BitrateConfig {
+        let mut config = bitrate_config(simple_heuristic_mode());
+        config.history_size = 4;
+        if let BitrateMode::SimpleHeuristic(SimpleHeuristicConfig {
+            threshold_random_uniform,
+            ..
+        }) = &mut config.mode
+        {
+            *threshold_random_uniform = Switch::Enabled(0.5);
+        }
+
+        config
+    }
+
+    #[test]
+    fn test_replay_is_reproducible() {
+        // About 4.4 s of frames, so that the update interval elapses several times
+        let frames = [5.0, 8.0, 30.0, 40.0, 10.0, 5.0, 35.0, 5.0]
+            .iter()
+            .cycle()
+            .take(400)
+            .map(|rtt_ms| {
+                format!(
+                    r#"{{
+                        "frame_interval_s": 0.0111,
+                        "network_rtt_s": {},
+                        "peak_throughput_bps": 300e6,
+                        "frame_interarrival_s": 0.0111,
+                        "encoder_latency_s": 0.004,
+                        "network_latency_s": 0.005,
+                        "decoder_latency_s": 0.003,
+                        "size_bytes": 50000
+                    }}"#,
+                    rtt_ms / 1000.0
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let path = std::env::temp_dir().join(format!(
+            "alvr_bitrate_replay_test.{}.json",
+            std::process::id()
+        ));
+        fs::write(&path, format!("[{}]", frames.join(","))).unwrap();
+        let trace = load_trace(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        let config = randomized_config();
+
+        let first = replay(&trace, &config, 42);
+        let second = replay(&trace, &config, 42);
+
+        assert_eq!(first.len(), trace.len());
+        assert_eq!(first, second);
+        // The bitrate is held between updates, not re-decided for every frame
+        assert!(first.windows(2).filter(|pair| pair[0] != pair[1]).count() < 10);
+    }
+
+    #[test]
+    fn test_csv_trace() {
+        // Columns in a different order than the header constant
+        let contents = format!(
+            "size_bytes,{}\n\n50000,0.0111,0.005,300e6,0.0111,0.004,0.005,0.003\n",
+            CSV_HEADER.replace(",size_bytes", "")
+        );
+        let trace = parse_csv_trace(&contents).unwrap();
+
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].size_bytes, 50_000);
+        assert_eq!(trace[0].network_rtt_s, 0.005);
+        assert_eq!(trace[0].peak_throughput_bps, 300e6);
+
+        assert!(parse_csv_trace(CSV_HEADER).unwrap().is_empty());
+        assert!(parse_csv_trace("").is_err());
+        assert!(parse_csv_trace(&format!("{CSV_HEADER}\n1,2,3")).is_err());
+        assert!(parse_csv_trace("frame_interval_s\n0.0111").is_err());
+    }
+
+    #[test]
+    fn test_invalid_frames_are_rejected() {
+        let path = std::env::temp_dir().join(format!(
+            "alvr_bitrate_replay_invalid_test.{}.csv",
+            std::process::id()
+        ));
+        let path_cstring = CString::new(path.to_str().unwrap()).unwrap();
+        let config_json =
+            CString::new(serde_json::to_string(&randomized_config()).unwrap()).unwrap();
+        let out_path = std::env::temp_dir().join(format!(
+            "alvr_bitrate_replay_invalid_test.{}.out",
+            std::process::id()
+        ));
+        let out_path_cstring = CString::new(out_path.to_str().unwrap()).unwrap();
+
+        for row in [
+            "0.0111,-0.005,300e6,0.0111,0.004,0.005,0.003,50000",
+            "0.0111,NaN,300e6,0.0111,0.004,0.005,0.003,50000",
+            "0.0111,0.005,300e6,0.0111,inf,0.005,0.003,50000",
+            "0,0.005,300e6,0.0111,0.004,0.005,0.003,50000",
+        ] {
+            fs::write(&path, format!("{CSV_HEADER}\n{row}\n")).unwrap();
+
+            assert!(load_trace(&path).is_err());
+            assert!(!unsafe {
+                alvr_replay_bitrate_trace(
+                    path_cstring.as_ptr(),
+                    config_json.as_ptr(),
+                    42,
+                    out_path_cstring.as_ptr(),
+                )
+            });
+        }
+
+        fs::remove_file(&path).ok();
+    }
+}
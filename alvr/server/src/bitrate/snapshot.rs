@@ -0,0 +1,351 @@
+// Serializable copy of the learned state of BitrateManager, used for crash recovery and for
+// warm-starting experiments. Instants are stored as the time elapsed before the snapshot and are
+// recomputed relative to the clock passed on restore. The RNG, the bitrate change callback, the
+// clock, the external capacity and the events sink are not part of the state. The capacity
+// estimators are rebuilt from the restored peak throughput windows. A harmonic mean estimator
+// restarts from its initial value and a custom estimator must be set again after restoring.
+
+use super::{capacity, BitrateManager, IncrementalVariance};
+use crate::{clock::Clock, stats_sink::EventsSink};
+use alvr_common::{Bitrate, SlidingWindowAverage, SlidingWindowMax};
+use alvr_events::HeuristicStats;
+use alvr_session::{BitrateConfig, PeakThroughputEstimator};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+fn elapsed_since(now: Instant, instant: Instant) -> Duration {
+    now.saturating_duration_since(instant)
+}
+
+fn instant_before(now: Instant, elapsed: Duration) -> Instant {
+    now.checked_sub(elapsed).unwrap_or(now)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BitrateManagerSnapshot {
+    nominal_frame_interval: Duration,
+    frame_interval_average: SlidingWindowAverage<Duration>,
+    frame_interval_variance: IncrementalVariance,
+    frame_interval_outlier_count: usize,
+    packet_sizes_bits_history: VecDeque<(Duration, usize)>,
+    encoder_latency_average: SlidingWindowAverage<Duration>,
+    network_latency_average: SlidingWindowAverage<Duration>,
+    decoder_queue_latency_average: SlidingWindowAverage<Duration>,
+    bitrate_average: SlidingWindowAverage<f32>,
+    content_fill_average: SlidingWindowAverage<f32>,
+    decoder_latency_overstep_count: usize,
+    since_last_decoder_latency_overstep: Duration,
+    since_last_frame: Duration,
+    since_last_update: Duration,
+    since_creation: Duration,
+    dynamic_max_bitrate: f32,
+    network_latency_ceiling: Option<f32>,
+    previous_config: Option<BitrateConfig>,
+    update_needed: bool,
+    last_target_bitrate: Bitrate,
+    since_below_qoe_floor: Option<Duration>,
+    blend_adaptive_bps: Option<f32>,
+    blend_heuristic_bps: Option<f32>,
+    cold_start_ended: bool,
+    last_emitted_bitrate: Option<Bitrate>,
+    last_raw_framerate: Option<f32>,
+    last_emitted_framerate: Option<f32>,
+    frame_interarrival_avg: f32,
+    rtt_average: SlidingWindowAverage<Duration>,
+    update_interval_setting: Duration,
+    heur_stats: HeuristicStats,
+    peak_throughput_average: SlidingWindowAverage<f32>,
+    peak_throughput_max: SlidingWindowMax<f32>,
+    peak_goodput_average: SlidingWindowAverage<f32>,
+    peak_goodput_max: SlidingWindowMax<f32>,
+    capacity_estimator_kind: Option<PeakThroughputEstimator>,
+    peak_throughput_sample_count: usize,
+    peak_goodput_sample_count: usize,
+    capacity_binding_streak: u32,
+    capacity_backoff_updates: u32,
+    capacity_backoff_capacity_bps: f32,
+    duplicate_ratio_average: SlidingWindowAverage<f32>,
+    since_last_throughput_sample: Duration,
+    packet_loss_average: SlidingWindowAverage<f32>,
+    packet_size_bits: f32,
+    loss_intervals: VecDeque<u64>,
+    packets_since_loss_event: u64,
+    // (age, rtt)
+    rtt_history: VecDeque<(Duration, Duration)>,
+    max_history_size: usize,
+    since_last_probe: Duration,
+}
+
+// Nothing in the server persists the bitrate state yet
+#[allow(dead_code)]
+impl BitrateManager {
+    pub fn to_snapshot(&self) -> BitrateManagerSnapshot {
+        let now = self.clock.now();
+
+        BitrateManagerSnapshot {
+            nominal_frame_interval: self.nominal_frame_interval,
+            frame_interval_average: self.frame_interval_average.clone(),
+            frame_interval_variance: self.frame_interval_variance.clone(),
+            frame_interval_outlier_count: self.frame_interval_outlier_count,
+            packet_sizes_bits_history: self.packet_sizes_bits_history.clone(),
+            encoder_latency_average: self.encoder_latency_average.clone(),
+            network_latency_average: self.network_latency_average.clone(),
+            decoder_queue_latency_average: self.decoder_queue_latency_average.clone(),
+            bitrate_average: self.bitrate_average.clone(),
+            content_fill_average: self.content_fill_average.clone(),
+            decoder_latency_overstep_count: self.decoder_latency_overstep_count,
+            since_last_decoder_latency_overstep: elapsed_since(
+                now,
+                self.last_decoder_latency_overstep_instant,
+            ),
+            since_last_frame: elapsed_since(now, self.last_frame_instant),
+            since_last_update: elapsed_since(now, self.last_update_instant),
+            since_creation: elapsed_since(now, self.creation_instant),
+            dynamic_max_bitrate: self.dynamic_max_bitrate,
+            network_latency_ceiling: self.network_latency_ceiling,
+            previous_config: self.previous_config.clone(),
+            update_needed: self.update_needed,
+            last_target_bitrate: self.last_target_bitrate,
+            since_below_qoe_floor: self
+                .below_qoe_floor_since
+                .map(|instant| elapsed_since(now, instant)),
+            blend_adaptive_bps: self.blend_adaptive_bps,
+            blend_heuristic_bps: self.blend_heuristic_bps,
+            cold_start_ended: self.cold_start_ended,
+            last_emitted_bitrate: self.last_emitted_bitrate,
+            last_raw_framerate: self.last_raw_framerate,
+            last_emitted_framerate: self.last_emitted_framerate,
+            frame_interarrival_avg: self.frame_interarrival_avg,
+            rtt_average: self.rtt_average.clone(),
+            update_interval_setting: self.update_interval_setting,
+            heur_stats: self.heur_stats,
+            peak_throughput_average: self.peak_throughput_average.clone(),
+            peak_throughput_max: self.peak_throughput_max.clone(),
+            peak_goodput_average: self.peak_goodput_average.clone(),
+            peak_goodput_max: self.peak_goodput_max.clone(),
+            capacity_estimator_kind: self.capacity_estimator_kind,
+            peak_throughput_sample_count: self.peak_throughput_sample_count,
+            peak_goodput_sample_count: self.peak_goodput_sample_count,
+            capacity_binding_streak: self.capacity_binding_streak,
+            capacity_backoff_updates: self.capacity_backoff_updates,
+            capacity_backoff_capacity_bps: self.capacity_backoff_capacity_bps,
+            duplicate_ratio_average: self.duplicate_ratio_average.clone(),
+            since_last_throughput_sample: elapsed_since(now, self.last_throughput_sample_instant),
+            packet_loss_average: self.packet_loss_average.clone(),
+            packet_size_bits: self.packet_size_bits,
+            loss_intervals: self.loss_intervals.clone(),
+            packets_since_loss_event: self.packets_since_loss_event,
+            rtt_history: self
+                .rtt_history
+                .iter()
+                .map(|(instant, rtt)| (elapsed_since(now, *instant), *rtt))
+                .collect(),
+            max_history_size: self.max_history_size,
+            since_last_probe: elapsed_since(now, self.last_probe_instant),
+        }
+    }
+
+    // When seed is None, the RNG is seeded from entropy
+    pub fn from_snapshot(
+        snapshot: BitrateManagerSnapshot,
+        seed: Option<u64>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let now = clock.now();
+
+        // Until a kind is selected the estimators follow the average windows
+        let estimator_kind = snapshot
+            .capacity_estimator_kind
+            .unwrap_or(PeakThroughputEstimator::Average);
+        let throughput_capacity_estimator = capacity::new_capacity_estimator(
+            estimator_kind,
+            &snapshot.peak_throughput_average,
+            &snapshot.peak_throughput_max,
+            snapshot.max_history_size,
+        );
+        let goodput_capacity_estimator = capacity::new_capacity_estimator(
+            estimator_kind,
+            &snapshot.peak_goodput_average,
+            &snapshot.peak_goodput_max,
+            snapshot.max_history_size,
+        );
+
+        Self {
+            nominal_frame_interval: snapshot.nominal_frame_interval,
+            frame_interval_average: snapshot.frame_interval_average,
+            frame_interval_variance: snapshot.frame_interval_variance,
+            frame_interval_outlier_count: snapshot.frame_interval_outlier_count,
+            packet_sizes_bits_history: snapshot.packet_sizes_bits_history,
+            encoder_latency_average: snapshot.encoder_latency_average,
+            network_latency_average: snapshot.network_latency_average,
+            decoder_queue_latency_average: snapshot.decoder_queue_latency_average,
+            bitrate_average: snapshot.bitrate_average,
+            content_fill_average: snapshot.content_fill_average,
+            decoder_latency_overstep_count: snapshot.decoder_latency_overstep_count,
+            last_decoder_latency_overstep_instant: instant_before(
+                now,
+                snapshot.since_last_decoder_latency_overstep,
+            ),
+            last_frame_instant: instant_before(now, snapshot.since_last_frame),
+            last_update_instant: instant_before(now, snapshot.since_last_update),
+            creation_instant: instant_before(now, snapshot.since_creation),
+            dynamic_max_bitrate: snapshot.dynamic_max_bitrate,
+            network_latency_ceiling: snapshot.network_latency_ceiling,
+            previous_config: snapshot.previous_config,
+            update_needed: snapshot.update_needed,
+            last_target_bitrate: snapshot.last_target_bitrate,
+            below_qoe_floor_since: snapshot
+                .since_below_qoe_floor
+                .map(|elapsed| instant_before(now, elapsed)),
+            blend_adaptive_bps: snapshot.blend_adaptive_bps,
+            blend_heuristic_bps: snapshot.blend_heuristic_bps,
+            cold_start_ended: snapshot.cold_start_ended,
+            last_emitted_bitrate: snapshot.last_emitted_bitrate,
+            last_raw_framerate: snapshot.last_raw_framerate,
+            last_emitted_framerate: snapshot.last_emitted_framerate,
+            frame_interarrival_avg: snapshot.frame_interarrival_avg,
+            rtt_average: snapshot.rtt_average,
+            update_interval_setting: snapshot.update_interval_setting,
+            heur_stats: snapshot.heur_stats,
+            peak_throughput_average: snapshot.peak_throughput_average,
+            peak_throughput_max: snapshot.peak_throughput_max,
+            peak_goodput_average: snapshot.peak_goodput_average,
+            peak_goodput_max: snapshot.peak_goodput_max,
+            throughput_capacity_estimator,
+            goodput_capacity_estimator,
+            capacity_estimator_kind: snapshot.capacity_estimator_kind,
+            custom_capacity_estimator: false,
+            peak_throughput_sample_count: snapshot.peak_throughput_sample_count,
+            peak_goodput_sample_count: snapshot.peak_goodput_sample_count,
+            external_capacity: None,
+            capacity_binding_streak: snapshot.capacity_binding_streak,
+            capacity_backoff_updates: snapshot.capacity_backoff_updates,
+            capacity_backoff_capacity_bps: snapshot.capacity_backoff_capacity_bps,
+            duplicate_ratio_average: snapshot.duplicate_ratio_average,
+            last_throughput_sample_instant: instant_before(
+                now,
+                snapshot.since_last_throughput_sample,
+            ),
+            packet_loss_average: snapshot.packet_loss_average,
+            packet_size_bits: snapshot.packet_size_bits,
+            loss_intervals: snapshot.loss_intervals,
+            packets_since_loss_event: snapshot.packets_since_loss_event,
+            rng: seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+            rtt_history: snapshot
+                .rtt_history
+                .into_iter()
+                .map(|(age, rtt)| (instant_before(now, age), rtt))
+                .collect(),
+            max_history_size: snapshot.max_history_size,
+            last_probe_instant: instant_before(now, snapshot.since_last_probe),
+            on_bitrate_change: None,
+            sink: Arc::new(EventsSink),
+            clock,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bitrate::tests::bitrate_config, clock::tests::MockClock};
+    use alvr_session::{settings_schema::Switch, AdaptiveBitrateConfig, BitrateMode};
+
+    fn feed_frames(manager: &mut BitrateManager, mode: &BitrateMode, first: u64, count: u64) {
+        for i in first..first + count {
+            let timestamp = Duration::from_millis(i * 11);
+            manager.report_frame_encoded(
+                timestamp,
+                Duration::from_millis(3 + i % 3),
+                40_000 + (i as usize % 5) * 5_000,
+            );
+            manager.report_network_rtt(Duration::from_millis(4 + i % 4), 200e6, 0.0);
+            manager.report_frame_latencies(
+                mode,
+                timestamp,
+                Duration::from_millis(4 + i % 6),
+                Duration::from_millis(2),
+                0.011,
+            );
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let config = bitrate_config(BitrateMode::Adaptive(AdaptiveBitrateConfig {
+            saturation_multiplier: 0.95,
+            max_bitrate_mbps: Switch::Enabled(200),
+            min_bitrate_mbps: Switch::Enabled(5),
+            max_network_latency_ms: Switch::Enabled(8),
+            network_latency_limiter_max_drop: Switch::Enabled(0.1),
+            encoder_latency_limiter: Switch::Disabled,
+            packet_loss_limiter: Switch::Disabled,
+            decoder_latency_limiter: Switch::Disabled,
+            content_limited_fill_ratio: Switch::Enabled(0.5),
+            cold_start: Switch::Disabled,
+        }));
+
+        let clock = Arc::new(MockClock::default());
+        let mut manager = BitrateManager::with_clock(16, 90.0, None, Arc::clone(&clock) as _);
+        feed_frames(&mut manager, &config.mode, 0, 20);
+        manager.get_encoder_params(&config);
+        feed_frames(&mut manager, &config.mode, 20, 10);
+
+        let json = serde_json::to_string(&manager.to_snapshot()).unwrap();
+        let mut restored = BitrateManager::from_snapshot(
+            serde_json::from_str(&json).unwrap(),
+            None,
+            Arc::clone(&clock) as _,
+        );
+
+        feed_frames(&mut manager, &config.mode, 30, 10);
+        feed_frames(&mut restored, &config.mode, 30, 10);
+        manager.update_needed = true;
+        restored.update_needed = true;
+
+        let (params, stats) = manager.get_encoder_params(&config);
+        let (restored_params, restored_stats) = restored.get_encoder_params(&config);
+
+        assert_eq!(restored_params.updated, 1);
+        assert_eq!(restored_params.bitrate_bps, params.bitrate_bps);
+        assert_eq!(restored_params.framerate, params.framerate);
+        assert_eq!(
+            restored_stats.unwrap().requested_bps,
+            stats.unwrap().requested_bps
+        );
+    }
+
+    #[test]
+    fn test_restored_capacity_estimators() {
+        let mode = bitrate_config(BitrateMode::ConstantMbps(100)).mode;
+
+        for kind in [None, Some(PeakThroughputEstimator::Max)] {
+            let clock = Arc::new(MockClock::default());
+            let mut manager = BitrateManager::with_clock(16, 90.0, None, Arc::clone(&clock) as _);
+            if let Some(kind) = kind {
+                manager.select_capacity_estimator(kind);
+            }
+            feed_frames(&mut manager, &mode, 0, 20);
+
+            let restored =
+                BitrateManager::from_snapshot(manager.to_snapshot(), None, Arc::clone(&clock) as _);
+
+            assert!(restored.capacity_estimator_kind == kind);
+            assert_eq!(
+                restored.throughput_capacity_estimator.estimate(),
+                manager.throughput_capacity_estimator.estimate()
+            );
+            assert_eq!(
+                restored.goodput_capacity_estimator.estimate(),
+                manager.goodput_capacity_estimator.estimate()
+            );
+            // Not the initial estimate
+            assert!(restored.throughput_capacity_estimator.estimate() < 300E6);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::Instant;
+
+// Source of the current time of StatisticsManager and BitrateManager. Allows tests to control the
+// passing of time instead of sleeping
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use alvr_common::parking_lot::Mutex;
+    use std::time::Duration;
+
+    // Starts at the creation instant and only moves when advanced or set
+    pub struct MockClock {
+        now: Mutex<Instant>,
+    }
+
+    impl MockClock {
+        pub fn advance(&self, duration: Duration) {
+            *self.now.lock() += duration;
+        }
+
+        pub fn set(&self, now: Instant) {
+            *self.now.lock() = now;
+        }
+    }
+
+    impl Default for MockClock {
+        fn default() -> Self {
+            Self {
+                now: Mutex::new(Instant::now()),
+            }
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            *self.now.lock()
+        }
+    }
+}
@@ -5,10 +5,11 @@ use crate::{
     haptics,
     input_mapping::ButtonMappingManager,
     sockets::WelcomeSocket,
-    statistics::StatisticsManager,
+    statistics::{self, ClientId, StatisticsManager},
     tracking::{self, TrackingManager},
-    FfiFov, FfiViewsConfig, VideoPacket, BITRATE_MANAGER, DECODER_CONFIG, LIFECYCLE_STATE,
-    SERVER_DATA_MANAGER, STATISTICS_MANAGER, VIDEO_MIRROR_SENDER, VIDEO_RECORDING_FILE,
+    FfiFov, FfiViewsConfig, VideoPacket, BITRATE_MANAGER, DECODER_CONFIG, FILESYSTEM_LAYOUT,
+    LIFECYCLE_STATE, SERVER_DATA_MANAGER, STATISTICS_MANAGER, VIDEO_MIRROR_SENDER,
+    VIDEO_RECORDING_FILE,
 };
 use alvr_audio::AudioDevice;
 use alvr_common::{
@@ -60,6 +61,8 @@ static HAPTICS_SENDER: OptLazy<StreamSender<Haptics>> = alvr_common::lazy_mut_no
 static CONNECTION_THREADS: Lazy<Mutex<Vec<JoinHandle<()>>>> = Lazy::new(|| Mutex::new(vec![]));
 pub static CLIENTS_TO_BE_REMOVED: Lazy<Mutex<HashSet<String>>> =
     Lazy::new(|| Mutex::new(HashSet::new()));
+// The compositor and encoder callbacks don't carry a client id, they report the streaming client
+pub static STREAMING_CLIENT_ID: OptLazy<ClientId> = alvr_common::lazy_mut_none();
 
 type InstantMap = Arc<RwLock<HashMap<u32, Instant>>>;
 
@@ -524,17 +527,79 @@ fn connection_pipeline(
     if !matches!(signal, ClientControlPacket::StreamReady) {
         con_bail!("Got unexpected packet waiting for stream ack");
     }
-    *STATISTICS_MANAGER.lock() = Some(StatisticsManager::new(
-        settings.connection.statistics_history_size,
-        Duration::from_secs_f32(1.0 / fps),
-        if let Switch::Enabled(config) = &settings.headset.controllers {
-            config.steamvr_pipeline_frames
-        } else {
-            0.0
-        },
+    let steamvr_pipeline_frames = if let Switch::Enabled(config) = &settings.headset.controllers {
+        config.steamvr_pipeline_frames
+    } else {
+        0.0
+    };
+    let mut stats = if let Switch::Enabled(averaging_window_size) =
+        settings.connection.statistics_averaging_window_size
+    {
+        StatisticsManager::new(
+            settings.connection.statistics_history_size,
+            averaging_window_size,
+            Duration::from_secs_f32(1.0 / fps),
+            steamvr_pipeline_frames,
+        )
+    } else {
+        StatisticsManager::with_history_size(
+            settings.connection.statistics_history_size,
+            Duration::from_secs_f32(1.0 / fps),
+            steamvr_pipeline_frames,
+        )
+    };
+    stats.set_full_report_interval(Duration::from_millis(
+        settings.connection.statistics_summary_interval_ms,
     ));
+    stats.set_qoe_weights(settings.connection.qoe_weights.clone());
+    stats.set_stall_threshold_multiplier(settings.connection.stall_threshold_multiplier);
+    stats.set_latency_histogram_bounds(settings.connection.latency_histogram_bounds_ms.clone());
+    stats.set_idle_timeout(
+        settings
+            .video
+            .bitrate
+            .idle_reset
+            .as_option()
+            .map(|config| Duration::from_secs_f32(config.timeout_s)),
+    );
+    if let Switch::Enabled(size) = settings.connection.recent_graph_statistics_size {
+        stats.set_recent_graph_stats_capacity(size);
+    }
+    stats.set_network_latency_estimator(settings.connection.network_latency_estimator.clone());
+    stats.set_owd_kalman_filter(&settings.connection.owd_kalman_filter);
+    stats.set_frame_interarrival_outlier_multiplier(
+        settings
+            .connection
+            .frame_interarrival_outlier_multiplier
+            .as_option()
+            .copied(),
+    );
+    if let Switch::Enabled(config) = &settings.connection.latency_budget_alarms {
+        stats.set_latency_budgets(config.clone());
+    }
+    if settings.logging.record_shard_timeline {
+        let path = FILESYSTEM_LAYOUT.log_dir.join(format!(
+            "shard_timeline.{}.csv{}",
+            chrono::Local::now().format("%F.%H-%M-%S"),
+            if settings.logging.compress_frame_logs {
+                ".gz"
+            } else {
+                ""
+            }
+        ));
+        match alvr_common::create_log_file(&path) {
+            Ok(writer) => stats.set_shard_timeline_writer(writer),
+            Err(e) => warn!("Failed to record the shard timeline: {e}"),
+        }
+    }
+    *STATISTICS_MANAGER.lock() = Some(stats);
+    let client_id = statistics::client_id(&client_hostname);
+    *STREAMING_CLIENT_ID.lock() = Some(client_id);
 
-    *BITRATE_MANAGER.lock() = BitrateManager::new(settings.video.bitrate.history_size, fps);
+    *BITRATE_MANAGER.lock() = BitrateManager::new(settings.video.bitrate.history_size, fps, None);
+    BITRATE_MANAGER
+        .lock()
+        .set_packet_size(settings.connection.packet_size as _);
 
     let mut stream_socket = StreamSocketBuilder::connect_to_client(
         HANDSHAKE_ACTION_TIMEOUT,
@@ -599,7 +664,7 @@ fn connection_pipeline(
                 let shards_count = video_sender.get_shards_count();
 
                 if let Some(stats) = &mut *STATISTICS_MANAGER.lock() {
-                    stats.report_frame_sent(header.timestamp, frame_index, shards_count);
+                    stats.report_frame_sent(client_id, header.timestamp, frame_index, shards_count);
                 }
             }
         }
@@ -877,7 +942,7 @@ fn connection_pipeline(
                 }
 
                 if let Some(stats) = &mut *STATISTICS_MANAGER.lock() {
-                    stats.report_tracking_received(tracking.target_timestamp);
+                    stats.report_tracking_received(client_id, tracking.target_timestamp);
 
                     unsafe {
                         crate::SetTracking(
@@ -919,8 +984,13 @@ fn connection_pipeline(
                 if let Some(stats) = &mut *STATISTICS_MANAGER.lock() {
                     let timestamp = client_stats.target_timestamp;
                     let decoder_latency = client_stats.video_decode;
-                    let (network_latency, frame_interarrival_avg) =
-                        stats.report_statistics(client_stats);
+                    let decoder_queue_latency = client_stats.video_decoder_queue;
+                    // A frame missing from the history must not feed the bitrate manager
+                    let Some((network_latency, frame_interarrival_avg, shard_loss_ratio)) =
+                        stats.report_statistics(client_id, client_stats)
+                    else {
+                        continue;
+                    };
 
                     let server_data_lock = SERVER_DATA_MANAGER.read();
                     BITRATE_MANAGER.lock().report_frame_latencies(
@@ -930,6 +1000,10 @@ fn connection_pipeline(
                         decoder_latency,
                         frame_interarrival_avg,
                     );
+                    BITRATE_MANAGER
+                        .lock()
+                        .report_decoder_queue_latency(decoder_queue_latency);
+                    BITRATE_MANAGER.lock().report_packet_loss(shard_loss_ratio);
                 }
             }
         }
@@ -1047,18 +1121,35 @@ fn connection_pipeline(
                                 // warn!("ZERO??");
                             }
 
-                            let peak_network_throughput_bps: f32 = if network_stats.frame_span != 0.0 {  // doing it here too since needs to be reported to BitrateManager 
-                                network_stats.bytes_in_frame as f32 * 8.0 / network_stats.frame_span
+                            let peak_network_throughput_bps =
+                                statistics::peak_network_throughput_bps(&network_stats);
+
+                            let duplicate_ratio = if network_stats.rx_shard_counter != 0 {
+                                network_stats.duplicated_shard_counter as f32
+                                    / network_stats.rx_shard_counter as f32
                             } else {
                                 0.0
                             };
 
-
-                            let heur_stats =
-                                BITRATE_MANAGER.lock().report_network_rtt(rtt_network_alt, peak_network_throughput_bps);
+                            let heur_stats = BITRATE_MANAGER.lock().report_network_rtt(
+                                rtt_network_alt,
+                                peak_network_throughput_bps,
+                                duplicate_ratio,
+                            );
                             BITRATE_MANAGER.lock().report_heuristic_event(heur_stats);
+                            BITRATE_MANAGER
+                                .lock()
+                                .report_peak_goodput(statistics::peak_goodput_bps(&network_stats));
 
-                            stats.report_network_statistics(network_stats, rtt_network_alt);
+                            let shards_received = network_stats.rx_shard_counter as usize;
+                            let shards_lost = stats.report_network_statistics(
+                                client_id,
+                                network_stats,
+                                rtt_network_alt,
+                            );
+                            BITRATE_MANAGER
+                                .lock()
+                                .report_shard_counters(shards_received, shards_lost);
                         }
                     }
 
@@ -1226,6 +1317,7 @@ fn connection_pipeline(
     // This requests shutdown from threads
     *VIDEO_CHANNEL_SENDER.lock() = None;
     *HAPTICS_SENDER.lock() = None;
+    *STREAMING_CLIENT_ID.lock() = None;
 
     *VIDEO_RECORDING_FILE.lock() = None;
 
@@ -1335,9 +1427,14 @@ pub extern "C" fn send_video(timestamp_ns: u64, buffer_ptr: *mut u8, len: i32, i
             warn!("Dropping video packet. Reason: Waiting for IDR frame");
         }
 
-        if let Some(stats) = &mut *STATISTICS_MANAGER.lock() {
-            let encoder_latency =
-                stats.report_frame_encoded(Duration::from_nanos(timestamp_ns), buffer_size, is_idr);
+        let client_id = *STREAMING_CLIENT_ID.lock();
+        if let (Some(stats), Some(client_id)) = (&mut *STATISTICS_MANAGER.lock(), client_id) {
+            let encoder_latency = stats.report_frame_encoded(
+                client_id,
+                Duration::from_nanos(timestamp_ns),
+                buffer_size,
+                is_idr,
+            );
 
             BITRATE_MANAGER
                 .lock()
@@ -0,0 +1,160 @@
+// Per-frame spans for correlating the latency breakdown with external tracing tools. A span is
+// opened when the tracking for a frame is received and it is closed when the client statistics for
+// that frame arrive. Each pipeline stage is recorded as a child event carrying the measured
+// durations as attributes, in seconds.
+
+use alvr_common::debug;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SpanEvent {
+    pub name: &'static str,
+    // Time since the start of the span
+    pub offset: Duration,
+    pub attributes: Vec<(&'static str, f64)>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FrameSpan {
+    pub target_timestamp: Duration,
+    pub duration: Duration,
+    pub events: Vec<SpanEvent>,
+}
+
+pub trait SpanExporter: Send + Sync {
+    fn export(&self, span: FrameSpan);
+}
+
+// Writes the spans to the log as JSON
+pub struct LogSpanExporter;
+
+impl SpanExporter for LogSpanExporter {
+    fn export(&self, span: FrameSpan) {
+        debug!("{}", serde_json::to_string(&span).unwrap());
+    }
+}
+
+struct OpenSpan {
+    start: Instant,
+    span: FrameSpan,
+}
+
+pub struct FrameTracer {
+    open_spans: VecDeque<OpenSpan>,
+    max_open_spans: usize,
+    exporter: Arc<dyn SpanExporter>,
+}
+
+impl FrameTracer {
+    pub fn new(max_open_spans: usize) -> Self {
+        Self {
+            open_spans: VecDeque::new(),
+            max_open_spans,
+            exporter: Arc::new(LogSpanExporter),
+        }
+    }
+
+    pub fn set_exporter(&mut self, exporter: Arc<dyn SpanExporter>) {
+        self.exporter = exporter;
+    }
+
+    // Spans of frames that are never completed are discarded once the limit is reached
+    pub fn start_span(&mut self, target_timestamp: Duration) {
+        if self
+            .open_spans
+            .iter()
+            .any(|open| open.span.target_timestamp == target_timestamp)
+        {
+            return;
+        }
+
+        self.open_spans.push_back(OpenSpan {
+            start: Instant::now(),
+            span: FrameSpan {
+                target_timestamp,
+                duration: Duration::ZERO,
+                events: vec![],
+            },
+        });
+
+        if self.open_spans.len() > self.max_open_spans {
+            self.open_spans.pop_front();
+        }
+    }
+
+    pub fn add_event(
+        &mut self,
+        target_timestamp: Duration,
+        name: &'static str,
+        attributes: &[(&'static str, Duration)],
+    ) {
+        if let Some(open) = self
+            .open_spans
+            .iter_mut()
+            .find(|open| open.span.target_timestamp == target_timestamp)
+        {
+            open.span.events.push(SpanEvent {
+                name,
+                offset: open.start.elapsed(),
+                attributes: attributes
+                    .iter()
+                    .map(|(key, duration)| (*key, duration.as_secs_f64()))
+                    .collect(),
+            });
+        }
+    }
+
+    pub fn end_span(&mut self, target_timestamp: Duration) {
+        if let Some(index) = self
+            .open_spans
+            .iter()
+            .position(|open| open.span.target_timestamp == target_timestamp)
+        {
+            let mut open = self.open_spans.remove(index).unwrap();
+            open.span.duration = open.start.elapsed();
+
+            self.exporter.export(open.span);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use alvr_common::parking_lot::Mutex;
+
+    #[derive(Default)]
+    pub struct MockExporter {
+        pub spans: Mutex<Vec<FrameSpan>>,
+    }
+
+    impl SpanExporter for MockExporter {
+        fn export(&self, span: FrameSpan) {
+            self.spans.lock().push(span);
+        }
+    }
+
+    #[test]
+    fn test_unfinished_spans_are_bounded() {
+        let exporter = Arc::new(MockExporter::default());
+
+        let mut tracer = FrameTracer::new(2);
+        tracer.set_exporter(Arc::clone(&exporter) as Arc<dyn SpanExporter>);
+
+        for i in 0..3 {
+            tracer.start_span(Duration::from_millis(i));
+        }
+        for i in 0..3 {
+            tracer.end_span(Duration::from_millis(i));
+        }
+
+        let spans = exporter.spans.lock();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].target_timestamp, Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,238 @@
+use alvr_common::anyhow::Result;
+use alvr_events::{Event, EventType};
+use std::{net::SocketAddr, pin::Pin};
+use tokio::{net::TcpListener, sync::broadcast};
+use tokio_stream::{
+    wrappers::{BroadcastStream, TcpListenerStream},
+    Stream, StreamExt,
+};
+use tonic::{transport::Server, Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("alvr.statistics");
+}
+
+use proto::{
+    statistics_message::Statistics,
+    statistics_stream_server::{StatisticsStream, StatisticsStreamServer},
+    StatisticsMessage, SubscribeRequest,
+};
+
+impl From<alvr_events::BindingConstraint> for proto::BindingConstraint {
+    fn from(constraint: alvr_events::BindingConstraint) -> Self {
+        use alvr_events::BindingConstraint as C;
+
+        match constraint {
+            C::None => Self::None,
+            C::Capacity => Self::Capacity,
+            C::PacketLoss => Self::PacketLoss,
+            C::NetworkLatency => Self::NetworkLatency,
+            C::EncoderLatency => Self::EncoderLatency,
+            C::DecoderLatency => Self::DecoderLatency,
+            C::ManualMax => Self::ManualMax,
+            C::ManualMin => Self::ManualMin,
+            C::QoeFloor => Self::QoeFloor,
+            C::Slew => Self::Slew,
+        }
+    }
+}
+
+impl From<alvr_events::NominalBitrateStats> for proto::NominalBitrateStats {
+    fn from(stats: alvr_events::NominalBitrateStats) -> Self {
+        Self {
+            scaled_calculated_bps: stats.scaled_calculated_bps.map(|b| b.as_bps()),
+            decoder_latency_limiter_bps: stats.decoder_latency_limiter_bps.map(|b| b.as_bps()),
+            network_latency_limiter_bps: stats.network_latency_limiter_bps.map(|b| b.as_bps()),
+            encoder_latency_limiter_bps: stats.encoder_latency_limiter_bps.map(|b| b.as_bps()),
+            packet_loss_limiter_bps: stats.packet_loss_limiter_bps.map(|b| b.as_bps()),
+            capacity_limiter_bps: stats.capacity_limiter_bps.map(|b| b.as_bps()),
+            heuristic_step_bps: stats.heuristic_step_bps,
+            buffer_based_bps: stats.buffer_based_bps.map(|b| b.as_bps()),
+            content_fill_ratio: stats.content_fill_ratio,
+            bottleneck_bandwidth_bps: stats.bottleneck_bandwidth_bps.map(|b| b.as_bps()),
+            bbr_probing: stats.bbr_probing,
+            aimd_congestion: stats.aimd_congestion,
+            manual_max_bps: stats.manual_max_bps.map(|b| b.as_bps()),
+            manual_min_bps: stats.manual_min_bps.map(|b| b.as_bps()),
+            requested_bps: stats.requested_bps.as_bps(),
+            update_interval_s: stats.update_interval_s,
+            capacity_headroom_bps: stats.capacity_headroom_bps,
+            tfrc_rate_bps: stats.tfrc_rate_bps.map(|b| b.as_bps()),
+            loss_event_interval: stats.loss_event_interval,
+            qoe_floor_bps: stats.qoe_floor_bps.map(|b| b.as_bps()),
+            blend_adaptive_bps: stats.blend_adaptive_bps.map(|b| b.as_bps()),
+            blend_heuristic_bps: stats.blend_heuristic_bps.map(|b| b.as_bps()),
+            binding_constraint: proto::BindingConstraint::from(stats.binding_constraint) as i32,
+        }
+    }
+}
+
+impl From<alvr_events::GraphStatistics> for proto::GraphStatistics {
+    fn from(graph: alvr_events::GraphStatistics) -> Self {
+        Self {
+            client_id: graph.client_id,
+            frame_index: graph.frame_index,
+            is_idr: graph.is_idr,
+            frames_dropped: graph.frames_dropped,
+            reordered_shard_counter: graph.reordered_shard_counter,
+            shard_loss_ratio: graph.shard_loss_ratio,
+            frame_loss_ratio: graph.frame_loss_ratio,
+            total_pipeline_latency_s: graph.total_pipeline_latency_s,
+            game_time_s: graph.game_time_s,
+            server_compositor_s: graph.server_compositor_s,
+            encoder_s: graph.encoder_s,
+            network_s: graph.network_s,
+            network_latency_min_s: graph.network_latency_min_s,
+            network_latency_max_s: graph.network_latency_max_s,
+            decoder_s: graph.decoder_s,
+            decoder_queue_s: graph.decoder_queue_s,
+            client_compositor_s: graph.client_compositor_s,
+            vsync_queue_s: graph.vsync_queue_s,
+            nominal_bitrate: Some(graph.nominal_bitrate.into()),
+            actual_bitrate_bps: graph.actual_bitrate_bps,
+            actual_bitrate_min_bps: graph.actual_bitrate_min_bps,
+            actual_bitrate_max_bps: graph.actual_bitrate_max_bps,
+            capacity_estimate_bps: graph.capacity_estimate_bps,
+            server_computed_jitter_s: graph.server_computed_jitter_s,
+            network_s_owd_based: graph.network_s_owd_based,
+            client_stats_defaulted: graph.client_stats_defaulted,
+            link_utilization: graph.link_utilization,
+            graph_time_ns: graph.graph_time_ns,
+        }
+    }
+}
+
+impl From<alvr_events::StatisticsSummary> for proto::StatisticsSummary {
+    fn from(summary: alvr_events::StatisticsSummary) -> Self {
+        Self {
+            video_packets_total: summary.video_packets_total as _,
+            video_packets_per_sec: summary.video_packets_per_sec as _,
+            video_mbytes_total: summary.video_mbytes_total as _,
+            video_mbits_per_sec: summary.video_mbits_per_sec,
+            video_throughput_mbits_per_sec: summary.video_throughput_mbits_per_sec,
+            total_pipeline_latency_average_ms: summary.total_pipeline_latency_average_ms,
+            game_delay_average_ms: summary.game_delay_average_ms,
+            server_compositor_delay_average_ms: summary.server_compositor_delay_average_ms,
+            encode_delay_average_ms: summary.encode_delay_average_ms,
+            network_delay_average_ms: summary.network_delay_average_ms,
+            decode_delay_average_ms: summary.decode_delay_average_ms,
+            decoder_queue_delay_average_ms: summary.decoder_queue_delay_average_ms,
+            client_compositor_average_ms: summary.client_compositor_average_ms,
+            vsync_queue_delay_average_ms: summary.vsync_queue_delay_average_ms,
+            packets_dropped_total: summary.packets_dropped_total as _,
+            packets_dropped_per_sec: summary.packets_dropped_per_sec as _,
+            packets_skipped_total: summary.packets_skipped_total as _,
+            packets_skipped_per_sec: summary.packets_skipped_per_sec as _,
+            frame_jitter_ms: summary.frame_jitter_ms,
+            client_fps: summary.client_fps,
+            server_fps: summary.server_fps,
+            battery_hmd: summary.battery_hmd,
+            hmd_plugged: summary.hmd_plugged,
+            qoe_score: summary.qoe_score,
+            stall_detected: summary.stall_detected,
+            stall_count_total: summary.stall_count_total as _,
+            stall_duration_total_s: summary.stall_duration_total_s,
+            network_latency_underflow_count: summary.network_latency_underflow_count as _,
+            network_latency_underflow_total_ms: summary.network_latency_underflow_total_ms,
+            server_frame_pacing_cv: summary.server_frame_pacing_cv,
+            effective_fps: summary.effective_fps,
+            estimated_clock_skew_s: summary.estimated_clock_skew_s,
+            avg_idr_bytes: summary.avg_idr_bytes,
+            avg_delta_bytes: summary.avg_delta_bytes,
+            frame_present_offset_clamp_count: summary.frame_present_offset_clamp_count as _,
+            bitrate_achievement_ratio: summary.bitrate_achievement_ratio,
+            video_mbits_per_sec_peak: summary.video_mbits_per_sec_peak,
+        }
+    }
+}
+
+fn statistics_message(event: Event) -> Option<StatisticsMessage> {
+    let statistics = match event.event_type {
+        EventType::GraphStatistics(graph) => Statistics::Graph(graph.into()),
+        EventType::StatisticsSummary(summary) => Statistics::Summary(summary.into()),
+        _ => return None,
+    };
+
+    Some(StatisticsMessage {
+        statistics: Some(statistics),
+    })
+}
+
+struct StatisticsService {
+    events_sender: broadcast::Sender<Event>,
+}
+
+#[tonic::async_trait]
+impl StatisticsStream for StatisticsService {
+    type SubscribeStream = Pin<Box<dyn Stream<Item = Result<StatisticsMessage, Status>> + Send>>;
+
+    // Every subscriber gets its own bounded receiver. A slow client makes its receiver lag, which
+    // drops the oldest messages for that client only; the statistics pipeline never blocks
+    async fn subscribe(
+        &self,
+        _: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let stream = BroadcastStream::new(self.events_sender.subscribe())
+            .filter_map(|maybe_event| maybe_event.ok().and_then(statistics_message).map(Ok));
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+async fn serve(listener: TcpListener, events_sender: broadcast::Sender<Event>) -> Result<()> {
+    Server::builder()
+        .add_service(StatisticsStreamServer::new(StatisticsService {
+            events_sender,
+        }))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await?;
+
+    Ok(())
+}
+
+// Streams GraphStatistics and StatisticsSummary to every connected client
+pub async fn grpc_server(port: u16, events_sender: broadcast::Sender<Event>) -> Result<()> {
+    let listener = TcpListener::bind(SocketAddr::new("0.0.0.0".parse().unwrap(), port)).await?;
+
+    serve(listener, events_sender).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        statistics::tests::{new_manager, report_complete_frame},
+        stats_sink::tests::BroadcastSink,
+    };
+    use proto::statistics_stream_client::StatisticsStreamClient;
+    use std::{sync::Arc, time::Duration};
+
+    #[tokio::test]
+    async fn test_stream_graph_statistics() {
+        let (events_sender, _) = broadcast::channel(16);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, events_sender.clone()));
+
+        let mut client = StatisticsStreamClient::connect(format!("http://{address}"))
+            .await
+            .unwrap();
+        // The subscription is active once the response is received
+        let mut stream = client
+            .subscribe(SubscribeRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+
+        let mut manager = new_manager();
+        manager.set_sink(Arc::new(BroadcastSink(events_sender)));
+        report_complete_frame(&mut manager, 1, Duration::from_millis(11), 7);
+
+        let message = stream.message().await.unwrap().unwrap();
+        match message.statistics {
+            Some(Statistics::Graph(graph)) => assert_eq!(graph.frame_index, 7),
+            _ => panic!("expected GraphStatistics"),
+        }
+    }
+}
@@ -1,14 +1,24 @@
 mod bitrate;
 mod c_api;
+mod clock;
 mod connection;
 mod face_tracking;
+#[cfg(feature = "frame-tracing")]
+mod frame_tracing;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod hand_gestures;
 mod haptics;
 mod input_mapping;
 mod logging_backend;
 mod openvr_props;
+mod prometheus;
+mod qoe;
 mod sockets;
 mod statistics;
+mod statistics_osc;
+mod statistics_websocket;
+mod stats_sink;
 mod tracking;
 mod web_server;
 
@@ -36,7 +46,7 @@ use alvr_events::EventType;
 use alvr_filesystem::{self as afs, Layout};
 use alvr_packets::{ClientListAction, DecoderInitializationConfig, VideoPacketHeader};
 use alvr_server_io::ServerDataManager;
-use alvr_session::{CodecType, Settings};
+use alvr_session::{settings_schema::Switch, CodecType, Settings};
 use bitrate::BitrateManager;
 use statistics::StatisticsManager;
 use std::{
@@ -68,7 +78,7 @@ static WEBSERVER_RUNTIME: OptLazy<Runtime> = Lazy::new(|| Mutex::new(Runtime::ne
 
 static STATISTICS_MANAGER: OptLazy<StatisticsManager> = alvr_common::lazy_mut_none();
 static BITRATE_MANAGER: Lazy<Mutex<BitrateManager>> =
-    Lazy::new(|| Mutex::new(BitrateManager::new(256, 60.0)));
+    Lazy::new(|| Mutex::new(BitrateManager::new(256, 60.0, None)));
 
 pub struct VideoPacket {
     pub header: VideoPacketHeader,
@@ -236,6 +246,60 @@ fn init() {
     SERVER_DATA_MANAGER.write().clean_client_list();
 
     if let Some(runtime) = WEBSERVER_RUNTIME.lock().as_mut() {
+        if let Switch::Enabled(port) = SERVER_DATA_MANAGER
+            .read()
+            .settings()
+            .logging
+            .prometheus_metrics_port
+        {
+            let events_receiver = events_sender.subscribe();
+            runtime.spawn(async move {
+                alvr_common::show_err(prometheus::prometheus_server(port, events_receiver).await)
+            });
+        }
+
+        if let Switch::Enabled(port) = SERVER_DATA_MANAGER
+            .read()
+            .settings()
+            .logging
+            .statistics_websocket_port
+        {
+            let events_receiver = events_sender.subscribe();
+            runtime.spawn(async move {
+                alvr_common::show_err(
+                    statistics_websocket::statistics_websocket_server(port, events_receiver).await,
+                )
+            });
+        }
+
+        if let Switch::Enabled(config) = SERVER_DATA_MANAGER
+            .read()
+            .settings()
+            .logging
+            .statistics_osc
+            .clone()
+        {
+            let events_receiver = events_sender.subscribe();
+            runtime.spawn(async move {
+                alvr_common::show_err(
+                    statistics_osc::statistics_osc_sender(config, events_receiver).await,
+                )
+            });
+        }
+
+        #[cfg(feature = "grpc")]
+        if let Switch::Enabled(port) = SERVER_DATA_MANAGER
+            .read()
+            .settings()
+            .logging
+            .grpc_statistics_port
+        {
+            let events_sender = events_sender.clone();
+            runtime.spawn(async move {
+                alvr_common::show_err(grpc::grpc_server(port, events_sender).await)
+            });
+        }
+
         runtime.spawn(async { alvr_common::show_err(web_server::web_server(events_sender).await) });
     }
 
@@ -373,22 +437,28 @@ pub unsafe extern "C" fn HmdDriverFactory(
     }
 
     extern "C" fn report_present(timestamp_ns: u64, offset_ns: u64) {
-        if let Some(stats) = &mut *STATISTICS_MANAGER.lock() {
+        let client_id = *connection::STREAMING_CLIENT_ID.lock();
+        if let (Some(stats), Some(client_id)) = (&mut *STATISTICS_MANAGER.lock(), client_id) {
             stats.report_frame_present(
+                client_id,
                 Duration::from_nanos(timestamp_ns),
                 Duration::from_nanos(offset_ns),
             );
         }
 
         let server_data_lock = SERVER_DATA_MANAGER.read();
-        BITRATE_MANAGER
-            .lock()
-            .report_frame_present(&server_data_lock.settings().video.bitrate.adapt_to_framerate);
+        let bitrate_config = &server_data_lock.settings().video.bitrate;
+        BITRATE_MANAGER.lock().report_frame_present(
+            &bitrate_config.adapt_to_framerate,
+            &bitrate_config.idle_reset,
+        );
     }
 
     extern "C" fn report_composed(timestamp_ns: u64, offset_ns: u64) {
-        if let Some(stats) = &mut *STATISTICS_MANAGER.lock() {
+        let client_id = *connection::STREAMING_CLIENT_ID.lock();
+        if let (Some(stats), Some(client_id)) = (&mut *STATISTICS_MANAGER.lock(), client_id) {
             stats.report_frame_composed(
+                client_id,
                 Duration::from_nanos(timestamp_ns),
                 Duration::from_nanos(offset_ns),
             );
@@ -0,0 +1,258 @@
+use alvr_common::{
+    anyhow::{self, Result},
+    parking_lot::Mutex,
+};
+use alvr_events::{Event, EventType, GraphStatistics, StatisticsSummary};
+use hyper::{header::CONTENT_TYPE, service, Body, Request, Response, StatusCode};
+use std::{fmt::Display, fmt::Write, net::SocketAddr, sync::Arc};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const CONTENT_TYPE_TEXT_FORMAT: &str = "text/plain; version=0.0.4";
+
+#[derive(Default)]
+struct Metrics {
+    summary: Option<StatisticsSummary>,
+    graph: Option<GraphStatistics>,
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl Display) {
+    writeln!(out, "# HELP alvr_{name} {help}").ok();
+    writeln!(out, "# TYPE alvr_{name} {kind}").ok();
+    writeln!(out, "alvr_{name} {value}").ok();
+}
+
+fn write_gauge(out: &mut String, name: &str, help: &str, value: impl Display) {
+    write_metric(out, name, "gauge", help, value);
+}
+
+impl Metrics {
+    // Prometheus text exposition format. Latencies are converted to seconds
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        if let Some(summary) = &self.summary {
+            let latencies_ms = [
+                ("total_pipeline", summary.total_pipeline_latency_average_ms),
+                ("game", summary.game_delay_average_ms),
+                (
+                    "server_compositor",
+                    summary.server_compositor_delay_average_ms,
+                ),
+                ("encoder", summary.encode_delay_average_ms),
+                ("network", summary.network_delay_average_ms),
+                ("decoder", summary.decode_delay_average_ms),
+                ("decoder_queue", summary.decoder_queue_delay_average_ms),
+                ("client_compositor", summary.client_compositor_average_ms),
+                ("vsync_queue", summary.vsync_queue_delay_average_ms),
+            ];
+            for (stage, latency_ms) in latencies_ms {
+                write_gauge(
+                    &mut out,
+                    &format!("{stage}_latency_seconds"),
+                    &format!("Average {stage} latency"),
+                    latency_ms / 1000.0,
+                );
+            }
+
+            write_gauge(
+                &mut out,
+                "video_bitrate_bits_per_second",
+                "Encoded video bitrate",
+                summary.video_mbits_per_sec * 1e6,
+            );
+            write_gauge(
+                &mut out,
+                "video_throughput_bits_per_second",
+                "Received video throughput",
+                summary.video_throughput_mbits_per_sec * 1e6,
+            );
+            write_gauge(
+                &mut out,
+                "client_fps",
+                "Client framerate",
+                summary.client_fps,
+            );
+            write_gauge(
+                &mut out,
+                "server_fps",
+                "Server framerate",
+                summary.server_fps,
+            );
+            write_gauge(
+                &mut out,
+                "effective_fps",
+                "Framerate excluding skipped and dropped frames",
+                summary.effective_fps,
+            );
+            write_metric(
+                &mut out,
+                "packets_dropped_total",
+                "counter",
+                "Video packets dropped",
+                summary.packets_dropped_total,
+            );
+            write_metric(
+                &mut out,
+                "packets_skipped_total",
+                "counter",
+                "Video packets skipped",
+                summary.packets_skipped_total,
+            );
+            write_gauge(
+                &mut out,
+                "hmd_battery_ratio",
+                "HMD battery level",
+                summary.battery_hmd as f32 / 100.0,
+            );
+            write_gauge(
+                &mut out,
+                "hmd_plugged",
+                "HMD is charging",
+                summary.hmd_plugged as u8,
+            );
+            write_gauge(
+                &mut out,
+                "qoe_score",
+                "Linear QoE score of the last statistics interval",
+                summary.qoe_score,
+            );
+        }
+
+        if let Some(graph) = &self.graph {
+            write_gauge(
+                &mut out,
+                "shard_loss_ratio",
+                "Fraction of lost shards",
+                graph.shard_loss_ratio,
+            );
+            write_gauge(
+                &mut out,
+                "frame_loss_ratio",
+                "Dropped frames over presented frames",
+                graph.frame_loss_ratio,
+            );
+            write_gauge(
+                &mut out,
+                "requested_bitrate_bits_per_second",
+                "Bitrate requested by the bitrate controller",
+                graph.nominal_bitrate.requested_bps.as_bps(),
+            );
+            write_gauge(
+                &mut out,
+                "actual_bitrate_bits_per_second",
+                "Bitrate as computed from the frame size and network latency",
+                graph.actual_bitrate_bps,
+            );
+        }
+
+        out
+    }
+}
+
+// Serves the latest statistics on /metrics. The receiver should be subscribed before the server
+// is spawned to avoid missing events
+pub async fn prometheus_server(
+    port: u16,
+    mut events_receiver: broadcast::Receiver<Event>,
+) -> Result<()> {
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+
+    tokio::spawn({
+        let metrics = Arc::clone(&metrics);
+        async move {
+            loop {
+                match events_receiver.recv().await {
+                    Ok(event) => match event.event_type {
+                        EventType::StatisticsSummary(summary) => {
+                            metrics.lock().summary = Some(summary)
+                        }
+                        // Keep the last measured latencies
+                        EventType::GraphStatistics(graph) if !graph.client_stats_defaulted => {
+                            metrics.lock().graph = Some(graph)
+                        }
+                        _ => (),
+                    },
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+    });
+
+    let service = service::make_service_fn(move |_| {
+        let metrics = Arc::clone(&metrics);
+        async move {
+            Ok::<_, anyhow::Error>(service::service_fn(move |request: Request<Body>| {
+                let metrics = Arc::clone(&metrics);
+                async move {
+                    let response = if request.uri().path() == "/metrics" {
+                        Response::builder()
+                            .header(CONTENT_TYPE, CONTENT_TYPE_TEXT_FORMAT)
+                            .body(Body::from(metrics.lock().render()))?
+                    } else {
+                        Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(Body::empty())?
+                    };
+
+                    Ok::<_, anyhow::Error>(response)
+                }
+            }))
+        }
+    });
+
+    Ok(
+        hyper::Server::bind(&SocketAddr::new("0.0.0.0".parse().unwrap(), port))
+            .serve(service)
+            .await?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scrape_metrics() {
+        const PORT: u16 = 19464;
+
+        let (events_sender, events_receiver) = broadcast::channel(16);
+        tokio::spawn(prometheus_server(PORT, events_receiver));
+
+        for event_type in [
+            EventType::StatisticsSummary(StatisticsSummary {
+                network_delay_average_ms: 5.0,
+                client_fps: 90.0,
+                ..Default::default()
+            }),
+            EventType::GraphStatistics(GraphStatistics {
+                shard_loss_ratio: 0.25,
+                ..Default::default()
+            }),
+        ] {
+            events_sender
+                .send(Event {
+                    timestamp: String::new(),
+                    event_type,
+                })
+                .unwrap();
+        }
+
+        // The server and the event loop run on the same thread, retry until both are ready
+        let mut text = String::new();
+        for _ in 0..100 {
+            if let Ok(response) = reqwest::get(format!("http://127.0.0.1:{PORT}/metrics")).await {
+                text = response.text().await.unwrap();
+                if text.contains("alvr_shard_loss_ratio") {
+                    break;
+                }
+            }
+            tokio::task::yield_now().await;
+        }
+
+        assert!(text.contains("alvr_network_latency_seconds 0.005"));
+        assert!(text.contains("alvr_client_fps 90"));
+        assert!(text.contains("# TYPE alvr_shard_loss_ratio gauge"));
+        assert!(text.contains("alvr_shard_loss_ratio 0.25"));
+    }
+}
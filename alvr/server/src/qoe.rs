@@ -0,0 +1,73 @@
+// Linear QoE model: delivered bitrate is rewarded, while dropped/skipped frames (used as a
+// rebuffering proxy) and bitrate switches between consecutive intervals are penalized.
+
+use alvr_session::QoeWeightsConfig;
+
+pub struct QoeScorer {
+    weights: QoeWeightsConfig,
+    last_bitrate_mbps: Option<f32>,
+}
+
+// Unit weights, the same as the setting defaults
+impl Default for QoeScorer {
+    fn default() -> Self {
+        Self::new(QoeWeightsConfig {
+            bitrate: 1.0,
+            rebuffering: 1.0,
+            bitrate_switch: 1.0,
+        })
+    }
+}
+
+impl QoeScorer {
+    pub fn new(weights: QoeWeightsConfig) -> Self {
+        Self {
+            weights,
+            last_bitrate_mbps: None,
+        }
+    }
+
+    // Scores one interval. The first interval has no switch penalty
+    pub fn score_interval(&mut self, bitrate_mbps: f32, rebuffering_frames_per_sec: f32) -> f32 {
+        let bitrate_switch_mbps = self
+            .last_bitrate_mbps
+            .map(|last| (bitrate_mbps - last).abs())
+            .unwrap_or(0.0);
+        self.last_bitrate_mbps = Some(bitrate_mbps);
+
+        self.weights.bitrate * bitrate_mbps
+            - self.weights.rebuffering * rebuffering_frames_per_sec
+            - self.weights.bitrate_switch * bitrate_switch_mbps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_score(intervals: &[(f32, f32)]) -> f32 {
+        let mut scorer = QoeScorer::default();
+
+        intervals
+            .iter()
+            .map(|&(bitrate_mbps, rebuffering)| scorer.score_interval(bitrate_mbps, rebuffering))
+            .sum()
+    }
+
+    #[test]
+    fn test_stable_scores_higher_than_unstable() {
+        let stable = total_score(&[(80.0, 0.0); 6]);
+        // Same average bitrate, but oscillating and with frame drops on the high peaks
+        let unstable = total_score(&[
+            (40.0, 0.0),
+            (120.0, 5.0),
+            (40.0, 0.0),
+            (120.0, 5.0),
+            (40.0, 0.0),
+            (120.0, 5.0),
+        ]);
+
+        assert_eq!(stable, 480.0);
+        assert!(stable > unstable);
+    }
+}
@@ -1,18 +1,162 @@
-use alvr_common::{SlidingWindowAverage, SlidingWindowTimely, SlidingWindowWeighted, HEAD_ID};
+#[cfg(feature = "frame-tracing")]
+use crate::frame_tracing::{FrameTracer, SpanExporter};
+use crate::{
+    clock::{Clock, SystemClock},
+    qoe::QoeScorer,
+    stats_sink::{EventsSink, StatsSink},
+};
+use alvr_common::{
+    warn, DelayKalmanFilter, Histogram, SlidingWindowAverage, SlidingWindowSum,
+    SlidingWindowTimely, SlidingWindowWeighted, HEAD_ID,
+};
 use alvr_events::{
-    EventType, GraphNetworkStatistics, GraphStatistics, NominalBitrateStats, StatisticsSummary,
+    EventType, GraphNetworkStatistics, GraphStatistics, LatencyBudgetAlarm, LatencyStage,
+    NominalBitrateStats, StatisticsSummary,
 };
 use alvr_packets::{ClientStatistics, NetworkStatisticsPacket};
+use alvr_session::{
+    LatencyBudgetsConfig, NetworkLatencyEstimator, OwdKalmanFilterConfig, QoeWeightsConfig,
+};
 use std::{
     collections::{HashMap, VecDeque},
+    io::Write,
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
-const FULL_REPORT_INTERVAL: Duration = Duration::from_millis(500);
+// Identifies a client across the per-client statistics state
+pub type ClientId = u64;
+
+const DEFAULT_FULL_REPORT_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_STALL_THRESHOLD_MULTIPLIER: f32 = 3.0;
+const DEFAULT_LATENCY_HISTOGRAM_BOUNDS_MS: [f32; 11] = [
+    5.0, 10.0, 15.0, 20.0, 30.0, 40.0, 50.0, 75.0, 100.0, 150.0, 200.0,
+];
+const NETWORK_LATENCY_UNDERFLOW_WARN_INTERVAL: Duration = Duration::from_secs(5);
+const CLOCK_SKEW_SMOOTHING: f32 = 0.05;
+const CLOCK_SKEW_WARN_THRESHOLD: Duration = Duration::from_millis(10);
+const CLOCK_SKEW_WARN_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_FRAME_PACING_SAMPLES: usize = 10;
+const MIN_NOMINAL_FRAME_INTERVAL: Duration = Duration::from_millis(1);
+// The vsync queue changes in steps when the client runtime changes its queue depth, a short window
+// lets the tracker prediction follow them quickly
+const PREDICTION_VSYNC_QUEUE_WINDOW_SIZE: usize = 8;
+// Variances of the one-way delay filter until set_owd_kalman_filter() is called, the same as the
+// setting defaults of 1 ms/s and 2 ms
+const DEFAULT_OWD_PROCESS_NOISE: f32 = 1E-6;
+const DEFAULT_OWD_MEASUREMENT_NOISE: f32 = 4E-6;
+// Graph statistics queued for a subscriber that doesn't keep up, newer ones are dropped
+const GRAPH_STATS_SUBSCRIBER_CAPACITY: usize = 256;
+const MAX_LINK_UTILIZATION: f32 = 2.0;
+const SHARD_TIMELINE_HEADER: &str = "frame_index,target_timestamp_ns,expected_shards,rx_shard_counter,highest_rx_shard_index,shards_lost";
+
+pub fn client_id(hostname: &str) -> ClientId {
+    alvr_common::hash_string(hostname)
+}
+
+// A client that couldn't measure a frame sends its statistics with every field but the frame
+// identifiers left to default
+fn is_defaulted_client_stats(client_stats: &ClientStatistics) -> bool {
+    client_stats.frame_interval.is_zero()
+        && client_stats.video_decode.is_zero()
+        && client_stats.video_decoder_queue.is_zero()
+        && client_stats.rendering.is_zero()
+        && client_stats.vsync_queue.is_zero()
+        && client_stats.total_pipeline_latency.is_zero()
+        && client_stats.frames_dropped == 0
+        && client_stats.reordered_shard_counter == 0
+}
+
+// Throughput over the span between the first and the last packet of the frame
+pub fn peak_network_throughput_bps(network_stats: &NetworkStatisticsPacket) -> f32 {
+    if network_stats.frame_span != 0.0 {
+        network_stats.bytes_in_frame as f32 * 8.0 / network_stats.frame_span
+    } else {
+        0.0
+    }
+}
+
+// Like the peak network throughput, but counting only the unique video payload: the application
+// bytes of the frame minus the share of duplicated shards
+pub fn peak_goodput_bps(network_stats: &NetworkStatisticsPacket) -> f32 {
+    if network_stats.frame_span == 0.0 || network_stats.rx_shard_counter == 0 {
+        return 0.0;
+    }
+
+    let unique_shard_ratio = network_stats
+        .rx_shard_counter
+        .saturating_sub(network_stats.duplicated_shard_counter) as f32
+        / network_stats.rx_shard_counter as f32;
+
+    network_stats.bytes_in_frame_app as f32 * unique_shard_ratio * 8.0 / network_stats.frame_span
+}
+
+// The received throughput can exceed the peak throughput average when the frames get bigger
+// faster than the average follows
+fn link_utilization(throughput_bps: f32, capacity_bps: f32) -> f32 {
+    if capacity_bps > 0.0 {
+        (throughput_bps / capacity_bps).clamp(0.0, MAX_LINK_UTILIZATION)
+    } else {
+        0.0
+    }
+}
+
+// Fraction of the received bytes that are not video payload (prefixes and network headers). Both
+// byte counts span the same frame, so the ratio equals the application over network throughput
+fn protocol_overhead_ratio(network_bytes: u32, application_bytes: u32) -> f32 {
+    if network_bytes != 0 {
+        1.0 - application_bytes as f32 / network_bytes as f32
+    } else {
+        0.0
+    }
+}
+
+// Signed distance from one frame index to another, modulo 2^32. Positive when `to` is newer than
+// `from`, as long as the two frames are less than 2^31 frames apart. This keeps the comparisons
+// correct when the frame counter wraps around in long sessions
+fn frame_index_delta(from: u32, to: u32) -> i32 {
+    to.wrapping_sub(from) as i32
+}
+
+// Signed difference in seconds, the instants can be in any order
+fn signed_secs_between(from: Instant, to: Instant) -> f32 {
+    if to >= from {
+        (to - from).as_secs_f32()
+    } else {
+        -(from - to).as_secs_f32()
+    }
+}
+
+// Backstop for frames whose shards are never acknowledged by the client
+const MAX_SHARDS_PER_FRAME_ENTRIES: usize = 1024;
+
+// A report for a frame at most this much older than the highest reported frame is a reordered
+// report. Older reports restart the loss estimation from their frame
+const MAX_REORDERED_FRAMES: i32 = 8;
+
+// Stage latencies of a single frame, as computed when its client statistics are reported
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LatencyBreakdown {
+    pub total_pipeline: Duration,
+    pub game_time: Duration,
+    pub server_compositor: Duration,
+    pub encoder: Duration,
+    pub network: Duration,
+    pub decoder: Duration,
+    pub decoder_queue: Duration,
+    pub rendering: Duration,
+    pub vsync_queue: Duration,
+}
 
 #[derive(Clone)]
 pub struct HistoryFrame {
     target_timestamp: Duration,
+    // Increases with each inserted frame. Distinct frames can share a target timestamp at low
+    // timestamp precision, the lookups pick the most recent one
+    sequence: u64,
 
     tracking_received: Instant,
     frame_present: Instant,
@@ -23,8 +167,60 @@ pub struct HistoryFrame {
     frame_index: i32,
     is_idr: bool,
 
+    is_presented: bool,
     is_composed: bool,
     is_encoded: bool,
+
+    latency_breakdown: Option<LatencyBreakdown>,
+}
+
+impl LatencyBreakdown {
+    fn non_network_latency(&self) -> Duration {
+        self.game_time
+            + self.server_compositor
+            + self.encoder
+            + self.decoder
+            + self.decoder_queue
+            + self.rendering
+            + self.vsync_queue
+    }
+
+    // How much the other stages exceed the total latency. The network latency is clamped to zero
+    // in this case
+    pub fn network_underflow(&self) -> Duration {
+        self.non_network_latency()
+            .saturating_sub(self.total_pipeline)
+    }
+}
+
+// The network latency cannot be estimated directly. It is what's left of the total latency after
+// subtracting all other latency intervals. In particular it contains the transport latency of the
+// tracking packet and the interval between the first video packet is sent and the last video
+// packet is received for a specific frame
+fn compute_breakdown(frame: &HistoryFrame, client_stats: &ClientStatistics) -> LatencyBreakdown {
+    let mut breakdown = LatencyBreakdown {
+        total_pipeline: client_stats.total_pipeline_latency,
+        game_time: frame
+            .frame_present
+            .saturating_duration_since(frame.tracking_received),
+        server_compositor: frame
+            .frame_composed
+            .saturating_duration_since(frame.frame_present),
+        encoder: frame
+            .frame_encoded
+            .saturating_duration_since(frame.frame_composed),
+        network: Duration::ZERO,
+        decoder: client_stats.video_decode,
+        decoder_queue: client_stats.video_decoder_queue,
+        rendering: client_stats.rendering,
+        vsync_queue: client_stats.vsync_queue,
+    };
+
+    breakdown.network = breakdown
+        .total_pipeline
+        .saturating_sub(breakdown.non_network_latency());
+
+    breakdown
 }
 
 impl Default for HistoryFrame {
@@ -32,6 +228,7 @@ impl Default for HistoryFrame {
         let now = Instant::now();
         Self {
             target_timestamp: Duration::ZERO,
+            sequence: 0,
 
             tracking_received: now,
             frame_present: now,
@@ -42,8 +239,11 @@ impl Default for HistoryFrame {
             frame_index: -1,
             is_idr: false,
 
+            is_presented: false,
             is_composed: false,
             is_encoded: false,
+
+            latency_breakdown: None,
         }
     }
 }
@@ -54,10 +254,72 @@ struct BatteryData {
     is_plugged: bool,
 }
 
-pub struct StatisticsManager {
+// Frame history and shard loss estimator state, kept separately for each client
+struct ClientFrameState {
     history_buffer: VecDeque<HistoryFrame>,
+    stats_history_buffer: VecDeque<HistoryFrame>,
+    map_frames_spf: HashMap<u32, usize>,
+    next_frame_sequence: u64,
+
+    prev_highest_shard: i32,
+    prev_highest_frame: u32,
+
+    // Counters reset on every report_statistics call, used for the instantaneous loss ratios
+    interval_shards_lost: usize,
+    interval_shards_received: usize,
+    interval_frames_presented: usize,
+
+    // Arrival of the last shard of the previous frame on the client. Only the client measured
+    // interarrival times advance it, the first network statistics set the origin
+    last_shard_arrival: Option<Instant>,
+    // (sent, arrival of the first shard) of the previous frame
+    last_frame_arrival: Option<(Instant, Instant)>,
+    rtp_jitter_s: f32,
+
+    // From the last network statistics, None until the clock skew is estimated
+    owd_network_latency: Option<Duration>,
+}
+
+impl Default for ClientFrameState {
+    fn default() -> Self {
+        Self {
+            history_buffer: VecDeque::new(),
+            stats_history_buffer: VecDeque::new(),
+            map_frames_spf: HashMap::new(),
+            next_frame_sequence: 0,
+            prev_highest_shard: -1,
+            prev_highest_frame: 0,
+            interval_shards_lost: 0,
+            interval_shards_received: 0,
+            interval_frames_presented: 0,
+            last_shard_arrival: None,
+            last_frame_arrival: None,
+            rtp_jitter_s: 0.0,
+            owd_network_latency: None,
+        }
+    }
+}
+
+impl ClientFrameState {
+    // Interarrival jitter of RFC 3550, section 6.4.1. The first frame only sets the reference
+    // transit time
+    fn report_frame_arrival(&mut self, sent: Instant, arrival: Instant) {
+        if let Some((prev_sent, prev_arrival)) = self.last_frame_arrival {
+            let transit_delta =
+                signed_secs_between(prev_arrival, arrival) - signed_secs_between(prev_sent, sent);
+            self.rtp_jitter_s += (transit_delta.abs() - self.rtp_jitter_s) / 16.0;
+        }
+
+        self.last_frame_arrival = Some((sent, arrival));
+    }
+}
+
+pub struct StatisticsManager {
+    clients: HashMap<ClientId, ClientFrameState>,
     max_history_size: usize,
+    averaging_window_size: usize,
 
+    full_report_interval: Duration,
     last_full_report_instant: Instant,
     last_nominal_bitrate_stats: NominalBitrateStats,
 
@@ -66,22 +328,36 @@ pub struct StatisticsManager {
 
     last_vsync_time: Instant,
 
-    video_packets_total: usize,
+    // A longer gap between presented frames is a pause, not a frame interval
+    idle_timeout: Option<Duration>,
+
+    // Session totals are u64 so that they don't overflow on 32-bit targets during long sessions
+    video_packets_total: u64,
     video_packets_partial_sum: usize,
 
-    video_bytes_total: usize,
-    video_bytes_partial_sum: usize,
+    video_bytes_total: u64,
+    // Covers the last summary interval regardless of when the summary is actually reported
+    video_bytes_window: SlidingWindowSum,
+    // Reset on every summary
+    video_bps_partial_peak: f32,
+    last_frame_encoded_instant: Option<Instant>,
 
     received_video_bytes_partial_sum: f32,
+    // Bytes received by the clients, probe shards included, and probe bytes sent over the same
+    // window
+    received_bytes_window: SlidingWindowSum,
+    probe_bytes_window: SlidingWindowSum,
 
     frame_interarrival_partial_sum: f32,
 
-    packets_dropped_total: usize,
+    packets_dropped_total: u64,
     packets_dropped_partial_sum: usize,
 
-    packets_skipped_total: usize,
+    packets_skipped_total: u64,
     packets_skipped_partial_sum: usize,
 
+    frames_presented_partial_sum: usize,
+
     battery_gauges: HashMap<u64, BatteryData>,
     steamvr_pipeline_latency: Duration,
 
@@ -95,6 +371,9 @@ pub struct StatisticsManager {
     decoder_queue_delay_average: SlidingWindowAverage<Duration>,
     client_compositor_average: SlidingWindowAverage<Duration>,
     vsync_queue_delay_average: SlidingWindowAverage<Duration>,
+    // Used by tracker_pose_time_offset()
+    pipeline_latency_before_vsync_average: SlidingWindowAverage<Duration>,
+    prediction_vsync_queue_average: SlidingWindowAverage<Duration>,
 
     frame_interval: Duration,
 
@@ -102,6 +381,12 @@ pub struct StatisticsManager {
     client_frame_interval_average: SlidingWindowAverage<Duration>,
 
     frame_interarrival_average: SlidingWindowAverage<f32>,
+    peak_throughput_average: SlidingWindowAverage<f32>,
+    actual_bitrate_average: SlidingWindowAverage<f32>,
+    // Encoded frame sizes in bytes. Created by the first frame of each kind, so that no initial
+    // value biases the average
+    idr_bytes_average: Option<SlidingWindowAverage<f32>>,
+    delta_bytes_average: Option<SlidingWindowAverage<f32>>,
 
     server_frames_moving: SlidingWindowTimely<f32>,
     client_frames_moving: SlidingWindowTimely<f32>,
@@ -110,41 +395,132 @@ pub struct StatisticsManager {
     interval_avg_plot_throughput: f32,
     instant_weighted_avg_prev: Instant,
 
-    prev_highest_shard: i32,
-    prev_highest_frame: i32,
+    is_first_stats: bool,
+    frame_interarrival_outlier_multiplier: Option<f32>,
 
-    stats_history_buffer: VecDeque<HistoryFrame>,
-    map_frames_spf: HashMap<u32, usize>,
+    qoe_scorer: QoeScorer,
 
-    is_first_stats: bool,
+    stall_threshold_multiplier: f32,
+    stall_detected: bool,
+    stall_count_total: usize,
+    stall_duration_total: Duration,
+
+    // Frames whose other latency intervals add up to more than the total pipeline latency
+    network_latency_underflow_count: usize,
+    network_latency_underflow_total: Duration,
+    last_network_latency_underflow_warn: Option<Instant>,
+
+    // Frames whose present offset was larger than the time since the tracking was received
+    frame_present_offset_clamp_count: usize,
+
+    // Moving average of the filtered one-way delay in excess of half the round trip time. With
+    // symmetric paths this is the offset between the client and server clocks
+    clock_skew_estimate_s: Option<f32>,
+    last_clock_skew_warn: Option<Instant>,
+
+    // Trend of the filtered one-way delay reported by the client
+    owd_filter: DelayKalmanFilter,
+    last_owd_sample_instant: Option<Instant>,
+
+    // Cumulative since the start of the stream, in milliseconds
+    total_pipeline_latency_histogram: Histogram,
+    network_latency_histogram: Histogram,
+
+    // Last emitted graph statistics, oldest first, used to backfill newly connected consumers
+    recent_graph_stats: VecDeque<GraphStatistics>,
+    recent_graph_stats_capacity: usize,
+    graph_stats_subscribers: Vec<SyncSender<GraphStatistics>>,
+    // Target timestamp of the first frame with graph statistics, the origin of graph_time_ns
+    graph_time_origin: Option<Duration>,
+
+    sink: Arc<dyn StatsSink>,
+    clock: Arc<dyn Clock>,
+
+    #[cfg(feature = "frame-tracing")]
+    frame_tracer: FrameTracer,
+
+    // CSV, one row per network statistics report
+    shard_timeline: Option<Box<dyn Write + Send>>,
+
+    latency_budgets: Option<LatencyBudgetsConfig>,
+    last_latency_alarms: HashMap<LatencyStage, Instant>,
+
+    network_latency_estimator: NetworkLatencyEstimator,
 }
 
 impl StatisticsManager {
-    // history size used to calculate average total pipeline latency
+    // The history size bounds the frame history buffers, the averaging window sizes the latency
+    // averages. Sizes of zero are raised to 1 and the nominal frame interval to at least 1 ms
     pub fn new(
         max_history_size: usize,
+        averaging_window_size: usize,
+        nominal_server_frame_interval: Duration,
+        steamvr_pipeline_frames: f32,
+    ) -> Self {
+        Self::with_clock(
+            max_history_size,
+            averaging_window_size,
+            nominal_server_frame_interval,
+            steamvr_pipeline_frames,
+            Arc::new(SystemClock),
+        )
+    }
+
+    // Averages over the whole history, as before the averaging window could be set
+    pub fn with_history_size(
+        max_history_size: usize,
+        nominal_server_frame_interval: Duration,
+        steamvr_pipeline_frames: f32,
+    ) -> Self {
+        Self::new(
+            max_history_size,
+            max_history_size,
+            nominal_server_frame_interval,
+            steamvr_pipeline_frames,
+        )
+    }
+
+    pub fn with_clock(
+        max_history_size: usize,
+        averaging_window_size: usize,
         nominal_server_frame_interval: Duration,
         steamvr_pipeline_frames: f32,
+        clock: Arc<dyn Clock>,
     ) -> Self {
+        let now = clock.now();
+
+        let max_history_size = max_history_size.max(1);
+        let averaging_window_size = averaging_window_size.max(1);
+        let nominal_server_frame_interval =
+            nominal_server_frame_interval.max(MIN_NOMINAL_FRAME_INTERVAL);
+
         Self {
-            history_buffer: VecDeque::new(),
+            clients: HashMap::new(),
             max_history_size,
+            averaging_window_size,
 
-            last_full_report_instant: Instant::now(),
+            full_report_interval: DEFAULT_FULL_REPORT_INTERVAL,
+            last_full_report_instant: now,
             last_nominal_bitrate_stats: NominalBitrateStats::default(),
 
-            last_frame_present_instant: Instant::now(),
+            last_frame_present_instant: now,
             last_frame_present_interval: Duration::ZERO,
 
-            last_vsync_time: Instant::now(),
+            last_vsync_time: now,
+
+            idle_timeout: None,
 
             video_packets_total: 0,
             video_packets_partial_sum: 0,
 
             video_bytes_total: 0,
-            video_bytes_partial_sum: 0,
+            video_bytes_window: SlidingWindowSum::new(DEFAULT_FULL_REPORT_INTERVAL),
+            video_bps_partial_peak: 0.0,
+            last_frame_encoded_instant: None,
 
             received_video_bytes_partial_sum: 0.,
+            received_bytes_window: SlidingWindowSum::new(DEFAULT_FULL_REPORT_INTERVAL),
+            probe_bytes_window: SlidingWindowSum::new(DEFAULT_FULL_REPORT_INTERVAL),
 
             frame_interarrival_partial_sum: 0.,
 
@@ -154,6 +530,8 @@ impl StatisticsManager {
             packets_skipped_total: 0,
             packets_skipped_partial_sum: 0,
 
+            frames_presented_partial_sum: 0,
+
             battery_gauges: HashMap::new(),
             steamvr_pipeline_latency: Duration::from_secs_f32(
                 steamvr_pipeline_frames * nominal_server_frame_interval.as_secs_f32(),
@@ -161,155 +539,491 @@ impl StatisticsManager {
 
             total_pipeline_latency_average: SlidingWindowAverage::new(
                 Duration::ZERO,
-                max_history_size,
+                averaging_window_size,
+            ),
+            game_delay_average: SlidingWindowAverage::new(Duration::ZERO, averaging_window_size),
+            server_compositor_average: SlidingWindowAverage::new(
+                Duration::ZERO,
+                averaging_window_size,
             ),
-            game_delay_average: SlidingWindowAverage::new(Duration::ZERO, max_history_size),
-            server_compositor_average: SlidingWindowAverage::new(Duration::ZERO, max_history_size),
-            encode_delay_average: SlidingWindowAverage::new(Duration::ZERO, max_history_size),
-            network_delay_average: SlidingWindowAverage::new(Duration::ZERO, max_history_size),
-            decode_delay_average: SlidingWindowAverage::new(Duration::ZERO, max_history_size),
+            encode_delay_average: SlidingWindowAverage::new(Duration::ZERO, averaging_window_size),
+            network_delay_average: SlidingWindowAverage::new(Duration::ZERO, averaging_window_size),
+            decode_delay_average: SlidingWindowAverage::new(Duration::ZERO, averaging_window_size),
             decoder_queue_delay_average: SlidingWindowAverage::new(
                 Duration::ZERO,
-                max_history_size,
+                averaging_window_size,
+            ),
+            client_compositor_average: SlidingWindowAverage::new(
+                Duration::ZERO,
+                averaging_window_size,
+            ),
+            vsync_queue_delay_average: SlidingWindowAverage::new(
+                Duration::ZERO,
+                averaging_window_size,
+            ),
+            pipeline_latency_before_vsync_average: SlidingWindowAverage::new(
+                Duration::ZERO,
+                averaging_window_size,
+            ),
+            prediction_vsync_queue_average: SlidingWindowAverage::new(
+                Duration::ZERO,
+                PREDICTION_VSYNC_QUEUE_WINDOW_SIZE,
             ),
-            client_compositor_average: SlidingWindowAverage::new(Duration::ZERO, max_history_size),
-            vsync_queue_delay_average: SlidingWindowAverage::new(Duration::ZERO, max_history_size),
 
             frame_interval: nominal_server_frame_interval,
 
             frame_interval_average: SlidingWindowAverage::new(
                 Duration::from_millis(16),
-                max_history_size,
+                averaging_window_size,
             ),
             client_frame_interval_average: SlidingWindowAverage::new(
                 Duration::from_millis(16),
-                max_history_size,
+                averaging_window_size,
             ),
 
-            frame_interarrival_average: SlidingWindowAverage::new(0., max_history_size),
+            frame_interarrival_average: SlidingWindowAverage::new(0., averaging_window_size),
+            peak_throughput_average: SlidingWindowAverage::new(0., averaging_window_size),
+            actual_bitrate_average: SlidingWindowAverage::new(0., averaging_window_size),
+            idr_bytes_average: None,
+            delta_bytes_average: None,
 
             server_frames_moving: SlidingWindowTimely::new(60., 16., 1.),
             client_frames_moving: SlidingWindowTimely::new(60., 16., 1.),
 
             history_throughput_weighted: SlidingWindowWeighted::new(0., 0.0),
-            instant_weighted_avg_prev: Instant::now(),
+            instant_weighted_avg_prev: now,
             interval_avg_plot_throughput: 0. as f32,
 
-            prev_highest_shard: -1,
-            prev_highest_frame: 0,
+            is_first_stats: true,
+            frame_interarrival_outlier_multiplier: None,
 
-            stats_history_buffer: VecDeque::new(),
-            map_frames_spf: HashMap::new(),
+            qoe_scorer: QoeScorer::default(),
 
-            is_first_stats: true,
+            stall_threshold_multiplier: DEFAULT_STALL_THRESHOLD_MULTIPLIER,
+            stall_detected: false,
+            stall_count_total: 0,
+            stall_duration_total: Duration::ZERO,
+
+            network_latency_underflow_count: 0,
+            network_latency_underflow_total: Duration::ZERO,
+            last_network_latency_underflow_warn: None,
+
+            frame_present_offset_clamp_count: 0,
+
+            clock_skew_estimate_s: None,
+            last_clock_skew_warn: None,
+
+            owd_filter: DelayKalmanFilter::new(
+                DEFAULT_OWD_PROCESS_NOISE,
+                DEFAULT_OWD_MEASUREMENT_NOISE,
+            ),
+            last_owd_sample_instant: None,
+
+            total_pipeline_latency_histogram: Histogram::new(
+                DEFAULT_LATENCY_HISTOGRAM_BOUNDS_MS.to_vec(),
+            ),
+            network_latency_histogram: Histogram::new(DEFAULT_LATENCY_HISTOGRAM_BOUNDS_MS.to_vec()),
+
+            recent_graph_stats: VecDeque::new(),
+            recent_graph_stats_capacity: max_history_size,
+            graph_time_origin: None,
+            graph_stats_subscribers: vec![],
+
+            sink: Arc::new(EventsSink),
+            clock,
+
+            #[cfg(feature = "frame-tracing")]
+            frame_tracer: FrameTracer::new(max_history_size),
+
+            shard_timeline: None,
+
+            latency_budgets: None,
+            last_latency_alarms: HashMap::new(),
+
+            network_latency_estimator: NetworkLatencyEstimator::StageSubtraction,
         }
     }
 
-    pub fn report_tracking_received(&mut self, target_timestamp: Duration) {
-        if !self
+    pub fn report_tracking_received(&mut self, client_id: ClientId, target_timestamp: Duration) {
+        let client = self.clients.entry(client_id).or_default();
+
+        // Tracking can be received more than once for the same frame. Once the frame has been
+        // presented, the same timestamp belongs to a new frame
+        let existing = client
             .history_buffer
             .iter()
-            .any(|frame| frame.target_timestamp == target_timestamp)
-        {
-            self.history_buffer.push_front(HistoryFrame {
+            .filter(|frame| frame.target_timestamp == target_timestamp)
+            .max_by_key(|frame| frame.sequence);
+        let insert = match existing {
+            Some(frame) if frame.is_presented => {
+                warn!("Target timestamp {target_timestamp:?} is shared by more than one frame");
+
+                true
+            }
+            Some(_) => false,
+            None => true,
+        };
+
+        if insert {
+            client.history_buffer.push_front(HistoryFrame {
                 target_timestamp,
-                tracking_received: Instant::now(),
+                sequence: client.next_frame_sequence,
+                tracking_received: self.clock.now(),
                 ..Default::default()
             });
+            client.next_frame_sequence += 1;
+        }
+
+        if client.history_buffer.len() > self.max_history_size {
+            client.history_buffer.pop_back();
         }
 
-        if self.history_buffer.len() > self.max_history_size {
-            self.history_buffer.pop_back();
+        #[cfg(feature = "frame-tracing")]
+        {
+            self.frame_tracer.start_span(target_timestamp);
+            self.frame_tracer
+                .add_event(target_timestamp, "tracking_received", &[]);
         }
     }
 
-    pub fn report_frame_present(&mut self, target_timestamp: Duration, offset: Duration) {
-        if let Some(frame) = self
+    pub fn report_frame_present(
+        &mut self,
+        client_id: ClientId,
+        target_timestamp: Duration,
+        offset: Duration,
+    ) {
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return;
+        };
+
+        if let Some(frame) = client
             .history_buffer
             .iter_mut()
-            .find(|frame| frame.target_timestamp == target_timestamp)
+            .filter(|frame| frame.target_timestamp == target_timestamp)
+            .max_by_key(|frame| frame.sequence)
         {
-            let now = Instant::now() - offset;
+            // An offset reaching back before the tracking was received would zero the game latency
+            // and shift the rest of the breakdown
+            let now = match self.clock.now().checked_sub(offset) {
+                Some(now) if now >= frame.tracking_received => now,
+                _ => {
+                    self.frame_present_offset_clamp_count += 1;
+
+                    frame.tracking_received
+                }
+            };
 
             let interval = now.saturating_duration_since(self.last_frame_present_instant);
-
-            self.last_frame_present_interval = interval;
             self.last_frame_present_instant = now;
 
+            // After a pause the vsync phase and the frame pacing restart from this frame
+            let resumed = self.idle_timeout.is_some_and(|timeout| interval > timeout);
+            if resumed {
+                self.last_vsync_time = now;
+            }
+            self.last_frame_present_interval = if resumed {
+                self.frame_interval
+            } else {
+                interval
+            };
+
             frame.frame_present = now;
+            frame.is_presented = true;
+
+            #[cfg(feature = "frame-tracing")]
+            self.frame_tracer.add_event(
+                target_timestamp,
+                "frame_present",
+                &[(
+                    "game_time",
+                    now.saturating_duration_since(frame.tracking_received),
+                )],
+            );
+
+            client.interval_frames_presented += 1;
+            self.frames_presented_partial_sum += 1;
 
-            self.frame_interval_average
-                .submit_sample(self.last_frame_present_interval);
+            if !resumed {
+                self.frame_interval_average
+                    .submit_sample(self.last_frame_present_interval);
 
-            self.server_frames_moving
-                .submit_sample(1., interval.as_secs_f32());
+                self.server_frames_moving
+                    .submit_sample(1., interval.as_secs_f32());
+            }
 
-            self.stats_history_buffer.push_back(frame.clone());
+            client.stats_history_buffer.push_back(frame.clone());
 
-            if self.stats_history_buffer.len() > self.max_history_size {
-                self.stats_history_buffer.pop_front();
+            if client.stats_history_buffer.len() > self.max_history_size {
+                client.stats_history_buffer.pop_front();
             }
         }
     }
 
-    pub fn report_frame_composed(&mut self, target_timestamp: Duration, offset: Duration) {
-        if let Some(frame) = self
+    pub fn report_frame_composed(
+        &mut self,
+        client_id: ClientId,
+        target_timestamp: Duration,
+        offset: Duration,
+    ) {
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return;
+        };
+
+        if let Some(frame) = client
             .stats_history_buffer
             .iter_mut()
-            .find(|frame| frame.target_timestamp == target_timestamp && !frame.is_composed)
+            .filter(|frame| frame.target_timestamp == target_timestamp && !frame.is_composed)
+            .max_by_key(|frame| frame.sequence)
         {
             frame.is_composed = true;
 
-            frame.frame_composed = Instant::now() - offset;
+            frame.frame_composed = self.clock.now() - offset;
+
+            #[cfg(feature = "frame-tracing")]
+            self.frame_tracer.add_event(
+                target_timestamp,
+                "frame_composed",
+                &[(
+                    "server_compositor",
+                    frame
+                        .frame_composed
+                        .saturating_duration_since(frame.frame_present),
+                )],
+            );
         }
     }
 
     // returns encoding interval
     pub fn report_frame_encoded(
         &mut self,
+        client_id: ClientId,
         target_timestamp: Duration,
         bytes_count: usize,
         is_idr: bool,
     ) -> Duration {
         self.video_packets_total += 1;
         self.video_packets_partial_sum += 1;
-        self.video_bytes_total += bytes_count;
-        self.video_bytes_partial_sum += bytes_count;
+        self.video_bytes_total += bytes_count as u64;
+        self.video_bytes_window
+            .submit_sample(bytes_count as f32, self.clock.now());
+
+        // Frames encoded back to back are spread over the nominal frame interval
+        let now = self.clock.now();
+        let frame_interval = self
+            .last_frame_encoded_instant
+            .map(|instant| now.saturating_duration_since(instant))
+            .unwrap_or(self.frame_interval)
+            .max(self.frame_interval);
+        self.video_bps_partial_peak = f32::max(
+            self.video_bps_partial_peak,
+            bytes_count as f32 * 8.0 / frame_interval.as_secs_f32(),
+        );
+        self.last_frame_encoded_instant = Some(now);
 
-        if let Some(frame) = self
-            .stats_history_buffer
-            .iter_mut()
-            .find(|frame| frame.target_timestamp == target_timestamp && !frame.is_encoded)
-        {
+        let bytes_average = if is_idr {
+            &mut self.idr_bytes_average
+        } else {
+            &mut self.delta_bytes_average
+        };
+        match bytes_average {
+            Some(average) => average.submit_sample(bytes_count as f32),
+            None => {
+                *bytes_average = Some(SlidingWindowAverage::new(
+                    bytes_count as f32,
+                    self.averaging_window_size,
+                ))
+            }
+        }
+
+        if let Some(frame) = self.clients.get_mut(&client_id).and_then(|client| {
+            client
+                .stats_history_buffer
+                .iter_mut()
+                .filter(|frame| frame.target_timestamp == target_timestamp && !frame.is_encoded)
+                .max_by_key(|frame| frame.sequence)
+        }) {
             frame.is_idr = is_idr;
             frame.is_encoded = true;
 
-            frame.frame_encoded = Instant::now();
+            frame.frame_encoded = self.clock.now();
 
             frame.video_packet_bytes = bytes_count;
 
-            frame
+            let encoder_latency = frame
                 .frame_encoded
-                .saturating_duration_since(frame.frame_composed)
+                .saturating_duration_since(frame.frame_composed);
+
+            #[cfg(feature = "frame-tracing")]
+            self.frame_tracer.add_event(
+                target_timestamp,
+                "frame_encoded",
+                &[("encoder", encoder_latency)],
+            );
+
+            encoder_latency
         } else {
             Duration::ZERO
         }
     }
 
+    // Bytes of the shards sent only to measure the capacity of the link, as received by the client.
+    // They count towards the capacity estimate but not towards the goodput. The video sender does
+    // not send probe shards yet
+    #[allow(dead_code)]
+    pub fn report_probe_bytes_received(&mut self, bytes_count: usize) {
+        self.probe_bytes_window
+            .submit_sample(bytes_count as f32, self.clock.now());
+    }
+
+    // Received throughput over the last summary interval without the probe traffic. Left out of
+    // GraphStatistics until there is a probe source, it would equal the capacity estimate
+    #[allow(dead_code)]
+    pub fn goodput_bps(&self) -> f32 {
+        let now = self.clock.now();
+
+        f32::max(
+            self.received_bytes_window.get_rate(now) * 8.0
+                - self.probe_bytes_window.get_rate(now) * 8.0,
+            0.0,
+        )
+    }
+
     pub fn report_frame_sent(
         &mut self,
+        client_id: ClientId,
         target_timestamp: Duration,
         frame_index: u32,
         shards_count: usize,
     ) {
-        if let Some(frame) = self
+        let client = self.clients.entry(client_id).or_default();
+
+        if let Some(frame) = client
             .stats_history_buffer
             .iter_mut()
-            .find(|frame| frame.target_timestamp == target_timestamp && frame.frame_index == -1)
+            .filter(|frame| frame.target_timestamp == target_timestamp && frame.frame_index == -1)
+            .max_by_key(|frame| frame.sequence)
         {
             frame.frame_index = frame_index as i32;
         }
-        self.map_frames_spf.insert(frame_index, shards_count);
+        client.map_frames_spf.insert(frame_index, shards_count);
+
+        if client.map_frames_spf.len() > MAX_SHARDS_PER_FRAME_ENTRIES {
+            // Oldest first, relative to the frame just sent
+            let mut frames = client.map_frames_spf.keys().copied().collect::<Vec<_>>();
+            frames.sort_unstable_by_key(|&frame| frame_index_delta(frame_index, frame));
+
+            for frame in &frames[..frames.len() - MAX_SHARDS_PER_FRAME_ENTRIES] {
+                client.map_frames_spf.remove(frame);
+            }
+        }
+    }
+
+    // Also sizes the windows of the byte rates reported with the summary, which restart empty
+    pub fn set_full_report_interval(&mut self, interval: Duration) {
+        self.full_report_interval = interval;
+        self.video_bytes_window = SlidingWindowSum::new(interval);
+        self.received_bytes_window = SlidingWindowSum::new(interval);
+        self.probe_bytes_window = SlidingWindowSum::new(interval);
+    }
+
+    pub fn set_qoe_weights(&mut self, weights: QoeWeightsConfig) {
+        self.qoe_scorer = QoeScorer::new(weights);
+    }
+
+    pub fn set_stall_threshold_multiplier(&mut self, multiplier: f32) {
+        self.stall_threshold_multiplier = multiplier;
+    }
+
+    // Resets both latency histograms
+    pub fn set_latency_histogram_bounds(&mut self, bounds_ms: Vec<f32>) {
+        self.total_pipeline_latency_histogram = Histogram::new(bounds_ms.clone());
+        self.network_latency_histogram = Histogram::new(bounds_ms);
+    }
+
+    // A longer gap between presented frames is treated as a pause. Disabled by default
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    // Writes the header immediately
+    pub fn set_shard_timeline_writer(&mut self, mut writer: Box<dyn Write + Send>) {
+        match writeln!(writer, "{SHARD_TIMELINE_HEADER}") {
+            Ok(()) => self.shard_timeline = Some(writer),
+            Err(e) => warn!("Failed to record the shard timeline: {e}"),
+        }
+    }
+
+    pub fn set_latency_budgets(&mut self, config: LatencyBudgetsConfig) {
+        self.latency_budgets = Some(config);
+    }
+
+    // Both estimates are always reported in the graph statistics
+    pub fn set_network_latency_estimator(&mut self, estimator: NetworkLatencyEstimator) {
+        self.network_latency_estimator = estimator;
+    }
+
+    pub fn set_owd_kalman_filter(&mut self, config: &OwdKalmanFilterConfig) {
+        self.owd_filter.set_noise(
+            (config.process_noise_std_ms / 1000.0).powi(2),
+            (config.measurement_noise_std_ms / 1000.0).powi(2),
+        );
+    }
+
+    // Interarrivals longer than this multiple of the nominal frame interval are clamped before
+    // entering the average that drives the heuristic framerate. Stalls are still detected from the
+    // raw value
+    pub fn set_frame_interarrival_outlier_multiplier(&mut self, multiplier: Option<f32>) {
+        self.frame_interarrival_outlier_multiplier = multiplier;
+    }
+
+    #[allow(dead_code)]
+    pub fn set_sink(&mut self, sink: Arc<dyn StatsSink>) {
+        self.sink = sink;
+    }
+
+    fn emit(&self, event_type: EventType) {
+        self.sink.emit(event_type);
+    }
+
+    // Defaults to the history size
+    pub fn set_recent_graph_stats_capacity(&mut self, capacity: usize) {
+        self.recent_graph_stats_capacity = capacity;
+
+        let excess = self.recent_graph_stats.len().saturating_sub(capacity);
+        self.recent_graph_stats.drain(..excess);
+    }
+
+    // Up to the last n emitted graph statistics, oldest first
+    #[allow(dead_code)]
+    pub fn recent_graph_stats(&self, n: usize) -> Vec<GraphStatistics> {
+        let skip = self.recent_graph_stats.len().saturating_sub(n);
+
+        self.recent_graph_stats.iter().skip(skip).cloned().collect()
+    }
+
+    // In-process alternative to the global events. Every subscriber receives the graph statistics
+    // emitted after subscribing, the ones that don't fit in its queue are dropped. Dropping the
+    // receiver unsubscribes
+    #[allow(dead_code)]
+    pub fn subscribe(&mut self) -> Receiver<GraphStatistics> {
+        let (sender, receiver) = mpsc::sync_channel(GRAPH_STATS_SUBSCRIBER_CAPACITY);
+        self.graph_stats_subscribers.push(sender);
+
+        receiver
+    }
+
+    fn publish_graph_stats(&mut self, graph_stats: &GraphStatistics) {
+        self.graph_stats_subscribers
+            .retain(|sender| match sender.try_send(graph_stats.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    #[cfg(feature = "frame-tracing")]
+    #[allow(dead_code)]
+    pub fn set_span_exporter(&mut self, exporter: Arc<dyn SpanExporter>) {
+        self.frame_tracer.set_exporter(exporter);
     }
 
     pub fn report_battery(&mut self, device_id: u64, gauge_value: f32, is_plugged: bool) {
@@ -323,31 +1037,77 @@ impl StatisticsManager {
         self.last_nominal_bitrate_stats = stats;
     }
 
+    // A long gap between consecutive frames is a visible freeze even if the averages look fine.
+    // The interarrival of the first frame is zero and never counts as a stall
+    fn report_frame_interarrival(&mut self, frame_interarrival: f32) {
+        let threshold = self.frame_interval.as_secs_f32() * self.stall_threshold_multiplier;
+        if frame_interarrival > 0.0 && frame_interarrival > threshold {
+            self.stall_detected = true;
+            self.stall_count_total += 1;
+            self.stall_duration_total += Duration::from_secs_f32(frame_interarrival);
+        }
+    }
+
     // This statistics are reported for every succesfully received frame
+    // Returns the number of shards lost since the previous report
     pub fn report_network_statistics(
         &mut self,
+        client_id: ClientId,
         network_stats: NetworkStatisticsPacket,
         rtt_alt: Duration,
-    ) {
-        self.packets_skipped_total += network_stats.frames_skipped as usize;
+    ) -> usize {
+        self.packets_skipped_total += network_stats.frames_skipped as u64;
         self.packets_skipped_partial_sum += network_stats.frames_skipped as usize;
 
         self.received_video_bytes_partial_sum += network_stats.rx_bytes as f32;
+        self.received_bytes_window
+            .submit_sample(network_stats.rx_bytes as f32, self.clock.now());
 
         self.frame_interarrival_partial_sum += network_stats.frame_interarrival;
 
+        self.report_frame_interarrival(network_stats.frame_interarrival);
+
+        // Without a round trip measurement the one-way delay cannot be split from the skew
+        if rtt_alt != Duration::ZERO {
+            self.report_clock_skew_sample(
+                network_stats.filtered_ow_delay - rtt_alt.as_secs_f32() / 2.0,
+            );
+        }
+
+        let now = self.clock.now();
+        let dt_s = self.last_owd_sample_instant.map_or(0.0, |instant| {
+            now.saturating_duration_since(instant).as_secs_f32()
+        });
+        self.owd_filter
+            .submit_sample(network_stats.filtered_ow_delay, dt_s);
+        self.last_owd_sample_instant = Some(now);
+
+        // The one-way delay measured by the client includes the clock skew. The network latency of
+        // the frame also includes the time to receive all its packets
+        let owd_network_latency = self.clock_skew_estimate_s.map(|skew_s| {
+            Duration::from_secs_f32(
+                (network_stats.filtered_ow_delay - skew_s + network_stats.frame_span).max(0.0),
+            )
+        });
+
         if !self.is_first_stats {
+            let frame_interarrival = match self.frame_interarrival_outlier_multiplier {
+                Some(multiplier) => network_stats
+                    .frame_interarrival
+                    .min(self.frame_interval.as_secs_f32() * multiplier),
+                None => network_stats.frame_interarrival,
+            };
             self.frame_interarrival_average
-                .submit_sample(network_stats.frame_interarrival);
+                .submit_sample(frame_interarrival);
         } else {
             self.is_first_stats = false
         }
 
-        let peak_network_throughput_bps: f32 = if network_stats.frame_span != 0.0 {
-            network_stats.bytes_in_frame as f32 * 8.0 / network_stats.frame_span
-        } else {
-            0.0
-        };
+        let peak_network_throughput_bps = peak_network_throughput_bps(&network_stats);
+        if peak_network_throughput_bps > 0.0 {
+            self.peak_throughput_average
+                .submit_sample(peak_network_throughput_bps);
+        }
 
         let instant_network_throughput_bps: f32 = if network_stats.frame_interarrival != 0.0 {
             network_stats.rx_bytes as f32 * 8.0 / network_stats.frame_interarrival
@@ -360,31 +1120,64 @@ impl StatisticsManager {
             network_stats.frame_interarrival,
         );
 
+        let client = self.clients.entry(client_id).or_default();
+        client.owd_network_latency = owd_network_latency;
+
+        // The frame is sent right after it is encoded. Its arrival is the reception of its first
+        // shard on the client, which trails the last shard by the frame span. The client clock has
+        // an unknown offset from the server clock, but a constant offset cancels out in the transit
+        // differences of the jitter, so the arrivals don't need the skew correction of the one-way
+        // delay
+        let last_shard_arrival = match client.last_shard_arrival {
+            Some(arrival) => {
+                arrival + Duration::from_secs_f32(network_stats.frame_interarrival.max(0.0))
+            }
+            None => now,
+        };
+        client.last_shard_arrival = Some(last_shard_arrival);
+        let first_shard_arrival = last_shard_arrival
+            .checked_sub(Duration::from_secs_f32(network_stats.frame_span.max(0.0)))
+            .unwrap_or(last_shard_arrival);
+
+        let sent = client
+            .stats_history_buffer
+            .iter()
+            .filter(|frame| frame.frame_index == network_stats.frame_index && frame.is_encoded)
+            .max_by_key(|frame| frame.sequence)
+            .map(|frame| frame.frame_encoded);
+        if let Some(sent) = sent {
+            client.report_frame_arrival(sent, first_shard_arrival);
+        }
+
         let mut shards_sent: usize = 0;
         let shards_lost: isize;
 
-        if self.prev_highest_frame == network_stats.highest_rx_frame_index as i32 {
-            if self.prev_highest_shard < network_stats.highest_rx_shard_index as i32 {
+        // The client reports the u32 frame index reinterpreted as i32
+        let highest_rx_frame = network_stats.highest_rx_frame_index as u32;
+        let frame_advance = frame_index_delta(client.prev_highest_frame, highest_rx_frame);
+        let is_reordered = (-MAX_REORDERED_FRAMES..0).contains(&frame_advance);
+
+        if frame_advance == 0 {
+            if client.prev_highest_shard < network_stats.highest_rx_shard_index as i32 {
                 shards_sent =
-                    (network_stats.highest_rx_shard_index - self.prev_highest_shard) as usize;
+                    (network_stats.highest_rx_shard_index - client.prev_highest_shard) as usize;
 
-                self.prev_highest_shard = network_stats.highest_rx_shard_index as i32;
+                client.prev_highest_shard = network_stats.highest_rx_shard_index as i32;
             }
-        } else if self.prev_highest_frame < network_stats.highest_rx_frame_index as i32 {
-            let shards_from_prev = match self.map_frames_spf.get(&(self.prev_highest_frame as u32))
-            {
+        } else if frame_advance > 0 {
+            let shards_from_prev = match client.map_frames_spf.get(&client.prev_highest_frame) {
                 Some(&shards_count_prev) => {
-                    shards_count_prev.saturating_sub((self.prev_highest_shard + 1) as usize)
+                    shards_count_prev.saturating_sub((client.prev_highest_shard + 1) as usize)
                 }
                 None => 0,
             };
 
-            let shards_from_inbetween: usize = self
+            let shards_from_inbetween: usize = client
                 .map_frames_spf
                 .iter()
-                .filter(|&(frame, _)| {
-                    *frame > self.prev_highest_frame as u32
-                        && *frame < network_stats.highest_rx_frame_index as u32
+                .filter(|&(&frame, _)| {
+                    frame_index_delta(client.prev_highest_frame, frame) > 0
+                        && frame_index_delta(frame, highest_rx_frame) > 0
                 })
                 .map(|(_, val)| *val)
                 .sum();
@@ -396,28 +1189,68 @@ impl StatisticsManager {
 
         shards_lost = shards_sent as isize - network_stats.rx_shard_counter as isize;
 
-        self.prev_highest_frame = network_stats.highest_rx_frame_index as i32;
-        self.prev_highest_shard = network_stats.highest_rx_shard_index as i32;
-
-        let keys_to_drop: Vec<_> = self
-            .map_frames_spf
-            .iter()
-            .filter(|&(frame, _)| *frame < self.prev_highest_frame as u32)
-            .map(|(key, _)| *key)
-            .collect();
-
-        for key in keys_to_drop {
-            self.map_frames_spf.remove_entry(&key);
+        if is_reordered {
+            // All the shards of the frame were counted as sent, and the missing ones as lost, when
+            // the newer frame was reported
+            client.interval_shards_lost = client
+                .interval_shards_lost
+                .saturating_sub(network_stats.rx_shard_counter as usize);
+        } else {
+            client.interval_shards_lost += shards_lost.max(0) as usize;
         }
+        client.interval_shards_received += network_stats.rx_shard_counter as usize;
 
-        if Instant::now().duration_since(self.instant_weighted_avg_prev) >= Duration::from_secs(1) {
-            self.instant_weighted_avg_prev = Instant::now();
-            self.interval_avg_plot_throughput = self.history_throughput_weighted.get_average();
+        if !is_reordered {
+            client.prev_highest_frame = highest_rx_frame;
+            client.prev_highest_shard = network_stats.highest_rx_shard_index as i32;
         }
 
-        alvr_events::send_event(EventType::GraphNetworkStatistics(GraphNetworkStatistics {
-            frame_index: network_stats.frame_index as u32,
-
+        if let Some(writer) = &mut self.shard_timeline {
+            let target_timestamp = client
+                .stats_history_buffer
+                .iter()
+                .filter(|frame| frame.frame_index == highest_rx_frame as i32)
+                .max_by_key(|frame| frame.sequence)
+                .map(|frame| frame.target_timestamp.as_nanos().to_string())
+                .unwrap_or_default();
+            let expected_shards = client
+                .map_frames_spf
+                .get(&highest_rx_frame)
+                .map(|shards_count| shards_count.to_string())
+                .unwrap_or_default();
+
+            // Unknown fields are left empty
+            if let Err(e) = writeln!(
+                writer,
+                "{highest_rx_frame},{target_timestamp},{expected_shards},{},{},{shards_lost}",
+                network_stats.rx_shard_counter, network_stats.highest_rx_shard_index,
+            ) {
+                warn!("Stopped recording the shard timeline: {e}");
+                self.shard_timeline = None;
+            }
+        }
+
+        // Older frames are not needed anymore. The highest frame is needed only if some of its
+        // shards are still missing, to count them as sent when the next frame is received
+        let highest_frame = client.prev_highest_frame;
+        let highest_frame_complete = client
+            .map_frames_spf
+            .get(&highest_frame)
+            .map(|&shards_count| (client.prev_highest_shard + 1).max(0) as usize >= shards_count)
+            .unwrap_or(false);
+        client.map_frames_spf.retain(|&frame, _| {
+            frame_index_delta(highest_frame, frame) > 0
+                || (frame == highest_frame && !highest_frame_complete)
+        });
+
+        if now.duration_since(self.instant_weighted_avg_prev) >= Duration::from_secs(1) {
+            self.instant_weighted_avg_prev = now;
+            self.interval_avg_plot_throughput = self.history_throughput_weighted.get_average();
+        }
+
+        self.emit(EventType::GraphNetworkStatistics(GraphNetworkStatistics {
+            frame_index: network_stats.frame_index as u32,
+
             server_fps: 1.
                 / self
                     .server_frames_moving
@@ -449,26 +1282,68 @@ impl StatisticsManager {
 
             instant_network_throughput_bps: instant_network_throughput_bps,
             peak_network_throughput_bps: peak_network_throughput_bps,
+            peak_goodput_bps: peak_goodput_bps(&network_stats),
+            protocol_overhead_ratio: protocol_overhead_ratio(
+                network_stats.bytes_in_frame,
+                network_stats.bytes_in_frame_app,
+            ),
 
             nominal_bitrate: self.last_nominal_bitrate_stats.clone(),
 
             interval_avg_plot_throughput: self.interval_avg_plot_throughput,
         }));
+
+        shards_lost.max(0) as usize
     }
 
-    pub fn report_statistics_summary(&mut self) {
-        let now = Instant::now();
-        if self.last_full_report_instant + FULL_REPORT_INTERVAL < now {
+    // Returns true if a summary has been sent
+    pub fn report_statistics_summary(&mut self) -> bool {
+        let now = self.clock.now();
+        if self.last_full_report_instant + self.full_report_interval < now {
             let interval_secs = now
                 .saturating_duration_since(self.last_full_report_instant)
                 .as_secs_f32();
 
-            alvr_events::send_event(EventType::StatisticsSummary(StatisticsSummary {
+            let video_mbits_per_sec = self.video_bytes_window.get_rate(now) * 8. / 1e6;
+            let requested_bps = self.last_nominal_bitrate_stats.requested_bps.as_bps();
+            let bitrate_achievement_ratio = if requested_bps > 0.0 {
+                video_mbits_per_sec * 1e6 / requested_bps
+            } else {
+                0.0
+            };
+            let qoe_score = self.qoe_scorer.score_interval(
+                video_mbits_per_sec,
+                (self.packets_dropped_partial_sum + self.packets_skipped_partial_sum) as f32
+                    / interval_secs,
+            );
+
+            // Frames the user actually sees: presented by the server and neither skipped nor
+            // dropped on the client
+            let effective_fps = self
+                .frames_presented_partial_sum
+                .saturating_sub(self.packets_dropped_partial_sum + self.packets_skipped_partial_sum)
+                as f32
+                / interval_secs;
+
+            self.emit(EventType::StatisticsSummary(StatisticsSummary {
                 video_packets_total: self.video_packets_total,
                 video_packets_per_sec: (self.video_packets_partial_sum as f32 / interval_secs) as _,
 
-                video_mbytes_total: (self.video_bytes_total as f32 / 1e6) as usize,
-                video_mbits_per_sec: self.video_bytes_partial_sum as f32 * 8. / 1e6 / interval_secs,
+                // Integer division, a f32 cast would lose precision after ~16 TB
+                video_mbytes_total: self.video_bytes_total / 1_000_000,
+                video_mbits_per_sec,
+                video_mbits_per_sec_peak: self.video_bps_partial_peak / 1e6,
+
+                avg_idr_bytes: self
+                    .idr_bytes_average
+                    .as_ref()
+                    .map(|average| average.get_average())
+                    .unwrap_or(0.0),
+                avg_delta_bytes: self
+                    .delta_bytes_average
+                    .as_ref()
+                    .map(|average| average.get_average())
+                    .unwrap_or(0.0),
 
                 video_throughput_mbits_per_sec: self.received_video_bytes_partial_sum as f32 * 8.
                     / 1e6
@@ -528,6 +1403,7 @@ impl StatisticsManager {
                         .get_average()
                         .max(Duration::from_millis(1))
                         .as_secs_f32(),
+                effective_fps,
 
                 battery_hmd: (self
                     .battery_gauges
@@ -542,151 +1418,611 @@ impl StatisticsManager {
                     .cloned()
                     .unwrap_or_default()
                     .is_plugged,
+
+                qoe_score,
+
+                stall_detected: self.stall_detected,
+                stall_count_total: self.stall_count_total,
+                stall_duration_total_s: self.stall_duration_total.as_secs_f32(),
+
+                network_latency_underflow_count: self.network_latency_underflow_count,
+                network_latency_underflow_total_ms: self
+                    .network_latency_underflow_total
+                    .as_secs_f32()
+                    * 1000.,
+
+                server_frame_pacing_cv: self.server_frame_pacing_cv(),
+
+                frame_present_offset_clamp_count: self.frame_present_offset_clamp_count,
+
+                estimated_clock_skew_s: self.clock_skew_estimate_s.unwrap_or(0.0),
+
+                bitrate_achievement_ratio,
             }));
 
             self.video_packets_partial_sum = 0;
-            self.video_bytes_partial_sum = 0;
+
+            self.video_bps_partial_peak = 0.0;
 
             self.received_video_bytes_partial_sum = 0.;
 
             self.frame_interarrival_partial_sum = 0.;
 
             self.packets_dropped_partial_sum = 0;
+            self.packets_skipped_partial_sum = 0;
+            self.frames_presented_partial_sum = 0;
+
+            self.stall_detected = false;
 
             self.last_full_report_instant = now;
+
+            true
+        } else {
+            false
         }
     }
 
-    // This statistics are reported for every succesfully displayed frame
-    // Returns network latency, frame interarrival average
-    pub fn report_statistics(&mut self, client_stats: ClientStatistics) -> (Duration, f32) {
-        if let Some(frame) = self
-            .stats_history_buffer
-            .iter_mut()
-            .find(|frame| frame.frame_index == client_stats.frame_index)
-        {
-            self.packets_dropped_total += client_stats.frames_dropped as usize;
-            self.packets_dropped_partial_sum += client_stats.frames_dropped as usize;
+    // Returns the shard loss ratio and frame loss ratio since the previous call, then resets the
+    // interval counters
+    fn take_loss_ratios(&mut self, client_id: ClientId, frames_dropped: u32) -> (f32, f32) {
+        let client = self.clients.entry(client_id).or_default();
 
-            self.client_frame_interval_average
-                .submit_sample(client_stats.frame_interval);
+        let shards_total = client.interval_shards_lost + client.interval_shards_received;
+        let shard_loss_ratio = if shards_total != 0 {
+            client.interval_shards_lost as f32 / shards_total as f32
+        } else {
+            0.0
+        };
 
-            self.client_frames_moving
-                .submit_sample(1., client_stats.frame_interval.as_secs_f32());
+        let frame_loss_ratio = if client.interval_frames_presented != 0 {
+            frames_dropped as f32 / client.interval_frames_presented as f32
+        } else {
+            0.0
+        };
 
-            let total_pipeline_latency = client_stats.total_pipeline_latency;
+        client.interval_shards_lost = 0;
+        client.interval_shards_received = 0;
+        client.interval_frames_presented = 0;
 
-            let game_time_latency = frame
-                .frame_present
-                .saturating_duration_since(frame.tracking_received);
+        (shard_loss_ratio, frame_loss_ratio)
+    }
 
-            let server_compositor_latency = frame
-                .frame_composed
-                .saturating_duration_since(frame.frame_present);
+    // Coefficient of variation of the frame present interval, independent of the client jitter.
+    // Zero until the window holds enough samples
+    pub fn server_frame_pacing_cv(&self) -> f32 {
+        if self.frame_interval_average.history_buffer_len() < MIN_FRAME_PACING_SAMPLES {
+            return 0.0;
+        }
 
-            let encoder_latency = frame
-                .frame_encoded
-                .saturating_duration_since(frame.frame_composed);
+        self.frame_interval_average.coefficient_of_variation()
+    }
+
+    fn report_network_latency_underflow(&mut self, magnitude: Duration) {
+        self.network_latency_underflow_count += 1;
+        self.network_latency_underflow_total += magnitude;
+
+        let now = self.clock.now();
+        if self
+            .last_network_latency_underflow_warn
+            .map(|last| now >= last + NETWORK_LATENCY_UNDERFLOW_WARN_INTERVAL)
+            .unwrap_or(true)
+        {
+            self.last_network_latency_underflow_warn = Some(now);
 
-            // The network latency cannot be estiamed directly. It is what's left of the total
-            // latency after subtracting all other latency intervals. In particular it contains the
-            // transport latency of the tracking packet and the interval between the first video
-            // packet is sent and the last video packet is received for a specific frame.
-            // For safety, use saturating_sub to avoid a crash if for some reason the network
-            // latency is miscalculated as negative.
-            let network_latency = total_pipeline_latency.saturating_sub(
-                game_time_latency
-                    + server_compositor_latency
-                    + encoder_latency
-                    + client_stats.video_decode
-                    + client_stats.video_decoder_queue
-                    + client_stats.rendering
-                    + client_stats.vsync_queue,
+            warn!(
+                "Network latency underflow by {:.2}ms ({} frames so far)",
+                magnitude.as_secs_f32() * 1000.,
+                self.network_latency_underflow_count
             );
+        }
+    }
 
-            self.total_pipeline_latency_average
-                .submit_sample(total_pipeline_latency);
-            self.game_delay_average.submit_sample(game_time_latency);
-            self.server_compositor_average
-                .submit_sample(server_compositor_latency);
-            self.encode_delay_average.submit_sample(encoder_latency);
-            self.network_delay_average.submit_sample(network_latency);
-            self.decode_delay_average
-                .submit_sample(client_stats.video_decode);
-            self.decoder_queue_delay_average
-                .submit_sample(client_stats.video_decoder_queue);
-            self.client_compositor_average
-                .submit_sample(client_stats.rendering);
-            self.vsync_queue_delay_average
-                .submit_sample(client_stats.vsync_queue);
-
-            /*
-            let client_fps = 1.0
-                / client_stats
-                    .frame_interval
-                    .max(Duration::from_millis(1))
-                    .as_secs_f32();
-            let server_fps = 1.0
-                / self
-                    .last_frame_present_interval
-                    .max(Duration::from_millis(1))
-                    .as_secs_f32();
-            */
+    fn check_latency_budgets(
+        &mut self,
+        client_id: ClientId,
+        frame_index: i32,
+        stage_latencies: [(LatencyStage, Duration); 8],
+    ) {
+        let Some(config) = &self.latency_budgets else {
+            return;
+        };
 
-            let bitrate_bps = if network_latency != Duration::ZERO {
-                frame.video_packet_bytes as f32 * 8.0 / network_latency.as_secs_f32()
-            } else {
-                0.0
+        let now = self.clock.now();
+        let throttle_interval = Duration::from_secs_f32(config.throttle_interval_s);
+
+        for (stage, latency) in stage_latencies {
+            let budget = match stage {
+                LatencyStage::GameTime => &config.game_time_ms,
+                LatencyStage::ServerCompositor => &config.server_compositor_ms,
+                LatencyStage::Encoder => &config.encoder_ms,
+                LatencyStage::Network => &config.network_ms,
+                LatencyStage::Decoder => &config.decoder_ms,
+                LatencyStage::DecoderQueue => &config.decoder_queue_ms,
+                LatencyStage::Rendering => &config.rendering_ms,
+                LatencyStage::VsyncQueue => &config.vsync_queue_ms,
             };
+            let Some(&budget_ms) = budget.as_option() else {
+                continue;
+            };
+
+            let measured_ms = latency.as_secs_f32() * 1000.;
+            if measured_ms <= budget_ms {
+                continue;
+            }
 
-            // todo: use target timestamp in nanoseconds. the dashboard needs to use the first
-            // timestamp as the graph time origin.
-            alvr_events::send_event(EventType::GraphStatistics(GraphStatistics {
-                frame_index: client_stats.frame_index, // added
-                is_idr: frame.is_idr,                  // added
-
-                frames_dropped: client_stats.frames_dropped, // added
-
-                total_pipeline_latency_s: client_stats.total_pipeline_latency.as_secs_f32(),
-                game_time_s: game_time_latency.as_secs_f32(),
-                server_compositor_s: server_compositor_latency.as_secs_f32(),
-                encoder_s: encoder_latency.as_secs_f32(),
-                network_s: network_latency.as_secs_f32(),
-                decoder_s: client_stats.video_decode.as_secs_f32(),
-                decoder_queue_s: client_stats.video_decoder_queue.as_secs_f32(),
-                client_compositor_s: client_stats.rendering.as_secs_f32(),
-                vsync_queue_s: client_stats.vsync_queue.as_secs_f32(),
-
-                // client_fps, // removed
-                // server_fps, // removed
-                nominal_bitrate: self.last_nominal_bitrate_stats.clone(),
-                actual_bitrate_bps: bitrate_bps, // bitrate as computed by ALVR
+            if self
+                .last_latency_alarms
+                .get(&stage)
+                .map(|&last| now < last + throttle_interval)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            self.last_latency_alarms.insert(stage, now);
+
+            self.emit(EventType::LatencyBudgetAlarm(LatencyBudgetAlarm {
+                client_id,
+                frame_index,
+                stage,
+                measured_ms,
+                budget_ms,
             }));
+        }
+    }
 
-            self.report_statistics_summary();
+    fn report_clock_skew_sample(&mut self, sample_s: f32) {
+        let estimate_s = match self.clock_skew_estimate_s {
+            Some(estimate_s) => estimate_s + CLOCK_SKEW_SMOOTHING * (sample_s - estimate_s),
+            None => sample_s,
+        };
+        self.clock_skew_estimate_s = Some(estimate_s);
+
+        let now = self.clock.now();
+        if estimate_s.abs() > CLOCK_SKEW_WARN_THRESHOLD.as_secs_f32()
+            && self
+                .last_clock_skew_warn
+                .map(|last| now >= last + CLOCK_SKEW_WARN_INTERVAL)
+                .unwrap_or(true)
+        {
+            self.last_clock_skew_warn = Some(now);
 
-            return (
-                network_latency,
-                self.frame_interarrival_average.get_average(),
+            warn!(
+                "Client clock skewed by {:.2}ms, the latency breakdown may be inaccurate",
+                estimate_s * 1000.
             );
+        }
+    }
+
+    // This statistics are reported for every succesfully displayed frame
+    // Returns network latency, frame interarrival average and shard loss ratio. Returns None if the
+    // frame is not found in the history
+    pub fn report_statistics(
+        &mut self,
+        client_id: ClientId,
+        client_stats: ClientStatistics,
+    ) -> Option<(Duration, f32, f32)> {
+        // Reports for frames missing from the history are common (the history is short and the
+        // frames are reported only once displayed). Return before touching any state
+        let frame = self.clients.get(&client_id).and_then(|client| {
+            client
+                .stats_history_buffer
+                .iter()
+                .find(|frame| frame.frame_index == client_stats.frame_index)
+                .cloned()
+        })?;
+
+        self.packets_dropped_total += client_stats.frames_dropped as u64;
+        self.packets_dropped_partial_sum += client_stats.frames_dropped as usize;
+
+        self.client_frame_interval_average
+            .submit_sample(client_stats.frame_interval);
+
+        self.client_frames_moving
+            .submit_sample(1., client_stats.frame_interval.as_secs_f32());
+
+        let breakdown = compute_breakdown(&frame, &client_stats);
+        let LatencyBreakdown {
+            total_pipeline: total_pipeline_latency,
+            game_time: game_time_latency,
+            server_compositor: server_compositor_latency,
+            encoder: encoder_latency,
+            network: network_latency,
+            ..
+        } = breakdown;
+
+        // A negative network latency points to a measurement or clock problem. It is clamped to
+        // zero, and counted so that it doesn't go unnoticed
+        let network_underflow = breakdown.network_underflow();
+        if !network_underflow.is_zero() {
+            self.report_network_latency_underflow(network_underflow);
+        }
+
+        let owd_network_latency = self
+            .clients
+            .get(&client_id)
+            .and_then(|client| client.owd_network_latency);
+
+        // Used for the averages and the bitrate. The breakdown keeps the stage subtraction so that
+        // the stages add up to the total latency
+        let estimated_network_latency = match self.network_latency_estimator {
+            NetworkLatencyEstimator::StageSubtraction => network_latency,
+            NetworkLatencyEstimator::OneWayDelay => owd_network_latency.unwrap_or(network_latency),
+        };
+
+        if let Some(frame) = self.clients.get_mut(&client_id).and_then(|client| {
+            client
+                .stats_history_buffer
+                .iter_mut()
+                .find(|frame| frame.frame_index == client_stats.frame_index)
+        }) {
+            frame.latency_breakdown = Some(breakdown);
+        }
+
+        self.check_latency_budgets(
+            client_id,
+            client_stats.frame_index,
+            [
+                (LatencyStage::GameTime, game_time_latency),
+                (LatencyStage::ServerCompositor, server_compositor_latency),
+                (LatencyStage::Encoder, encoder_latency),
+                (LatencyStage::Network, network_latency),
+                (LatencyStage::Decoder, client_stats.video_decode),
+                (LatencyStage::DecoderQueue, client_stats.video_decoder_queue),
+                (LatencyStage::Rendering, client_stats.rendering),
+                (LatencyStage::VsyncQueue, client_stats.vsync_queue),
+            ],
+        );
+
+        self.total_pipeline_latency_average
+            .submit_sample(total_pipeline_latency);
+        self.game_delay_average.submit_sample(game_time_latency);
+        self.server_compositor_average
+            .submit_sample(server_compositor_latency);
+        self.encode_delay_average.submit_sample(encoder_latency);
+        self.network_delay_average
+            .submit_sample(estimated_network_latency);
+        self.decode_delay_average
+            .submit_sample(client_stats.video_decode);
+        self.decoder_queue_delay_average
+            .submit_sample(client_stats.video_decoder_queue);
+        self.client_compositor_average
+            .submit_sample(client_stats.rendering);
+        self.vsync_queue_delay_average
+            .submit_sample(client_stats.vsync_queue);
+        self.pipeline_latency_before_vsync_average
+            .submit_sample(total_pipeline_latency.saturating_sub(client_stats.vsync_queue));
+        self.prediction_vsync_queue_average
+            .submit_sample(client_stats.vsync_queue);
+
+        self.total_pipeline_latency_histogram
+            .submit_sample(total_pipeline_latency.as_secs_f32() * 1000.0);
+        self.network_latency_histogram
+            .submit_sample(estimated_network_latency.as_secs_f32() * 1000.0);
+
+        /*
+        let client_fps = 1.0
+            / client_stats
+                .frame_interval
+                .max(Duration::from_millis(1))
+                .as_secs_f32();
+        let server_fps = 1.0
+            / self
+                .last_frame_present_interval
+                .max(Duration::from_millis(1))
+                .as_secs_f32();
+        */
+
+        let (shard_loss_ratio, frame_loss_ratio) =
+            self.take_loss_ratios(client_id, client_stats.frames_dropped);
+
+        let bitrate_bps = if estimated_network_latency != Duration::ZERO {
+            frame.video_packet_bytes as f32 * 8.0 / estimated_network_latency.as_secs_f32()
         } else {
-            (Duration::ZERO, 0.0)
+            0.0
+        };
+        self.actual_bitrate_average.submit_sample(bitrate_bps);
+
+        #[cfg(feature = "frame-tracing")]
+        {
+            let target_timestamp = frame.target_timestamp;
+            self.frame_tracer.add_event(
+                target_timestamp,
+                "client_decode",
+                &[
+                    ("network", network_latency),
+                    ("decoder", client_stats.video_decode),
+                    ("decoder_queue", client_stats.video_decoder_queue),
+                ],
+            );
+            self.frame_tracer.add_event(
+                target_timestamp,
+                "client_compositor",
+                &[("client_compositor", client_stats.rendering)],
+            );
+            self.frame_tracer.add_event(
+                target_timestamp,
+                "client_submit",
+                &[
+                    ("vsync_queue", client_stats.vsync_queue),
+                    ("total_pipeline", total_pipeline_latency),
+                ],
+            );
+            self.frame_tracer.end_span(target_timestamp);
+        }
+
+        // Frames reported out of order before the origin are clamped to it
+        let graph_time_origin = *self.graph_time_origin.get_or_insert(frame.target_timestamp);
+        let graph_time_ns = frame
+            .target_timestamp
+            .saturating_sub(graph_time_origin)
+            .as_nanos() as u64;
+
+        let received_bps = self.received_bytes_window.get_rate(self.clock.now()) * 8.0;
+
+        let graph_stats = GraphStatistics {
+            client_id,
+
+            graph_time_ns,
+
+            frame_index: client_stats.frame_index, // added
+            is_idr: frame.is_idr,                  // added
+
+            frames_dropped: client_stats.frames_dropped, // added
+            reordered_shard_counter: client_stats.reordered_shard_counter,
+
+            shard_loss_ratio,
+            frame_loss_ratio,
+
+            total_pipeline_latency_s: client_stats.total_pipeline_latency.as_secs_f32(),
+            game_time_s: game_time_latency.as_secs_f32(),
+            server_compositor_s: server_compositor_latency.as_secs_f32(),
+            encoder_s: encoder_latency.as_secs_f32(),
+            network_s: network_latency.as_secs_f32(),
+            network_latency_min_s: self.network_delay_average.get_min().as_secs_f32(),
+            network_latency_max_s: self.network_delay_average.get_max().as_secs_f32(),
+            decoder_s: client_stats.video_decode.as_secs_f32(),
+            decoder_queue_s: client_stats.video_decoder_queue.as_secs_f32(),
+            client_compositor_s: client_stats.rendering.as_secs_f32(),
+            vsync_queue_s: client_stats.vsync_queue.as_secs_f32(),
+
+            // client_fps, // removed
+            // server_fps, // removed
+            nominal_bitrate: self.last_nominal_bitrate_stats.clone(),
+            actual_bitrate_bps: bitrate_bps, // bitrate as computed by ALVR
+            actual_bitrate_min_bps: self.actual_bitrate_average.get_min(),
+            actual_bitrate_max_bps: self.actual_bitrate_average.get_max(),
+            capacity_estimate_bps: received_bps,
+            server_computed_jitter_s: self
+                .clients
+                .get(&client_id)
+                .map(|client| client.rtp_jitter_s)
+                .unwrap_or(0.0),
+            network_s_owd_based: owd_network_latency
+                .map(|latency| latency.as_secs_f32())
+                .unwrap_or(0.0),
+            client_stats_defaulted: is_defaulted_client_stats(&client_stats),
+            link_utilization: link_utilization(
+                received_bps,
+                self.peak_throughput_average.get_average(),
+            ),
+        };
+
+        if self.recent_graph_stats_capacity > 0 {
+            if self.recent_graph_stats.len() == self.recent_graph_stats_capacity {
+                self.recent_graph_stats.pop_front();
+            }
+            self.recent_graph_stats.push_back(graph_stats.clone());
         }
+        self.publish_graph_stats(&graph_stats);
+        self.emit(EventType::GraphStatistics(graph_stats));
+
+        self.report_statistics_summary();
+
+        Some((
+            estimated_network_latency,
+            self.frame_interarrival_average.get_average(),
+            shard_loss_ratio,
+        ))
+    }
+
+    // Available until the frame leaves the history, only for frames whose client statistics
+    // have been reported
+    #[allow(dead_code)]
+    pub fn latency_breakdown(&self, target_timestamp: Duration) -> Option<LatencyBreakdown> {
+        self.clients
+            .values()
+            .flat_map(|client| &client.stats_history_buffer)
+            .filter(|frame| frame.target_timestamp == target_timestamp)
+            .filter_map(|frame| Some((frame.sequence, frame.latency_breakdown?)))
+            .max_by_key(|(sequence, _)| *sequence)
+            .map(|(_, breakdown)| breakdown)
     }
 
     pub fn video_pipeline_latency_average(&self) -> Duration {
         self.total_pipeline_latency_average.get_average()
     }
 
+    // The stage latencies are known only once the client statistics for a frame arrive, so these
+    // average the last averaging_window_size frames reported by the client, not the frames still in
+    // flight in the client history buffers
+    #[allow(dead_code)]
+    pub fn game_time_latency_average(&self) -> Duration {
+        self.game_delay_average.get_average()
+    }
+
+    #[allow(dead_code)]
+    pub fn server_compositor_latency_average(&self) -> Duration {
+        self.server_compositor_average.get_average()
+    }
+
+    #[allow(dead_code)]
+    pub fn encoder_latency_average(&self) -> Duration {
+        self.encode_delay_average.get_average()
+    }
+
+    #[allow(dead_code)]
+    pub fn network_latency_average(&self) -> Duration {
+        self.network_delay_average.get_average()
+    }
+
+    // Kalman filtered trend of the one-way delay reported by the client, which includes the clock
+    // skew. Zero before the first network statistics
+    #[allow(dead_code)]
+    pub fn filtered_owd(&self) -> Duration {
+        Duration::from_secs_f32(self.owd_filter.delay().max(0.0))
+    }
+
+    // Delay gradient in seconds per second, positive while a queue builds up
+    #[allow(dead_code)]
+    pub fn filtered_owd_rate(&self) -> f32 {
+        self.owd_filter.delay_rate()
+    }
+
+    #[allow(dead_code)]
+    pub fn total_pipeline_latency_histogram(&self) -> &Histogram {
+        &self.total_pipeline_latency_histogram
+    }
+
+    #[allow(dead_code)]
+    pub fn network_latency_histogram(&self) -> &Histogram {
+        &self.network_latency_histogram
+    }
+
+    // This is the opposite of the client's StatisticsManager::tracker_prediction_offset(). The
+    // total pipeline latency is modeled as the latency up to the client submit, averaged over the
+    // averaging window, plus the vsync queue, averaged over the last few frames:
+    // offset = steamvr_pipeline_latency - (avg(total - vsync_queue) + short_avg(vsync_queue))
     pub fn tracker_pose_time_offset(&self) -> Duration {
-        // This is the opposite of the client's StatisticsManager::tracker_prediction_offset().
+        let modeled_pipeline_latency = self.pipeline_latency_before_vsync_average.get_average()
+            + self.prediction_vsync_queue_average.get_average();
+
         self.steamvr_pipeline_latency
-            .saturating_sub(self.total_pipeline_latency_average.get_average())
+            .saturating_sub(modeled_pipeline_latency)
+    }
+
+    // Combines the statistics collected by another manager, for example a shard of a multi-process
+    // experiment. The totals, the partial sums of the current summary interval and the histograms
+    // add up exactly, the byte windows too since their samples are timestamped. The sliding
+    // averages are approximated by interleaving the most recent samples of both windows, see
+    // SlidingWindowAverage::merge(). The loss counters of the current report interval are summed
+    // per client id. The per-client frame history, the clock skew and one-way delay estimates and
+    // the configuration are left unchanged
+    #[allow(dead_code)]
+    pub fn merge(&mut self, other: &StatisticsManager) {
+        self.video_packets_total += other.video_packets_total;
+        self.video_packets_partial_sum += other.video_packets_partial_sum;
+        self.video_bytes_total += other.video_bytes_total;
+        self.video_bytes_window.merge(&other.video_bytes_window);
+        self.video_bps_partial_peak =
+            f32::max(self.video_bps_partial_peak, other.video_bps_partial_peak);
+
+        self.received_video_bytes_partial_sum += other.received_video_bytes_partial_sum;
+        self.received_bytes_window
+            .merge(&other.received_bytes_window);
+        self.probe_bytes_window.merge(&other.probe_bytes_window);
+        self.frame_interarrival_partial_sum += other.frame_interarrival_partial_sum;
+
+        self.packets_dropped_total += other.packets_dropped_total;
+        self.packets_dropped_partial_sum += other.packets_dropped_partial_sum;
+        self.packets_skipped_total += other.packets_skipped_total;
+        self.packets_skipped_partial_sum += other.packets_skipped_partial_sum;
+        self.frames_presented_partial_sum += other.frames_presented_partial_sum;
+
+        for (&client_id, other_client) in &other.clients {
+            let client = self.clients.entry(client_id).or_default();
+            client.interval_shards_lost += other_client.interval_shards_lost;
+            client.interval_shards_received += other_client.interval_shards_received;
+            client.interval_frames_presented += other_client.interval_frames_presented;
+        }
+
+        self.stall_detected |= other.stall_detected;
+        self.stall_count_total += other.stall_count_total;
+        self.stall_duration_total += other.stall_duration_total;
+        self.network_latency_underflow_count += other.network_latency_underflow_count;
+        self.network_latency_underflow_total += other.network_latency_underflow_total;
+        self.frame_present_offset_clamp_count += other.frame_present_offset_clamp_count;
+
+        // Histograms with different bounds cannot be combined, which is not worth failing for
+        self.total_pipeline_latency_histogram
+            .merge(&other.total_pipeline_latency_histogram);
+        self.network_latency_histogram
+            .merge(&other.network_latency_histogram);
+
+        for (average, other_average) in [
+            (
+                &mut self.total_pipeline_latency_average,
+                &other.total_pipeline_latency_average,
+            ),
+            (&mut self.game_delay_average, &other.game_delay_average),
+            (
+                &mut self.server_compositor_average,
+                &other.server_compositor_average,
+            ),
+            (&mut self.encode_delay_average, &other.encode_delay_average),
+            (
+                &mut self.network_delay_average,
+                &other.network_delay_average,
+            ),
+            (&mut self.decode_delay_average, &other.decode_delay_average),
+            (
+                &mut self.decoder_queue_delay_average,
+                &other.decoder_queue_delay_average,
+            ),
+            (
+                &mut self.client_compositor_average,
+                &other.client_compositor_average,
+            ),
+            (
+                &mut self.vsync_queue_delay_average,
+                &other.vsync_queue_delay_average,
+            ),
+            (
+                &mut self.pipeline_latency_before_vsync_average,
+                &other.pipeline_latency_before_vsync_average,
+            ),
+            (
+                &mut self.prediction_vsync_queue_average,
+                &other.prediction_vsync_queue_average,
+            ),
+            (
+                &mut self.frame_interval_average,
+                &other.frame_interval_average,
+            ),
+            (
+                &mut self.client_frame_interval_average,
+                &other.client_frame_interval_average,
+            ),
+        ] {
+            average.merge(other_average);
+        }
+        self.frame_interarrival_average
+            .merge(&other.frame_interarrival_average);
+        self.peak_throughput_average
+            .merge(&other.peak_throughput_average);
+        self.actual_bitrate_average
+            .merge(&other.actual_bitrate_average);
+
+        self.graph_time_origin = match (self.graph_time_origin, other.graph_time_origin) {
+            (Some(origin), Some(other_origin)) => Some(origin.min(other_origin)),
+            (origin, other_origin) => origin.or(other_origin),
+        };
+
+        for (average, other_average) in [
+            (&mut self.idr_bytes_average, &other.idr_bytes_average),
+            (&mut self.delta_bytes_average, &other.delta_bytes_average),
+        ] {
+            if let Some(other_average) = other_average {
+                match average {
+                    Some(average) => average.merge(other_average),
+                    None => *average = Some(other_average.clone()),
+                }
+            }
+        }
     }
 
     // NB: this call is non-blocking, waiting should be done externally
     pub fn duration_until_next_vsync(&mut self) -> Duration {
-        let now = Instant::now();
+        let now = self.clock.now();
 
         // update the last vsync if it's too old
         while self.last_vsync_time + self.frame_interval < now {
@@ -696,3 +2032,1841 @@ impl StatisticsManager {
         (self.last_vsync_time + self.frame_interval).saturating_duration_since(now)
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{clock::tests::MockClock, stats_sink::tests::CapturingSink};
+    use alvr_common::{parking_lot::Mutex, Bitrate};
+    use alvr_session::settings_schema::Switch;
+
+    const CLIENT_ID: ClientId = 1;
+
+    fn push_history_frame(
+        manager: &mut StatisticsManager,
+        client_id: ClientId,
+        frame: HistoryFrame,
+    ) {
+        manager
+            .clients
+            .entry(client_id)
+            .or_default()
+            .stats_history_buffer
+            .push_back(frame);
+    }
+
+    fn network_stats(
+        highest_rx_frame_index: i32,
+        highest_rx_shard_index: i32,
+        rx_shard_counter: u32,
+    ) -> NetworkStatisticsPacket {
+        NetworkStatisticsPacket {
+            frame_index: highest_rx_frame_index,
+            frame_span: 0.001,
+            bytes_in_frame: 10_000,
+            bytes_in_frame_app: 10_000,
+            frame_interarrival: 0.011,
+            interarrival_jitter: 0.0,
+            ow_delay: 0.0,
+            filtered_ow_delay: 0.0,
+            frames_skipped: 0,
+            rx_bytes: 10_000,
+            rx_shard_counter,
+            duplicated_shard_counter: 0,
+            highest_rx_frame_index,
+            highest_rx_shard_index,
+        }
+    }
+
+    pub(crate) fn new_manager() -> StatisticsManager {
+        let mut manager =
+            StatisticsManager::with_history_size(256, Duration::from_secs_f32(1.0 / 90.0), 0.0);
+        manager.set_latency_histogram_bounds(vec![10.0, 20.0, 50.0]);
+
+        manager
+    }
+
+    fn new_manager_with_clock(clock: Arc<MockClock>) -> StatisticsManager {
+        let mut manager =
+            StatisticsManager::with_clock(256, 256, Duration::from_millis(10), 0.0, clock);
+        manager.set_latency_histogram_bounds(vec![]);
+        manager.set_idle_timeout(Some(Duration::from_secs(1)));
+
+        manager
+    }
+
+    // Reports a frame through every server stage and then the client statistics for it. The
+    // stages take no time
+    pub(crate) fn report_complete_frame(
+        manager: &mut StatisticsManager,
+        client_id: ClientId,
+        target_timestamp: Duration,
+        frame_index: u32,
+    ) -> Option<(Duration, f32, f32)> {
+        manager.report_tracking_received(client_id, target_timestamp);
+        manager.report_frame_present(client_id, target_timestamp, Duration::ZERO);
+        manager.report_frame_sent(client_id, target_timestamp, frame_index, 10);
+
+        manager.report_statistics(
+            client_id,
+            ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_frames_sharing_a_target_timestamp() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+        let target_timestamp = Duration::from_millis(10);
+        let first_tracking = clock.now();
+
+        // A repeated tracking report before the present is the same frame
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        assert_eq!(manager.clients[&CLIENT_ID].history_buffer.len(), 1);
+
+        manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+        manager.report_frame_composed(CLIENT_ID, target_timestamp, Duration::ZERO);
+        manager.report_frame_encoded(CLIENT_ID, target_timestamp, 10_000, false);
+        manager.report_frame_sent(CLIENT_ID, target_timestamp, 0, 10);
+
+        // A second frame with the same timestamp
+        clock.advance(Duration::from_millis(5));
+        let second_tracking = clock.now();
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        assert_eq!(manager.clients[&CLIENT_ID].history_buffer.len(), 2);
+
+        clock.advance(Duration::from_millis(5));
+        manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+        manager.report_frame_composed(CLIENT_ID, target_timestamp, Duration::ZERO);
+        manager.report_frame_encoded(CLIENT_ID, target_timestamp, 20_000, true);
+        manager.report_frame_sent(CLIENT_ID, target_timestamp, 1, 20);
+
+        let frames = &manager.clients[&CLIENT_ID].stats_history_buffer;
+        assert_eq!(frames.len(), 2);
+
+        assert_eq!(frames[0].sequence, 0);
+        assert_eq!(frames[0].frame_index, 0);
+        assert_eq!(frames[0].frame_present, first_tracking);
+        assert_eq!(frames[0].video_packet_bytes, 10_000);
+
+        assert_eq!(frames[1].sequence, 1);
+        assert_eq!(frames[1].frame_index, 1);
+        assert_eq!(frames[1].tracking_received, second_tracking);
+        assert_eq!(frames[1].frame_present, clock.now());
+        assert_eq!(frames[1].video_packet_bytes, 20_000);
+        assert!(frames[1].is_idr);
+    }
+
+    #[test]
+    fn test_full_report_boundary_with_mock_clock() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+        clock.advance(Duration::from_millis(500));
+        assert!(!manager.report_statistics_summary());
+
+        clock.advance(Duration::from_millis(1));
+        assert!(manager.report_statistics_summary());
+        assert!(!manager.report_statistics_summary());
+    }
+
+    #[test]
+    fn test_resume_after_idle_gap() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+        let mut present_frame = |frame_index: u64, gap: Duration| {
+            let target_timestamp = Duration::from_millis(frame_index * 10);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            clock.advance(gap);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+        };
+        for frame_index in 0..5 {
+            present_frame(frame_index, Duration::from_millis(10));
+        }
+
+        // Headset set down for about 10 seconds, off the previous vsync phase
+        present_frame(5, Duration::from_millis(10_003));
+        present_frame(6, Duration::from_millis(10));
+
+        // Only the initial 16 ms sample and the 10 ms intervals are in the window
+        assert_eq!(
+            manager.frame_interval_average.get_max(),
+            Duration::from_millis(16)
+        );
+        assert_eq!(
+            manager.last_frame_present_interval,
+            Duration::from_millis(10)
+        );
+
+        // The vsync phase restarted from the first frame after the pause
+        clock.advance(Duration::from_millis(4));
+        assert_eq!(
+            manager.duration_until_next_vsync(),
+            Duration::from_millis(6)
+        );
+    }
+
+    // Stays readable after a clone is handed over as the writer
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shard_timeline_rows_match_loss() {
+        let buffer = SharedBuffer::default();
+        let mut manager = new_manager();
+        manager.set_shard_timeline_writer(Box::new(buffer.clone()));
+
+        let mut shards_lost = vec![];
+        for (frame_index, rx_shard_counter) in [(0, 10), (1, 7), (2, 10)] {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 10);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            shards_lost.push(manager.report_network_statistics(
+                CLIENT_ID,
+                network_stats(frame_index as i32, 9, rx_shard_counter),
+                Duration::ZERO,
+            ));
+        }
+        assert_eq!(shards_lost, vec![0, 3, 0]);
+
+        let text = String::from_utf8(buffer.0.lock().clone()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(SHARD_TIMELINE_HEADER));
+        assert_eq!(
+            lines.collect::<Vec<_>>(),
+            vec![
+                "0,0,10,10,9,0",
+                "1,10000000,10,7,9,3",
+                "2,20000000,10,10,9,0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_frame_report_leaves_loss_state_unchanged() {
+        let mut manager = new_manager();
+
+        let target_timestamp = Duration::from_millis(10);
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+        manager.report_frame_sent(CLIENT_ID, target_timestamp, 3, 10);
+        manager.report_network_statistics(CLIENT_ID, network_stats(3, 5, 6), Duration::ZERO);
+
+        let before = &manager.clients[&CLIENT_ID];
+        let state_before = (
+            before.prev_highest_frame,
+            before.prev_highest_shard,
+            before.interval_shards_lost,
+            before.interval_shards_received,
+        );
+
+        let client_stats = ClientStatistics {
+            target_timestamp: Duration::from_millis(999),
+            frame_index: 99,
+            ..Default::default()
+        };
+        assert!(manager.report_statistics(CLIENT_ID, client_stats).is_none());
+
+        let after = &manager.clients[&CLIENT_ID];
+        assert_eq!(
+            (
+                after.prev_highest_frame,
+                after.prev_highest_shard,
+                after.interval_shards_lost,
+                after.interval_shards_received,
+            ),
+            state_before
+        );
+        assert_eq!(state_before.0, 3);
+        assert_eq!(state_before.1, 5);
+    }
+
+    #[test]
+    fn test_rtp_jitter_follows_rfc3550() {
+        let start = Instant::now();
+        let mut client = ClientFrameState::default();
+
+        // One frame every 10 ms, with varying transit times
+        let transits_ms = [5, 7, 6, 10, 5];
+        let mut expected_jitter_s = 0.0;
+        for (i, transit_ms) in transits_ms.into_iter().enumerate() {
+            let sent = start + Duration::from_millis(i as u64 * 10);
+            client.report_frame_arrival(sent, sent + Duration::from_millis(transit_ms));
+
+            if i == 0 {
+                assert_eq!(client.rtp_jitter_s, 0.0);
+            } else {
+                let transit_delta_s = (transit_ms as f32 - transits_ms[i - 1] as f32) / 1000.0;
+                expected_jitter_s += (transit_delta_s.abs() - expected_jitter_s) / 16.0;
+                assert!((client.rtp_jitter_s - expected_jitter_s).abs() < 1e-7);
+            }
+        }
+
+        // J = 0.125, 0.1797, 0.4185, 0.7048 ms
+        assert!((client.rtp_jitter_s - 0.000_704_8).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_rtp_jitter_uses_client_arrivals() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+        // One frame sent every 10 ms. The first shards reach the client every 10 ms too, with the
+        // frame spans alternating, while the statistics reach the server after varying delays
+        let report_delays_ms = [1, 8, 3, 9, 2];
+        for (frame_index, report_delay_ms) in report_delays_ms.into_iter().enumerate() {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 10);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_encoded(CLIENT_ID, target_timestamp, 10_000, false);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index as u32, 10);
+
+            clock.advance(Duration::from_millis(report_delay_ms));
+            let frame_span = if frame_index % 2 == 0 { 0.001 } else { 0.003 };
+            let prev_frame_span = if frame_index % 2 == 0 { 0.003 } else { 0.001 };
+            manager.report_network_statistics(
+                CLIENT_ID,
+                NetworkStatisticsPacket {
+                    frame_span,
+                    frame_interarrival: 0.010 + frame_span - prev_frame_span,
+                    ..network_stats(frame_index as i32, 9, 10)
+                },
+                Duration::ZERO,
+            );
+            clock.advance(Duration::from_millis(10 - report_delay_ms));
+        }
+
+        assert!(manager.clients[&CLIENT_ID].rtp_jitter_s.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_probe_bytes_raise_capacity_but_not_goodput() {
+        let capacity_and_goodput = |probe_bytes| {
+            let clock = Arc::new(MockClock::default());
+            let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+            let target_timestamp = Duration::from_millis(10);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, 0, 10);
+
+            // The probe shards reach the client along with the video
+            if probe_bytes > 0 {
+                manager.report_probe_bytes_received(probe_bytes);
+            }
+            manager.report_network_statistics(
+                CLIENT_ID,
+                NetworkStatisticsPacket {
+                    rx_bytes: 100_000 + probe_bytes as u32,
+                    ..network_stats(0, 9, 10)
+                },
+                Duration::ZERO,
+            );
+
+            manager.report_statistics(
+                CLIENT_ID,
+                ClientStatistics {
+                    target_timestamp,
+                    frame_index: 0,
+                    ..Default::default()
+                },
+            );
+
+            let graph = manager.recent_graph_stats(1).pop().unwrap();
+
+            (graph.capacity_estimate_bps, manager.goodput_bps())
+        };
+
+        // 100 KB over the 500 ms window
+        let (capacity_bps, goodput_bps) = capacity_and_goodput(0);
+        assert!((capacity_bps - 1.6E6).abs() < 1.0);
+        assert!((goodput_bps - 1.6E6).abs() < 1.0);
+
+        let (capacity_bps, goodput_bps) = capacity_and_goodput(50_000);
+        assert!((capacity_bps - 2.4E6).abs() < 1.0);
+        assert!((goodput_bps - 1.6E6).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_idr_and_delta_sizes_are_averaged_separately() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        for (frame, (bytes_count, is_idr)) in [
+            (200_000, true),
+            (10_000, false),
+            (20_000, false),
+            (300_000, true),
+            (30_000, false),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            manager.report_frame_encoded(
+                CLIENT_ID,
+                Duration::from_millis(frame as u64 * 10),
+                bytes_count,
+                is_idr,
+            );
+        }
+
+        clock.advance(Duration::from_secs(1));
+        assert!(manager.report_statistics_summary());
+
+        let events = sink.events.lock();
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                EventType::StatisticsSummary(summary) => Some(summary),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(summary.avg_idr_bytes, 250_000.0);
+        assert_eq!(summary.avg_delta_bytes, 20_000.0);
+    }
+
+    #[test]
+    fn test_bitrate_achievement_ratio() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        manager.report_nominal_bitrate_stats(NominalBitrateStats {
+            requested_bps: Bitrate::from_mbps(30.0),
+            ..Default::default()
+        });
+
+        // 1.25 MB within the 500 ms window, 20 Mbps
+        clock.advance(Duration::from_millis(501));
+        for frame in 0..10 {
+            manager.report_frame_encoded(
+                CLIENT_ID,
+                Duration::from_millis(frame * 10),
+                125_000,
+                false,
+            );
+        }
+        assert!(manager.report_statistics_summary());
+
+        let events = sink.events.lock();
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                EventType::StatisticsSummary(summary) => Some(summary),
+                _ => None,
+            })
+            .unwrap();
+        assert!((summary.video_mbits_per_sec - 20.0).abs() < 1e-3);
+        assert!(summary.bitrate_achievement_ratio < 1.0);
+        assert!((summary.bitrate_achievement_ratio - 2.0 / 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_video_bitrate_peak_within_interval() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        let last_summary = |sink: &CapturingSink| {
+            sink.events
+                .lock()
+                .iter()
+                .rev()
+                .find_map(|event| match event {
+                    EventType::StatisticsSummary(summary) => Some(summary.clone()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        // 12.5 KB every 10 ms is 10 Mbps, the 125 KB frame is a 100 Mbps burst
+        for frame in 0..50 {
+            clock.advance(Duration::from_millis(10));
+            let bytes_count = if frame == 25 { 125_000 } else { 12_500 };
+            manager.report_frame_encoded(
+                CLIENT_ID,
+                Duration::from_millis(frame * 10),
+                bytes_count,
+                false,
+            );
+        }
+        clock.advance(Duration::from_millis(1));
+        assert!(manager.report_statistics_summary());
+
+        let summary = last_summary(&sink);
+        assert!((summary.video_mbits_per_sec_peak - 100.0).abs() < 1e-3);
+        assert!(summary.video_mbits_per_sec < 15.0);
+
+        // The peak is reset every interval
+        for frame in 50..100 {
+            clock.advance(Duration::from_millis(10));
+            manager.report_frame_encoded(
+                CLIENT_ID,
+                Duration::from_millis(frame * 10),
+                12_500,
+                false,
+            );
+        }
+        clock.advance(Duration::from_millis(1));
+        assert!(manager.report_statistics_summary());
+
+        assert!((last_summary(&sink).video_mbits_per_sec_peak - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_video_mbytes_total_is_exact() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        // 2^24 + 1 MB cannot be represented exactly as f32
+        manager.video_bytes_total = (1 << 24) * 1_000_000;
+        manager.report_frame_encoded(CLIENT_ID, Duration::ZERO, 1_000_000, false);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(manager.report_statistics_summary());
+
+        let events = sink.events.lock();
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                EventType::StatisticsSummary(summary) => Some(summary),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(summary.video_mbytes_total, (1 << 24) + 1);
+    }
+
+    #[test]
+    fn test_merge_sums_counters() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        let mut other = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        // Every frame is presented, 10 shards are sent for frame 0
+        for (shard, frames_count, frames_skipped, frames_dropped, received_shards) in
+            [(&mut manager, 3, 1, 2, 8), (&mut other, 5, 4, 8, 5)]
+        {
+            for frame in 0..frames_count {
+                let target_timestamp = Duration::from_millis(frame * 10);
+                shard.report_tracking_received(CLIENT_ID, target_timestamp);
+                shard.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+                shard.report_frame_encoded(CLIENT_ID, target_timestamp, 1_000_000, false);
+            }
+            shard.report_frame_sent(CLIENT_ID, Duration::ZERO, 0, 10);
+            shard.report_network_statistics(
+                CLIENT_ID,
+                NetworkStatisticsPacket {
+                    frames_skipped,
+                    ..network_stats(0, 9, received_shards)
+                },
+                Duration::ZERO,
+            );
+            shard.packets_dropped_total = frames_dropped;
+            shard.stall_count_total = frames_dropped as usize;
+            shard
+                .total_pipeline_latency_histogram
+                .submit_sample(frames_count as f32);
+        }
+
+        manager.merge(&other);
+
+        assert_eq!(manager.stall_count_total, 10);
+        assert_eq!(manager.total_pipeline_latency_histogram.counts(), &[2]);
+
+        // 7 of 20 shards lost, 2 frames dropped out of 8 presented
+        let (shard_loss_ratio, frame_loss_ratio) = manager.take_loss_ratios(CLIENT_ID, 2);
+        assert!((shard_loss_ratio - 0.35).abs() < 1e-6);
+        assert!((frame_loss_ratio - 0.25).abs() < 1e-6);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(manager.report_statistics_summary());
+
+        let events = sink.events.lock();
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                EventType::StatisticsSummary(summary) => Some(summary),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(summary.video_packets_total, 8);
+        assert_eq!(summary.video_mbytes_total, 8);
+        assert_eq!(summary.packets_skipped_total, 5);
+        assert_eq!(summary.packets_dropped_total, 10);
+        assert_eq!(summary.avg_delta_bytes, 1_000_000.0);
+    }
+
+    #[test]
+    fn test_effective_fps_excludes_dropped_and_skipped_frames() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        for frame_index in 0..20 {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 10);
+            clock.advance(Duration::from_millis(10));
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+        }
+
+        // 2 frames skipped before the network and 5 dropped on the client
+        manager.report_network_statistics(
+            CLIENT_ID,
+            NetworkStatisticsPacket {
+                frames_skipped: 2,
+                ..network_stats(19, 9, 10)
+            },
+            Duration::ZERO,
+        );
+        let client_stats = ClientStatistics {
+            target_timestamp: Duration::from_millis(190),
+            frame_index: 19,
+            frame_interval: Duration::from_millis(10),
+            frames_dropped: 5,
+            ..Default::default()
+        };
+        assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+
+        clock.advance(Duration::from_millis(800));
+        assert!(manager.report_statistics_summary());
+
+        let events = sink.events.lock();
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                EventType::StatisticsSummary(summary) => Some(summary),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(summary.effective_fps, 13.0);
+        assert!(summary.effective_fps < summary.client_fps);
+    }
+
+    #[test]
+    fn test_duration_until_next_vsync_with_mock_clock() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+        assert_eq!(
+            manager.duration_until_next_vsync(),
+            Duration::from_millis(10)
+        );
+
+        clock.advance(Duration::from_millis(4));
+        assert_eq!(
+            manager.duration_until_next_vsync(),
+            Duration::from_millis(6)
+        );
+
+        // The last vsync moves forward by whole frame intervals
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(
+            manager.duration_until_next_vsync(),
+            Duration::from_millis(6)
+        );
+    }
+
+    #[test]
+    fn test_duration_until_next_vsync_on_boundary() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        let start = manager.last_vsync_time;
+
+        // The next vsync is due now, so it is not skipped
+        clock.set(start + Duration::from_millis(10));
+        assert_eq!(manager.duration_until_next_vsync(), Duration::ZERO);
+        assert_eq!(manager.last_vsync_time, start);
+
+        clock.set(start + Duration::from_millis(20));
+        assert_eq!(manager.duration_until_next_vsync(), Duration::ZERO);
+        assert_eq!(manager.last_vsync_time, start + Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_duration_until_next_vsync_just_before_boundary() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        let start = manager.last_vsync_time;
+
+        clock.set(start + Duration::from_micros(9_999));
+        assert_eq!(
+            manager.duration_until_next_vsync(),
+            Duration::from_micros(1)
+        );
+        assert_eq!(manager.last_vsync_time, start);
+    }
+
+    #[test]
+    fn test_duration_until_next_vsync_after_many_intervals() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        let start = manager.last_vsync_time;
+
+        clock.set(start + Duration::from_millis(1005));
+        assert_eq!(
+            manager.duration_until_next_vsync(),
+            Duration::from_millis(5)
+        );
+
+        // Advanced by whole intervals, up to the last vsync before now
+        assert_eq!(manager.last_vsync_time, start + Duration::from_millis(1000));
+        assert!(manager.last_vsync_time <= clock.now());
+    }
+
+    #[test]
+    fn test_full_report_interval() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager_500ms = new_manager_with_clock(Arc::clone(&clock));
+        let mut manager_100ms = StatisticsManager::with_clock(
+            256,
+            256,
+            Duration::from_secs_f32(1.0 / 90.0),
+            0.0,
+            Arc::clone(&clock) as _,
+        );
+        manager_100ms.set_full_report_interval(Duration::from_millis(100));
+
+        // A summary is due once more than the interval has passed. In 5 ms steps up to 1045 ms, the
+        // 500 ms summaries come at 505 and 1010 ms, the 100 ms ones at 105, 210, ..., 945 ms
+        let mut summaries_500ms = 0;
+        let mut summaries_100ms = 0;
+        for _ in 0..209 {
+            clock.advance(Duration::from_millis(5));
+
+            summaries_500ms += manager_500ms.report_statistics_summary() as usize;
+            summaries_100ms += manager_100ms.report_statistics_summary() as usize;
+        }
+
+        assert_eq!(summaries_500ms, 2);
+        assert_eq!(summaries_100ms, 9);
+    }
+
+    #[test]
+    fn test_shards_per_frame_map_is_bounded() {
+        let mut manager = new_manager();
+
+        // Frames sent in full and acknowledged
+        for frame_index in 0..5000 {
+            manager.report_frame_sent(CLIENT_ID, Duration::ZERO, frame_index, 4);
+            manager.report_network_statistics(
+                CLIENT_ID,
+                network_stats(frame_index as i32, 3, 4),
+                Duration::ZERO,
+            );
+            assert!(manager.clients[&CLIENT_ID].map_frames_spf.len() <= 1);
+        }
+
+        // Frames sent but never acknowledged
+        for frame_index in 5000..10000 {
+            manager.report_frame_sent(CLIENT_ID, Duration::ZERO, frame_index, 4);
+        }
+        let map_frames_spf = &manager.clients[&CLIENT_ID].map_frames_spf;
+        assert!(map_frames_spf.len() <= MAX_SHARDS_PER_FRAME_ENTRIES);
+        assert!(map_frames_spf.contains_key(&9999));
+    }
+
+    #[test]
+    fn test_report_statistics_missing_frame() {
+        let mut manager = new_manager();
+
+        let client_stats = ClientStatistics {
+            target_timestamp: Duration::from_millis(10),
+            frame_index: 42,
+            ..Default::default()
+        };
+        assert!(manager.report_statistics(CLIENT_ID, client_stats).is_none());
+    }
+
+    #[test]
+    fn test_loss_ratios_zero_traffic() {
+        let mut manager = new_manager();
+
+        assert_eq!(manager.take_loss_ratios(CLIENT_ID, 0), (0.0, 0.0));
+        assert_eq!(manager.take_loss_ratios(CLIENT_ID, 3), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_loss_ratios_crafted_loss() {
+        let mut manager = new_manager();
+
+        for i in 0..4 {
+            let timestamp = Duration::from_millis(i * 11);
+            manager.report_tracking_received(CLIENT_ID, timestamp);
+            manager.report_frame_present(CLIENT_ID, timestamp, Duration::ZERO);
+        }
+
+        // 10 shards sent for frame 0, only 8 received
+        manager.report_frame_sent(CLIENT_ID, Duration::ZERO, 0, 10);
+        manager.report_network_statistics(CLIENT_ID, network_stats(0, 9, 8), Duration::ZERO);
+
+        let (shard_loss_ratio, frame_loss_ratio) = manager.take_loss_ratios(CLIENT_ID, 1);
+        assert!((shard_loss_ratio - 0.2).abs() < 1e-6);
+        assert!((frame_loss_ratio - 0.25).abs() < 1e-6);
+
+        // Ratios are per report, not cumulative
+        assert_eq!(manager.take_loss_ratios(CLIENT_ID, 0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_reordered_network_statistics() {
+        let mut manager = new_manager();
+
+        for frame_index in 0..4 {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+        }
+
+        // Frame 2 is reported before frame 1, all shards are received up to frame 3
+        let mut shards_lost = vec![];
+        for (frame_index, rx_shard_counter) in [(0, 10), (2, 10), (1, 10), (3, 7)] {
+            shards_lost.push(manager.report_network_statistics(
+                CLIENT_ID,
+                network_stats(frame_index, 9, rx_shard_counter),
+                Duration::ZERO,
+            ));
+        }
+        // The shards of frame 1 are counted as lost until its report arrives
+        assert_eq!(shards_lost, vec![0, 10, 0, 3]);
+        assert_eq!(manager.clients[&CLIENT_ID].prev_highest_frame, 3);
+
+        let (shard_loss_ratio, _) = manager.take_loss_ratios(CLIENT_ID, 0);
+        assert!((shard_loss_ratio - 3.0 / 40.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_stall_detection() {
+        let mut manager = new_manager();
+
+        // First frame and regular frames at the nominal 90 Hz
+        manager.report_frame_interarrival(0.0);
+        manager.report_frame_interarrival(1.0 / 90.0);
+        assert!(!manager.stall_detected);
+        assert_eq!(manager.stall_count_total, 0);
+
+        manager.report_frame_interarrival(0.2);
+        manager.report_frame_interarrival(1.0 / 90.0);
+        manager.report_frame_interarrival(0.3);
+
+        assert!(manager.stall_detected);
+        assert_eq!(manager.stall_count_total, 2);
+        assert!((manager.stall_duration_total.as_secs_f32() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_frame_interarrival_outliers_are_clamped() {
+        let heuristic_fps = |multiplier| {
+            let mut manager = new_manager_with_clock(Arc::new(MockClock::default()));
+            manager.set_frame_interarrival_outlier_multiplier(multiplier);
+
+            // 100 Hz with a single 1 s stall. The first sample is not averaged
+            for frame_index in 0..=100 {
+                let frame_interarrival = if frame_index == 50 { 1.0 } else { 0.010 };
+                manager.report_network_statistics(
+                    CLIENT_ID,
+                    NetworkStatisticsPacket {
+                        frame_interarrival,
+                        ..network_stats(frame_index, 9, 10)
+                    },
+                    Duration::ZERO,
+                );
+            }
+
+            1.0 / manager.frame_interarrival_average.get_average()
+        };
+
+        assert!(heuristic_fps(None) < 55.0);
+        assert!(heuristic_fps(Some(2.0)) > 98.0);
+    }
+
+    #[test]
+    fn test_protocol_overhead_ratio() {
+        // 10 shards of 1400 B, each with a 42 B UDP/IP header and a 20 B shard prefix
+        let application_bytes = 10 * (1400 - 20);
+        let network_bytes = 10 * (1400 + 42);
+
+        let ratio = protocol_overhead_ratio(network_bytes, application_bytes);
+        assert!((ratio - 620.0 / 14420.0).abs() < 1e-6);
+
+        assert_eq!(protocol_overhead_ratio(1000, 1000), 0.0);
+        assert_eq!(protocol_overhead_ratio(0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_link_utilization() {
+        assert!((link_utilization(50E6, 50E6) - 1.0).abs() < 1e-6);
+        assert!((link_utilization(25E6, 50E6) - 0.5).abs() < 1e-6);
+
+        assert_eq!(link_utilization(500E6, 50E6), MAX_LINK_UTILIZATION);
+        assert_eq!(link_utilization(25E6, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_goodput_excludes_duplicated_shards() {
+        let clean = network_stats(0, 9, 10);
+        assert_eq!(
+            peak_goodput_bps(&clean),
+            peak_network_throughput_bps(&clean)
+        );
+
+        // 4 of the 10 received shards are duplicates
+        let duplicated = NetworkStatisticsPacket {
+            duplicated_shard_counter: 4,
+            ..network_stats(0, 9, 10)
+        };
+        let goodput = peak_goodput_bps(&duplicated);
+        assert!(goodput < peak_network_throughput_bps(&duplicated));
+        assert!((goodput - 10_000.0 * 0.6 * 8.0 / 0.001).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_graph_statistics_per_report() {
+        let sink = Arc::new(CapturingSink::default());
+
+        let mut manager = new_manager();
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        for frame_index in 0..3 {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        }
+
+        let graph_frame_indices = sink
+            .events
+            .lock()
+            .iter()
+            .filter_map(|event| match event {
+                EventType::GraphStatistics(graph) => Some(graph.frame_index),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(graph_frame_indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_latency_budget_alarms_are_throttled() {
+        let clock = Arc::new(MockClock::default());
+        let sink = Arc::new(CapturingSink::default());
+
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+        manager.set_latency_budgets(LatencyBudgetsConfig {
+            game_time_ms: Switch::Disabled,
+            server_compositor_ms: Switch::Disabled,
+            encoder_ms: Switch::Enabled(5.0),
+            network_ms: Switch::Disabled,
+            decoder_ms: Switch::Disabled,
+            decoder_queue_ms: Switch::Disabled,
+            rendering_ms: Switch::Disabled,
+            vsync_queue_ms: Switch::Disabled,
+            throttle_interval_s: 1.0,
+        });
+
+        // Every frame takes 20 ms to encode
+        let mut report_frame = |frame_index: u32| {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_composed(CLIENT_ID, target_timestamp, Duration::ZERO);
+            clock.advance(Duration::from_millis(20));
+            manager.report_frame_encoded(CLIENT_ID, target_timestamp, 1000, false);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                total_pipeline_latency: Duration::from_millis(50),
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        };
+
+        let alarms = || {
+            sink.events
+                .lock()
+                .iter()
+                .filter_map(|event| match event {
+                    EventType::LatencyBudgetAlarm(alarm) => Some(alarm.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // 10 frames within 200 ms, all over budget
+        for frame_index in 0..10 {
+            report_frame(frame_index);
+        }
+        let throttled_alarms = alarms();
+        assert_eq!(throttled_alarms.len(), 1);
+        assert_eq!(throttled_alarms[0].stage, LatencyStage::Encoder);
+        assert_eq!(throttled_alarms[0].frame_index, 0);
+        assert!((throttled_alarms[0].measured_ms - 20.0).abs() < 0.01);
+        assert_eq!(throttled_alarms[0].budget_ms, 5.0);
+
+        clock.advance(Duration::from_secs(1));
+        report_frame(10);
+        assert_eq!(alarms().len(), 2);
+    }
+
+    // 5 ms of game time, 2 ms of compositor and 3 ms of encoder latency on the server. 14 ms of
+    // client stages
+    fn breakdown_inputs(total_pipeline_latency: Duration) -> (HistoryFrame, ClientStatistics) {
+        let tracking_received = Instant::now();
+        let frame = HistoryFrame {
+            tracking_received,
+            frame_present: tracking_received + Duration::from_millis(5),
+            frame_composed: tracking_received + Duration::from_millis(7),
+            frame_encoded: tracking_received + Duration::from_millis(10),
+            ..Default::default()
+        };
+        let client_stats = ClientStatistics {
+            video_decode: Duration::from_millis(5),
+            video_decoder_queue: Duration::from_millis(2),
+            rendering: Duration::from_millis(3),
+            vsync_queue: Duration::from_millis(4),
+            total_pipeline_latency,
+            ..Default::default()
+        };
+
+        (frame, client_stats)
+    }
+
+    #[test]
+    fn test_compute_breakdown() {
+        let (frame, client_stats) = breakdown_inputs(Duration::from_millis(50));
+        let breakdown = compute_breakdown(&frame, &client_stats);
+
+        assert_eq!(
+            breakdown,
+            LatencyBreakdown {
+                total_pipeline: Duration::from_millis(50),
+                game_time: Duration::from_millis(5),
+                server_compositor: Duration::from_millis(2),
+                encoder: Duration::from_millis(3),
+                network: Duration::from_millis(26),
+                decoder: Duration::from_millis(5),
+                decoder_queue: Duration::from_millis(2),
+                rendering: Duration::from_millis(3),
+                vsync_queue: Duration::from_millis(4),
+            }
+        );
+        assert_eq!(breakdown.network_underflow(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_compute_breakdown_zero_network() {
+        // The other stages add up exactly to the total
+        let (frame, client_stats) = breakdown_inputs(Duration::from_millis(24));
+        let breakdown = compute_breakdown(&frame, &client_stats);
+
+        assert_eq!(breakdown.network, Duration::ZERO);
+        assert_eq!(breakdown.network_underflow(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_compute_breakdown_over_saturated() {
+        let (frame, client_stats) = breakdown_inputs(Duration::from_millis(20));
+        let breakdown = compute_breakdown(&frame, &client_stats);
+
+        assert_eq!(breakdown.network, Duration::ZERO);
+        assert_eq!(breakdown.network_underflow(), Duration::from_millis(4));
+
+        // Server timestamps out of order saturate the stage at zero
+        let frame = HistoryFrame {
+            frame_encoded: frame.frame_composed - Duration::from_millis(1),
+            ..frame
+        };
+        let breakdown = compute_breakdown(&frame, &client_stats);
+        assert_eq!(breakdown.encoder, Duration::ZERO);
+        assert_eq!(breakdown.network_underflow(), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_latency_breakdown_of_frame() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+        let target_timestamp = Duration::from_millis(11);
+        assert_eq!(manager.latency_breakdown(target_timestamp), None);
+
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        clock.advance(Duration::from_millis(4));
+        manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+        clock.advance(Duration::from_millis(2));
+        manager.report_frame_composed(CLIENT_ID, target_timestamp, Duration::ZERO);
+        clock.advance(Duration::from_millis(3));
+        manager.report_frame_encoded(CLIENT_ID, target_timestamp, 1000, false);
+        manager.report_frame_sent(CLIENT_ID, target_timestamp, 0, 10);
+
+        // Not reported by the client yet
+        assert_eq!(manager.latency_breakdown(target_timestamp), None);
+
+        let client_stats = ClientStatistics {
+            target_timestamp,
+            frame_index: 0,
+            video_decode: Duration::from_millis(5),
+            video_decoder_queue: Duration::from_millis(1),
+            rendering: Duration::from_millis(2),
+            vsync_queue: Duration::from_millis(6),
+            total_pipeline_latency: Duration::from_millis(40),
+            ..Default::default()
+        };
+        assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+
+        assert_eq!(
+            manager.latency_breakdown(target_timestamp),
+            Some(LatencyBreakdown {
+                total_pipeline: Duration::from_millis(40),
+                game_time: Duration::from_millis(4),
+                server_compositor: Duration::from_millis(2),
+                encoder: Duration::from_millis(3),
+                network: Duration::from_millis(17),
+                decoder: Duration::from_millis(5),
+                decoder_queue: Duration::from_millis(1),
+                rendering: Duration::from_millis(2),
+                vsync_queue: Duration::from_millis(6),
+            })
+        );
+        assert_eq!(manager.latency_breakdown(Duration::from_millis(22)), None);
+    }
+
+    #[test]
+    fn test_owd_network_latency_is_independent_of_stages() {
+        let sink = Arc::new(CapturingSink::default());
+        let mut manager = new_manager();
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        let report_frame = |manager: &mut StatisticsManager, frame_index: u32| {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            // Skew of 20 ms: one-way delay of 30 ms with a round trip of 20 ms
+            manager.report_network_statistics(
+                CLIENT_ID,
+                NetworkStatisticsPacket {
+                    filtered_ow_delay: 0.030,
+                    ..network_stats(frame_index as i32, 9, 10)
+                },
+                Duration::from_millis(20),
+            );
+
+            // The other stages exceed the total latency, the subtraction saturates to zero
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                video_decode: Duration::from_millis(5),
+                total_pipeline_latency: Duration::from_millis(1),
+                ..Default::default()
+            };
+            let (network_latency, ..) = manager.report_statistics(CLIENT_ID, client_stats).unwrap();
+
+            network_latency
+        };
+
+        // Half the round trip plus the 1 ms frame span
+        let owd_network_latency_s = 0.011;
+
+        assert_eq!(report_frame(&mut manager, 0), Duration::ZERO);
+
+        let graph = sink
+            .events
+            .lock()
+            .iter()
+            .find_map(|event| match event {
+                EventType::GraphStatistics(graph) => Some(graph.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(graph.network_s, 0.0);
+        assert!((graph.network_s_owd_based - owd_network_latency_s).abs() < 1e-6);
+
+        manager.set_network_latency_estimator(NetworkLatencyEstimator::OneWayDelay);
+        let network_latency = report_frame(&mut manager, 1);
+        assert!((network_latency.as_secs_f32() - owd_network_latency_s).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_tracker_pose_time_offset_follows_vsync_queue() {
+        let mut manager =
+            StatisticsManager::with_history_size(32, Duration::from_secs_f32(1.0 / 90.0), 0.0);
+        manager.steamvr_pipeline_latency = Duration::from_millis(100);
+
+        let report_frame = |manager: &mut StatisticsManager, frame_index: u32, vsync_ms: u64| {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            // 40 ms up to the client submit
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                vsync_queue: Duration::from_millis(vsync_ms),
+                total_pipeline_latency: Duration::from_millis(40 + vsync_ms),
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        };
+
+        for frame_index in 0..32 {
+            report_frame(&mut manager, frame_index, 5);
+        }
+        assert_eq!(
+            manager.tracker_pose_time_offset(),
+            Duration::from_millis(55)
+        );
+
+        // The total pipeline latency average still mostly holds the old vsync queue
+        for frame_index in 32..40 {
+            report_frame(&mut manager, frame_index, 15);
+        }
+        assert_eq!(
+            manager.tracker_pose_time_offset(),
+            Duration::from_millis(45)
+        );
+    }
+
+    #[test]
+    fn test_graph_time_from_first_frame() {
+        let mut manager = new_manager();
+
+        for (frame_index, timestamp_ms) in [(0, 1000), (1, 1011)] {
+            let target_timestamp = Duration::from_millis(timestamp_ms);
+
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                ..Default::default()
+            };
+            manager.report_statistics(CLIENT_ID, client_stats);
+        }
+
+        let graph_times = manager
+            .recent_graph_stats(2)
+            .iter()
+            .map(|graph| graph.graph_time_ns)
+            .collect::<Vec<_>>();
+        assert_eq!(graph_times, vec![0, 11_000_000]);
+    }
+
+    #[test]
+    fn test_recent_graph_stats() {
+        let mut manager = new_manager();
+        manager.set_recent_graph_stats_capacity(4);
+
+        for frame_index in 0..10 {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        }
+
+        let frame_indices = |graph_stats: Vec<GraphStatistics>| {
+            graph_stats
+                .iter()
+                .map(|graph| graph.frame_index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            frame_indices(manager.recent_graph_stats(4)),
+            vec![6, 7, 8, 9]
+        );
+        assert_eq!(frame_indices(manager.recent_graph_stats(2)), vec![8, 9]);
+        assert_eq!(
+            frame_indices(manager.recent_graph_stats(100)),
+            vec![6, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn test_defaulted_client_stats_are_flagged() {
+        let mut manager = new_manager();
+
+        let mut report_frame = |frame_index: u32, client_stats: ClientStatistics| {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                ..client_stats
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+
+            manager.recent_graph_stats(1).pop().unwrap()
+        };
+
+        assert!(report_frame(0, ClientStatistics::default()).client_stats_defaulted);
+
+        let measured = report_frame(
+            1,
+            ClientStatistics {
+                total_pipeline_latency: Duration::from_millis(40),
+                ..Default::default()
+            },
+        );
+        assert!(!measured.client_stats_defaulted);
+    }
+
+    #[test]
+    fn test_graph_stats_subscribers() {
+        let mut manager = new_manager();
+
+        let report_frame = |manager: &mut StatisticsManager, frame_index: u32| {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+            assert!(
+                report_complete_frame(manager, CLIENT_ID, target_timestamp, frame_index).is_some()
+            );
+        };
+
+        let first = manager.subscribe();
+        let second = manager.subscribe();
+
+        report_frame(&mut manager, 0);
+        assert_eq!(first.try_recv().unwrap().frame_index, 0);
+        assert_eq!(second.try_recv().unwrap().frame_index, 0);
+
+        // A dropped receiver is unsubscribed on the next emission
+        drop(second);
+        report_frame(&mut manager, 1);
+        assert_eq!(first.try_recv().unwrap().frame_index, 1);
+        assert_eq!(manager.graph_stats_subscribers.len(), 1);
+
+        // A subscriber that doesn't keep up keeps the oldest statistics
+        for frame_index in 2..2 + GRAPH_STATS_SUBSCRIBER_CAPACITY as u32 + 1 {
+            report_frame(&mut manager, frame_index);
+        }
+        assert_eq!(first.try_iter().count(), GRAPH_STATS_SUBSCRIBER_CAPACITY);
+    }
+
+    #[test]
+    fn test_constructor_clamps_invalid_parameters() {
+        let mut manager = StatisticsManager::new(0, 0, Duration::ZERO, 0.0);
+        assert_eq!(manager.max_history_size, 1);
+        assert_eq!(manager.averaging_window_size, 1);
+        assert_eq!(manager.frame_interval, Duration::from_millis(1));
+
+        for frame_index in 0..3 {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 11);
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                total_pipeline_latency: Duration::from_millis(20),
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        }
+
+        assert_eq!(manager.clients[&CLIENT_ID].stats_history_buffer.len(), 1);
+        assert_eq!(
+            manager.video_pipeline_latency_average(),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn test_averaging_window_is_independent_of_history_size() {
+        let mut manager = StatisticsManager::new(8, 2, Duration::from_millis(10), 0.0);
+
+        for frame_index in 0..6 {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 10);
+
+            manager.report_tracking_received(CLIENT_ID, target_timestamp);
+            manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+            manager.report_frame_sent(CLIENT_ID, target_timestamp, frame_index, 10);
+
+            let client_stats = ClientStatistics {
+                target_timestamp,
+                frame_index: frame_index as i32,
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        }
+
+        let client = &manager.clients[&CLIENT_ID];
+        assert_eq!(client.history_buffer.len(), 6);
+        assert_eq!(client.stats_history_buffer.len(), 6);
+
+        assert_eq!(
+            manager.total_pipeline_latency_average.history_buffer_len(),
+            2
+        );
+        assert_eq!(manager.game_delay_average.history_buffer_len(), 2);
+        assert_eq!(manager.network_delay_average.history_buffer_len(), 2);
+    }
+
+    #[test]
+    fn test_clients_keep_independent_state() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        // Both clients use the same target timestamps and frame indices. Client 1 loses 2 of 10
+        // shards per frame, client 2 drops every frame it is sent. Client 1 frames are presented
+        // 1 ms after the tracking, client 2 frames 2 ms after. The other server stages take no time
+        let clients = [(1, 1, 8, 20, 0), (2, 2, 10, 50, 1)];
+        for frame_index in 0..3 {
+            let target_timestamp = Duration::from_millis(frame_index as u64 * 10);
+
+            for &(client_id, ..) in &clients {
+                manager.report_tracking_received(client_id, target_timestamp);
+            }
+
+            for &(client_id, _, received_shards, ..) in &clients {
+                clock.advance(Duration::from_millis(1));
+
+                manager.report_frame_present(client_id, target_timestamp, Duration::ZERO);
+                manager.report_frame_composed(client_id, target_timestamp, Duration::ZERO);
+                manager.report_frame_encoded(client_id, target_timestamp, 10_000, false);
+                manager.report_frame_sent(client_id, target_timestamp, frame_index, 10);
+                manager.report_network_statistics(
+                    client_id,
+                    network_stats(frame_index as i32, 9, received_shards),
+                    Duration::ZERO,
+                );
+            }
+
+            for &(client_id, game_ms, received_shards, latency_ms, frames_dropped) in &clients {
+                let client_stats = ClientStatistics {
+                    target_timestamp,
+                    frame_index: frame_index as i32,
+                    frames_dropped,
+                    total_pipeline_latency: Duration::from_millis(latency_ms),
+                    ..Default::default()
+                };
+                let (network_latency, _, shard_loss_ratio) =
+                    manager.report_statistics(client_id, client_stats).unwrap();
+
+                assert_eq!(network_latency, Duration::from_millis(latency_ms - game_ms));
+                assert!((shard_loss_ratio - (10 - received_shards) as f32 / 10.0).abs() < 1e-6);
+            }
+        }
+
+        let graph_clients = sink
+            .events
+            .lock()
+            .iter()
+            .filter_map(|event| match event {
+                EventType::GraphStatistics(graph) => Some((
+                    graph.client_id,
+                    graph.game_time_s,
+                    graph.network_s,
+                    graph.frame_loss_ratio,
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(graph_clients.len(), 6);
+        // Each client presents one frame per report, so its frame loss ratio is its own drop count
+        for (client_id, game_time_s, network_s, frame_loss_ratio) in graph_clients {
+            let (expected_game_s, expected_network_s, expected_frame_loss_ratio) = if client_id == 1
+            {
+                (0.001, 0.019, 0.0)
+            } else {
+                (0.002, 0.048, 1.0)
+            };
+            assert!((game_time_s - expected_game_s).abs() < 1e-6);
+            assert!((network_s - expected_network_s).abs() < 1e-6);
+            assert!((frame_loss_ratio - expected_frame_loss_ratio).abs() < 1e-6);
+        }
+    }
+
+    #[cfg(feature = "frame-tracing")]
+    #[test]
+    fn test_frame_span_per_reported_frame() {
+        use crate::frame_tracing::tests::MockExporter;
+
+        let exporter = Arc::new(MockExporter::default());
+
+        let mut manager = new_manager();
+        manager.set_span_exporter(Arc::clone(&exporter) as Arc<dyn SpanExporter>);
+
+        let target_timestamp = Duration::from_millis(11);
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::ZERO);
+        manager.report_frame_composed(CLIENT_ID, target_timestamp, Duration::ZERO);
+        manager.report_frame_encoded(CLIENT_ID, target_timestamp, 10_000, false);
+        manager.report_frame_sent(CLIENT_ID, target_timestamp, 0, 10);
+
+        let client_stats = ClientStatistics {
+            target_timestamp,
+            frame_index: 0,
+            video_decode: Duration::from_millis(3),
+            video_decoder_queue: Duration::from_millis(1),
+            rendering: Duration::from_millis(2),
+            vsync_queue: Duration::from_millis(4),
+            total_pipeline_latency: Duration::from_millis(50),
+            ..Default::default()
+        };
+        assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+
+        let spans = exporter.spans.lock();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].target_timestamp, target_timestamp);
+
+        let event_names = spans[0]
+            .events
+            .iter()
+            .map(|event| event.name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            event_names,
+            vec![
+                "tracking_received",
+                "frame_present",
+                "frame_composed",
+                "frame_encoded",
+                "client_decode",
+                "client_compositor",
+                "client_submit",
+            ]
+        );
+
+        let attribute = |name| {
+            spans[0]
+                .events
+                .iter()
+                .flat_map(|event| &event.attributes)
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .unwrap()
+        };
+        assert!((attribute("decoder") - 0.003).abs() < 1e-9);
+        assert!((attribute("decoder_queue") - 0.001).abs() < 1e-9);
+        assert!((attribute("client_compositor") - 0.002).abs() < 1e-9);
+        assert!((attribute("vsync_queue") - 0.004).abs() < 1e-9);
+        assert!((attribute("total_pipeline") - 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_latency_breakdown_averages() {
+        // The window holds exactly the two frames below, the initial zero sample is evicted
+        let mut manager =
+            StatisticsManager::with_history_size(2, Duration::from_secs_f32(1.0 / 90.0), 0.0);
+
+        // Game time, server compositor and encoder latencies of 4/2/3 ms and 6/4/5 ms
+        let start = Instant::now();
+        for (frame_index, stages_ms) in [[4, 2, 3], [6, 4, 5]].into_iter().enumerate() {
+            let tracking_received = start + Duration::from_millis(frame_index as u64 * 20);
+            let frame_present = tracking_received + Duration::from_millis(stages_ms[0]);
+            let frame_composed = frame_present + Duration::from_millis(stages_ms[1]);
+            let frame_encoded = frame_composed + Duration::from_millis(stages_ms[2]);
+
+            push_history_frame(
+                &mut manager,
+                CLIENT_ID,
+                HistoryFrame {
+                    target_timestamp: Duration::from_millis(frame_index as u64 * 11),
+                    tracking_received,
+                    frame_present,
+                    frame_composed,
+                    frame_encoded,
+                    frame_index: frame_index as i32,
+                    is_composed: true,
+                    is_encoded: true,
+                    ..Default::default()
+                },
+            );
+
+            // The client stages add up to 10 ms, so the network latency is 10 ms for both frames
+            let client_stats = ClientStatistics {
+                target_timestamp: Duration::from_millis(frame_index as u64 * 11),
+                frame_index: frame_index as i32,
+                video_decode: Duration::from_millis(4),
+                video_decoder_queue: Duration::from_millis(1),
+                rendering: Duration::from_millis(2),
+                vsync_queue: Duration::from_millis(3),
+                total_pipeline_latency: Duration::from_millis(
+                    stages_ms.iter().sum::<u64>() + 10 + 10,
+                ),
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        }
+
+        assert_eq!(
+            manager.game_time_latency_average(),
+            Duration::from_millis(5)
+        );
+        assert_eq!(
+            manager.server_compositor_latency_average(),
+            Duration::from_millis(3)
+        );
+        assert_eq!(manager.encoder_latency_average(), Duration::from_millis(4));
+        assert_eq!(manager.network_latency_average(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_network_latency_underflow() {
+        let sink = Arc::new(CapturingSink::default());
+
+        let mut manager = new_manager();
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        let start = Instant::now();
+        for frame_index in 0..2 {
+            // 9 ms of server latencies
+            let tracking_received = start + Duration::from_millis(frame_index as u64 * 20);
+            push_history_frame(
+                &mut manager,
+                CLIENT_ID,
+                HistoryFrame {
+                    target_timestamp: Duration::from_millis(frame_index as u64 * 11),
+                    tracking_received,
+                    frame_present: tracking_received + Duration::from_millis(4),
+                    frame_composed: tracking_received + Duration::from_millis(6),
+                    frame_encoded: tracking_received + Duration::from_millis(9),
+                    frame_index,
+                    is_composed: true,
+                    is_encoded: true,
+                    ..Default::default()
+                },
+            );
+
+            // 10 ms of client latencies, 4 ms more than the total
+            let client_stats = ClientStatistics {
+                target_timestamp: Duration::from_millis(frame_index as u64 * 11),
+                frame_index,
+                video_decode: Duration::from_millis(4),
+                video_decoder_queue: Duration::from_millis(1),
+                rendering: Duration::from_millis(2),
+                vsync_queue: Duration::from_millis(3),
+                total_pipeline_latency: Duration::from_millis(15),
+                ..Default::default()
+            };
+            let (network_latency, ..) = manager.report_statistics(CLIENT_ID, client_stats).unwrap();
+            assert_eq!(network_latency, Duration::ZERO);
+        }
+
+        manager.last_full_report_instant -= Duration::from_secs(1);
+        assert!(manager.report_statistics_summary());
+
+        let events = sink.events.lock();
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                EventType::StatisticsSummary(summary) => Some(summary),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(summary.network_latency_underflow_count, 2);
+        assert!((summary.network_latency_underflow_total_ms - 8.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_clock_skew_recovers_one_way_delay_bias() {
+        let sink = Arc::new(CapturingSink::default());
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+        manager.set_sink(Arc::clone(&sink) as Arc<dyn StatsSink>);
+
+        let rtt = Duration::from_millis(10);
+        let bias_s = 0.02;
+
+        for frame_index in 0..100 {
+            // Jitter around the biased one-way delay
+            let jitter_s = if frame_index % 2 == 0 { 0.001 } else { -0.001 };
+            let network_stats = NetworkStatisticsPacket {
+                filtered_ow_delay: rtt.as_secs_f32() / 2.0 + bias_s + jitter_s,
+                ..network_stats(frame_index, 9, 10)
+            };
+            manager.report_network_statistics(CLIENT_ID, network_stats, rtt);
+            clock.advance(Duration::from_millis(10));
+        }
+
+        assert!(manager.report_statistics_summary());
+
+        let events = sink.events.lock();
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                EventType::StatisticsSummary(summary) => Some(summary),
+                _ => None,
+            })
+            .unwrap();
+        assert!((summary.estimated_clock_skew_s - bias_s).abs() < 0.002);
+    }
+
+    #[test]
+    fn test_frame_present_offset_is_clamped_to_tracking() {
+        let clock = Arc::new(MockClock::default());
+        let mut manager = new_manager_with_clock(Arc::clone(&clock));
+
+        let target_timestamp = Duration::from_millis(10);
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        clock.advance(Duration::from_millis(5));
+        manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::from_millis(1));
+        assert_eq!(manager.frame_present_offset_clamp_count, 0);
+
+        let target_timestamp = Duration::from_millis(20);
+        manager.report_tracking_received(CLIENT_ID, target_timestamp);
+        clock.advance(Duration::from_millis(5));
+        manager.report_frame_present(CLIENT_ID, target_timestamp, Duration::from_secs(1));
+        assert_eq!(manager.frame_present_offset_clamp_count, 1);
+
+        let frame = manager.clients[&CLIENT_ID]
+            .stats_history_buffer
+            .back()
+            .unwrap();
+        assert_eq!(frame.target_timestamp, target_timestamp);
+        assert_eq!(frame.frame_present, frame.tracking_received);
+    }
+
+    #[test]
+    fn test_server_frame_pacing_cv() {
+        let mut manager = new_manager();
+        assert_eq!(manager.server_frame_pacing_cv(), 0.0);
+
+        for _ in 0..256 {
+            manager
+                .frame_interval_average
+                .submit_sample(Duration::from_millis(11));
+        }
+        assert!(manager.server_frame_pacing_cv() < 1e-3);
+
+        for frame_index in 0..256 {
+            let interval_ms = if frame_index % 2 == 0 { 5 } else { 17 };
+            manager
+                .frame_interval_average
+                .submit_sample(Duration::from_millis(interval_ms));
+        }
+        assert!((manager.server_frame_pacing_cv() - 6.0 / 11.0).abs() < 0.01);
+    }
+
+    // Sends every frame of the sequence with 10 shards. All shards are received except for the
+    // frames in `lost_frames`, which are never reported
+    fn shard_loss_over_sequence(first_frame: u32, count: u32, lost_frames: &[u32]) -> f32 {
+        let mut manager = new_manager();
+
+        for i in 0..count {
+            let frame_index = first_frame.wrapping_add(i);
+            manager.report_frame_sent(CLIENT_ID, Duration::ZERO, frame_index, 10);
+
+            if !lost_frames.contains(&frame_index) {
+                manager.report_network_statistics(
+                    CLIENT_ID,
+                    network_stats(frame_index as i32, 9, 10),
+                    Duration::ZERO,
+                );
+            }
+        }
+
+        manager.take_loss_ratios(CLIENT_ID, 0).0
+    }
+
+    #[test]
+    fn test_frame_index_wrap_around() {
+        let half_range = 1_u32 << 31;
+
+        assert_eq!(frame_index_delta(half_range - 1, half_range), 1);
+        assert_eq!(frame_index_delta(u32::MAX, 0), 1);
+        assert_eq!(frame_index_delta(0, u32::MAX), -1);
+
+        // Crossing the i32 boundary and the u32 boundary without losses
+        assert_eq!(shard_loss_over_sequence(half_range - 3, 6, &[]), 0.0);
+        assert_eq!(shard_loss_over_sequence(u32::MAX - 2, 6, &[]), 0.0);
+
+        // One lost frame out of five, right at the boundary
+        let loss = shard_loss_over_sequence(half_range - 2, 5, &[half_range]);
+        assert!((loss - 0.2).abs() < 1e-6);
+        let loss = shard_loss_over_sequence(u32::MAX - 1, 5, &[0]);
+        assert!((loss - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_latency_histograms() {
+        let mut manager = new_manager();
+
+        // Bounds are 10, 20 and 50 ms. The network latency is 5 ms less than the total, since the
+        // client stages add up to 5 ms and the server stages are zero
+        let latencies_ms = [5, 10, 12, 18, 25, 40, 50, 70, 300];
+        for (frame_index, latency_ms) in latencies_ms.into_iter().enumerate() {
+            push_history_frame(
+                &mut manager,
+                CLIENT_ID,
+                HistoryFrame {
+                    frame_index: frame_index as i32,
+                    ..Default::default()
+                },
+            );
+
+            let client_stats = ClientStatistics {
+                frame_index: frame_index as i32,
+                video_decode: Duration::from_millis(5),
+                total_pipeline_latency: Duration::from_millis(latency_ms),
+                ..Default::default()
+            };
+            assert!(manager.report_statistics(CLIENT_ID, client_stats).is_some());
+        }
+
+        assert_eq!(
+            manager.total_pipeline_latency_histogram().counts(),
+            &[2, 2, 3, 2]
+        );
+        assert_eq!(manager.network_latency_histogram().counts(), &[3, 2, 2, 2]);
+    }
+}
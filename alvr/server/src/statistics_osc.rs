@@ -0,0 +1,165 @@
+use alvr_common::anyhow::Result;
+use alvr_events::{Event, EventType, StatisticsSummary};
+use alvr_session::StatisticsOscConfig;
+use rosc::{OscBundle, OscMessage, OscPacket, OscTime, OscType};
+use tokio::{
+    net::UdpSocket,
+    sync::broadcast::{self, error::RecvError},
+};
+
+// Address layout, all values are floats clamped to 0..1:
+// <prefix>/Bitrate: video bitrate divided by max_bitrate_mbps
+// <prefix>/Latency: total pipeline latency divided by max_latency_ms
+// <prefix>/Loss: shard loss ratio of the last frame
+fn encode_bundle(
+    config: &StatisticsOscConfig,
+    summary: &StatisticsSummary,
+    shard_loss_ratio: f32,
+) -> Vec<u8> {
+    let prefix = config.address_prefix.trim_end_matches('/');
+
+    let values = [
+        (
+            "Bitrate",
+            summary.video_mbits_per_sec / config.max_bitrate_mbps,
+        ),
+        (
+            "Latency",
+            summary.total_pipeline_latency_average_ms / config.max_latency_ms,
+        ),
+        ("Loss", shard_loss_ratio),
+    ];
+
+    let content = values
+        .into_iter()
+        .map(|(name, value)| {
+            // NaN (zero max in the config) is sent as 0
+            let value = if value.is_nan() {
+                0.0
+            } else {
+                value.clamp(0.0, 1.0)
+            };
+
+            OscPacket::Message(OscMessage {
+                addr: format!("{prefix}/{name}"),
+                args: vec![OscType::Float(value)],
+            })
+        })
+        .collect();
+
+    rosc::encoder::encode(&OscPacket::Bundle(OscBundle {
+        timetag: OscTime::from((0, 1)), // immediately
+        content,
+    }))
+    .unwrap()
+}
+
+// Sends one OSC bundle per StatisticsSummary. Sending never waits for the receiver and errors
+// (nobody listening) are ignored. The receiver should be subscribed before the sender is spawned
+pub async fn statistics_osc_sender(
+    config: StatisticsOscConfig,
+    mut events_receiver: broadcast::Receiver<Event>,
+) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(&config.destination).await?;
+
+    let mut shard_loss_ratio = 0.0;
+    loop {
+        match events_receiver.recv().await {
+            Ok(event) => match event.event_type {
+                EventType::StatisticsSummary(summary) => {
+                    // try_send() would drop bundles until the socket is first polled as writable
+                    socket
+                        .send(&encode_bundle(&config, &summary, shard_loss_ratio))
+                        .await
+                        .ok();
+                }
+                EventType::GraphStatistics(graph) => shard_loss_ratio = graph.shard_loss_ratio,
+                _ => (),
+            },
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_events::GraphStatistics;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_receive_statistics_bundle() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let config = StatisticsOscConfig {
+            destination: receiver.local_addr().unwrap().to_string(),
+            address_prefix: "/avatar/parameters/ALVR/".into(),
+            max_bitrate_mbps: 200.0,
+            max_latency_ms: 100.0,
+        };
+
+        let (events_sender, events_receiver) = broadcast::channel(16);
+        tokio::spawn(statistics_osc_sender(config, events_receiver));
+
+        for event_type in [
+            EventType::GraphStatistics(GraphStatistics {
+                shard_loss_ratio: 0.25,
+                ..Default::default()
+            }),
+            EventType::StatisticsSummary(StatisticsSummary {
+                video_mbits_per_sec: 50.0,
+                total_pipeline_latency_average_ms: 150.0,
+                ..Default::default()
+            }),
+        ] {
+            events_sender
+                .send(Event {
+                    timestamp: String::new(),
+                    event_type,
+                })
+                .unwrap();
+        }
+
+        let mut buffer = [0; 1024];
+        let size = tokio::time::timeout(Duration::from_secs(5), receiver.recv(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (_, OscPacket::Bundle(bundle)) = rosc::decoder::decode_udp(&buffer[..size]).unwrap()
+        else {
+            panic!("Expected an OSC bundle");
+        };
+
+        let values = bundle
+            .content
+            .into_iter()
+            .map(|packet| match packet {
+                OscPacket::Message(OscMessage { addr, args }) => (addr, args),
+                OscPacket::Bundle(_) => panic!("Unexpected nested bundle"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            values,
+            vec![
+                (
+                    "/avatar/parameters/ALVR/Bitrate".into(),
+                    vec![OscType::Float(0.25)]
+                ),
+                (
+                    "/avatar/parameters/ALVR/Latency".into(),
+                    vec![OscType::Float(1.0)]
+                ),
+                (
+                    "/avatar/parameters/ALVR/Loss".into(),
+                    vec![OscType::Float(0.25)]
+                ),
+            ]
+        );
+    }
+}
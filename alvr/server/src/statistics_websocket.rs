@@ -0,0 +1,122 @@
+use crate::web_server::WS_BROADCAST_CAPACITY;
+use alvr_common::{anyhow::Result, info};
+use alvr_events::{Event, EventType};
+use futures::{SinkExt, StreamExt};
+use serde_json as json;
+use std::net::SocketAddr;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+async fn send_to_client(
+    stream: TcpStream,
+    mut json_receiver: broadcast::Receiver<String>,
+) -> Result<()> {
+    let mut ws = tokio_tungstenite::accept_async(stream).await?;
+
+    loop {
+        tokio::select! {
+            maybe_json = json_receiver.recv() => match maybe_json {
+                Ok(json) => ws.send(Message::Text(json)).await?,
+                // A stuck client loses the oldest frames, the other clients are not affected
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            maybe_message = ws.next() => match maybe_message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        }
+    }
+
+    ws.close(None).await.ok();
+
+    Ok(())
+}
+
+async fn serve(
+    listener: TcpListener,
+    mut events_receiver: broadcast::Receiver<Event>,
+) -> Result<()> {
+    // GraphStatistics are serialized once and shared by all clients
+    let (json_sender, _) = broadcast::channel(WS_BROADCAST_CAPACITY);
+
+    tokio::spawn({
+        let json_sender = json_sender.clone();
+        async move {
+            loop {
+                match events_receiver.recv().await {
+                    Ok(Event {
+                        event_type: EventType::GraphStatistics(graph),
+                        ..
+                    }) => {
+                        json_sender.send(json::to_string(&graph).unwrap()).ok();
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+    });
+
+    loop {
+        let (stream, address) = listener.accept().await?;
+
+        let json_receiver = json_sender.subscribe();
+        tokio::spawn(async move {
+            if let Err(e) = send_to_client(stream, json_receiver).await {
+                info!("Statistics websocket client {address} disconnected: {e}");
+            }
+        });
+    }
+}
+
+// Pushes every GraphStatistics as JSON to all connected WebSocket clients. The receiver should be
+// subscribed before the server is spawned to avoid missing events
+pub async fn statistics_websocket_server(
+    port: u16,
+    events_receiver: broadcast::Receiver<Event>,
+) -> Result<()> {
+    let listener = TcpListener::bind(SocketAddr::new("0.0.0.0".parse().unwrap(), port)).await?;
+
+    serve(listener, events_receiver).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        statistics::tests::{new_manager, report_complete_frame},
+        stats_sink::tests::BroadcastSink,
+    };
+    use alvr_events::GraphStatistics;
+    use std::{sync::Arc, time::Duration};
+
+    #[tokio::test]
+    async fn test_push_graph_statistics() {
+        let (events_sender, events_receiver) = broadcast::channel(16);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, events_receiver));
+
+        // The client is subscribed once the handshake is complete
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{address}"))
+            .await
+            .unwrap();
+
+        let mut manager = new_manager();
+        manager.set_sink(Arc::new(BroadcastSink(events_sender)));
+        report_complete_frame(&mut manager, 1, Duration::from_millis(11), 7);
+
+        let Some(Ok(Message::Text(text))) = ws.next().await else {
+            panic!("expected a text frame");
+        };
+        let graph = json::from_str::<GraphStatistics>(&text).unwrap();
+        assert_eq!(graph.frame_index, 7);
+
+        ws.close(None).await.unwrap();
+    }
+}
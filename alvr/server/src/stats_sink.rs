@@ -0,0 +1,48 @@
+use alvr_events::EventType;
+
+// Destination of the events produced by StatisticsManager and BitrateManager. Allows tests and
+// other tools to capture the events instead of going through the logger
+pub trait StatsSink: Send + Sync {
+    fn emit(&self, event_type: EventType);
+}
+
+pub struct EventsSink;
+
+impl StatsSink for EventsSink {
+    fn emit(&self, event_type: EventType) {
+        alvr_events::send_event(event_type);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use alvr_common::parking_lot::Mutex;
+    use alvr_events::Event;
+    use tokio::sync::broadcast;
+
+    #[derive(Default)]
+    pub struct CapturingSink {
+        pub events: Mutex<Vec<EventType>>,
+    }
+
+    impl StatsSink for CapturingSink {
+        fn emit(&self, event_type: EventType) {
+            self.events.lock().push(event_type);
+        }
+    }
+
+    // Feeds the events to the servers that subscribe to the events broadcast
+    pub struct BroadcastSink(pub broadcast::Sender<Event>);
+
+    impl StatsSink for BroadcastSink {
+        fn emit(&self, event_type: EventType) {
+            self.0
+                .send(Event {
+                    timestamp: String::new(),
+                    event_type,
+                })
+                .ok();
+        }
+    }
+}
@@ -222,6 +222,14 @@ pub enum MediacodecDataType {
     String(String),
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[schema(gui = "button_group")]
+pub enum EncoderLatencyReferenceInterval {
+    Automatic,
+    Nominal,
+    Measured,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
 pub struct EncoderLatencyLimiter {
     #[schema(strings(
@@ -230,6 +238,59 @@ pub struct EncoderLatencyLimiter {
     #[schema(flag = "real-time")]
     #[schema(gui(slider(min = 0.3, max = 1.0, step = 0.01)))]
     pub max_saturation_multiplier: f32,
+
+    #[schema(strings(
+        help = "Gate on this percentile of the encoder latency instead of the mean, so that occasional latency spikes are limited too"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 50.0, max = 100.0, step = 1.0)))]
+    pub latency_percentile: Switch<f32>,
+
+    #[schema(strings(
+        help = "Frame interval the encoder latency is compared to. Automatic uses the measured frame interval when the bitrate adapts to the framerate and the nominal one otherwise"
+    ))]
+    #[schema(flag = "real-time")]
+    pub reference_interval: EncoderLatencyReferenceInterval,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ColdStartConfig {
+    #[schema(strings(help = "Maximum length of the ramp from the start of the session"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 30.0, step = 0.5)), suffix = "s")]
+    pub duration_s: f32,
+
+    #[schema(strings(help = "Multiplier applied to the bitrate at each update during the ramp"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.01, max = 2.0, step = 0.01)), suffix = "x")]
+    pub growth_factor: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PacketLossLimiter {
+    #[schema(strings(
+        display_name = "TCP-friendly constant",
+        help = "Constant of the TCP-friendly rate formula: constant * packet size / (RTT * sqrt(packet loss ratio))"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.5, max = 5.0, step = 0.01)))]
+    pub tcp_friendly_constant: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct DecoderLatencyRecoveryConfig {
+    #[schema(strings(
+        help = "Time without latency oversteps before each raise of the bitrate cap"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 60.0, step = 1.0)), suffix = "s")]
+    pub interval_s: f32,
+
+    #[schema(strings(help = "Factor applied to the bitrate cap at each raise"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.01, max = 2.0, step = 0.01)), suffix = "x")]
+    pub multiplier: f32,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
@@ -257,6 +318,232 @@ pub struct DecoderLatencyLimiter {
     #[schema(flag = "real-time")]
     #[schema(gui(slider(min = 0.5, max = 1.0)))]
     pub latency_overstep_multiplier: f32,
+
+    #[schema(strings(
+        display_name = "Panic decoder latency",
+        help = "A single frame with a decoder latency above this threshold halves the bitrate immediately, without waiting for the overstep frames"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 10, max = 500, logarithmic)), suffix = "ms")]
+    pub panic_decoder_latency_ms: Switch<u64>,
+
+    #[schema(strings(
+        help = "Gradually raise the bitrate cap set by this limiter while the decoder latency stays below the threshold. When disabled the cap only ever goes down"
+    ))]
+    #[schema(flag = "real-time")]
+    pub recovery: Switch<DecoderLatencyRecoveryConfig>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct ThresholdAnnealingConfig {
+    #[schema(strings(help = "Threshold of the random uniform distribution at the session start"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub start_value: f32,
+
+    #[schema(strings(help = "Threshold the random uniform distribution decays towards"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub end_value: f32,
+
+    #[schema(strings(
+        display_name = "Decay time constant",
+        help = "Time after which the threshold has covered ~63% of the distance to the end value"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 600.0, logarithmic)), suffix = "s")]
+    pub decay_time_constant_s: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct CapacityStalenessConfig {
+    #[schema(strings(
+        display_name = "Staleness window",
+        help = "Time without throughput samples after which the capacity estimate starts decaying"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 10.0, logarithmic)), suffix = "s")]
+    pub staleness_window_s: f32,
+
+    #[schema(strings(
+        display_name = "Decay time constant",
+        help = "Time after the staleness window at which the estimate has covered ~63% of the distance to the floor"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 60.0, logarithmic)), suffix = "s")]
+    pub decay_time_constant_s: f32,
+
+    #[schema(strings(
+        display_name = "Floor",
+        help = "Conservative capacity estimate the stale estimate decays towards"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 100.0, logarithmic)), suffix = "Mbps")]
+    pub floor_mbps: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[schema(gui = "button_group")]
+pub enum PeakThroughputEstimator {
+    Average,
+    #[schema(strings(display_name = "Maximum"))]
+    Max,
+    #[schema(strings(display_name = "Harmonic mean"))]
+    HarmonicMean,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct AdaptiveBitrateConfig {
+    #[schema(strings(
+        help = "Percentage of network bandwidth to allocate for video transmission"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.5, max = 5.0, step = 0.01)))]
+    pub saturation_multiplier: f32,
+
+    #[schema(strings(display_name = "Maximum bitrate"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1, max = 1000, logarithmic)), suffix = "Mbps")]
+    pub max_bitrate_mbps: Switch<u64>,
+
+    #[schema(strings(display_name = "Minimum bitrate"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1, max = 100, logarithmic)), suffix = "Mbps")]
+    pub min_bitrate_mbps: Switch<u64>,
+
+    #[schema(strings(display_name = "Maximum network latency"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1, max = 50)), suffix = "ms")]
+    pub max_network_latency_ms: Switch<u64>,
+
+    #[schema(strings(
+        display_name = "Network latency limiter maximum drop",
+        help = "Maximum fraction by which the network latency limiter can lower its ceiling in a single update. Prevents single latency spikes from collapsing the bitrate"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.01, max = 1.0, step = 0.01)))]
+    pub network_latency_limiter_max_drop: Switch<f32>,
+
+    #[schema(flag = "real-time")]
+    pub encoder_latency_limiter: Switch<EncoderLatencyLimiter>,
+
+    #[schema(strings(
+        help = "Limit the bitrate with the TCP-friendly rate for the recent shard loss ratio"
+    ))]
+    #[schema(flag = "real-time")]
+    pub packet_loss_limiter: Switch<PacketLossLimiter>,
+
+    #[schema(strings(
+        help = "Currently there is a bug where the decoder latency keeps rising when above a certain bitrate"
+    ))]
+    #[schema(flag = "real-time")]
+    pub decoder_latency_limiter: Switch<DecoderLatencyLimiter>,
+
+    #[schema(strings(
+        display_name = "Content-limited fill ratio",
+        help = "When the encoded frames fill less than this fraction of the requested bitrate, the scene is considered simple and the bitrate is not decreased"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub content_limited_fill_ratio: Switch<f32>,
+
+    #[schema(strings(
+        help = "Ramp the bitrate multiplicatively at the start of the session, like TCP slow start, until packet loss or a network latency above the maximum is detected"
+    ))]
+    #[schema(flag = "real-time")]
+    pub cold_start: Switch<ColdStartConfig>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct SimpleHeuristicConfig {
+    #[schema(strings(display_name = "Maximum bitrate"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 1000.0, logarithmic)))]
+    pub max_bitrate_mbps: Switch<f32>,
+    #[schema(strings(display_name = "Minimum bitrate"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 100.0, logarithmic)))]
+    pub min_bitrate_mbps: Switch<f32>,
+    #[schema(strings(display_name = "Steps of heuristic in mbps"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 100.0, logarithmic)))]
+    pub steps_mbps: Switch<f32>,
+
+    #[schema(strings(display_name = "Threshold of Random Uniform distribution"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, logarithmic)))]
+    pub threshold_random_uniform: Switch<f32>,
+
+    #[schema(strings(
+        display_name = "Threshold annealing",
+        help = "Decay the threshold of the random uniform distribution over the session, overriding the static value"
+    ))]
+    #[schema(flag = "real-time")]
+    pub threshold_annealing: Switch<ThresholdAnnealingConfig>,
+
+    #[schema(strings(display_name = "Update Interval for heuristic"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 5.0, logarithmic)))]
+    pub update_interval_heuristic: Switch<f32>,
+
+    #[schema(strings(display_name = "Multiplier for the threshold of 1/FPS in heuristic"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 5.0, logarithmic)))]
+    pub multiplier_rtt_threshold: Switch<f32>,
+
+    #[schema(strings(display_name = "Threshold of 1/FPS in heuristic"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 2.0, logarithmic)))]
+    pub fps_threshold_multiplier: Switch<f32>,
+
+    #[schema(strings(
+        display_name = "Capacity estimation",
+        help = "Statistic of the peak throughput window used to limit the bitrate"
+    ))]
+    #[schema(flag = "real-time")]
+    pub capacity_estimation: PeakThroughputEstimator,
+
+    #[schema(strings(
+        display_name = "Capacity from goodput",
+        help = "Estimate the capacity from the unique video payload only, excluding duplicated and retransmitted shards"
+    ))]
+    #[schema(flag = "real-time")]
+    pub capacity_from_goodput: bool,
+
+    #[schema(strings(
+        display_name = "Capacity staleness decay",
+        help = "Decay the capacity estimate when no throughput samples have been received recently"
+    ))]
+    #[schema(flag = "real-time")]
+    pub capacity_staleness: Switch<CapacityStalenessConfig>,
+
+    #[schema(strings(
+        display_name = "Maximum duplicate ratio",
+        help = "Bitrate increases are suppressed when the fraction of duplicated shards over received shards exceeds this value"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub max_duplicate_ratio: Switch<f32>,
+
+    #[schema(strings(
+        display_name = "Capacity warm-up samples",
+        help = "The capacity is considered unknown until this many throughput samples have been received. Meanwhile the bitrate is not increased"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1, max = 100)))]
+    pub capacity_warmup_samples: Switch<usize>,
+
+    #[schema(strings(
+        display_name = "Capacity binding backoff",
+        help = "When increases keep being clamped by the capacity limit, skip the following increase attempts for exponentially more updates, up to this many, until the capacity estimate rises"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1, max = 64)))]
+    pub capacity_binding_backoff: Switch<u32>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
@@ -265,14 +552,67 @@ pub enum BitrateMode {
     #[schema(strings(display_name = "Constant"))]
     ConstantMbps(#[schema(gui(slider(min = 5, max = 1000, logarithmic)), suffix = "Mbps")] u64),
 
+    Adaptive(AdaptiveBitrateConfig),
+    SimpleHeuristic(SimpleHeuristicConfig),
+    #[schema(collapsible)]
+    BufferBased {
+        #[schema(strings(
+            display_name = "Reservoir",
+            help = "Below this decoder queue latency the minimum bitrate is used"
+        ))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 0, max = 50)), suffix = "ms")]
+        reservoir_ms: u64,
+
+        #[schema(strings(
+            display_name = "Cushion",
+            help = "Above this decoder queue latency the maximum bitrate is used"
+        ))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1, max = 100)), suffix = "ms")]
+        cushion_ms: u64,
+
+        #[schema(strings(display_name = "Maximum bitrate"))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1, max = 1000, logarithmic)), suffix = "Mbps")]
+        max_bitrate_mbps: u64,
+
+        #[schema(strings(display_name = "Minimum bitrate"))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1, max = 100, logarithmic)), suffix = "Mbps")]
+        min_bitrate_mbps: u64,
+    },
+    #[schema(strings(display_name = "BBR"))]
     #[schema(collapsible)]
-    Adaptive {
+    Bbr {
         #[schema(strings(
-            help = "Percentage of network bandwidth to allocate for video transmission"
+            display_name = "Probe interval",
+            help = "Interval between bitrate updates that probe for more bandwidth"
         ))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 0.5, max = 5.0, step = 0.01)))]
-        saturation_multiplier: f32,
+        #[schema(gui(slider(min = 1.0, max = 30.0)), suffix = "s")]
+        probe_interval_s: f32,
+
+        #[schema(strings(help = "Multiplier of the estimated bandwidth used while probing"))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1.0, max = 2.0, step = 0.01)))]
+        probe_gain: f32,
+
+        #[schema(strings(
+            display_name = "Minimum RTT window",
+            help = "The minimum RTT is forgotten after this time, so it is reset if the RTT stays elevated"
+        ))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1.0, max = 60.0)), suffix = "s")]
+        min_rtt_window_s: f32,
+
+        #[schema(strings(
+            display_name = "Maximum RTT inflation",
+            help = "When the RTT average exceeds the minimum RTT by this factor, the bitrate is reduced to drain the queue"
+        ))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1.0, max = 5.0, step = 0.1)))]
+        max_rtt_inflation: f32,
 
         #[schema(strings(display_name = "Maximum bitrate"))]
         #[schema(flag = "real-time")]
@@ -283,58 +623,136 @@ pub enum BitrateMode {
         #[schema(flag = "real-time")]
         #[schema(gui(slider(min = 1, max = 100, logarithmic)), suffix = "Mbps")]
         min_bitrate_mbps: Switch<u64>,
+    },
+    #[schema(strings(display_name = "MPC"))]
+    #[schema(collapsible)]
+    Mpc {
+        #[schema(strings(help = "Number of future bitrate updates the QoE is predicted over"))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1, max = 5)))]
+        horizon: usize,
 
-        #[schema(strings(display_name = "Maximum network latency"))]
+        #[schema(strings(
+            help = "Number of candidate bitrate steps above and below the current bitrate evaluated at each update"
+        ))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 1, max = 50)), suffix = "ms")]
-        max_network_latency_ms: Switch<u64>,
+        #[schema(gui(slider(min = 1, max = 5)))]
+        candidate_steps: usize,
 
+        #[schema(strings(display_name = "Candidate step"))]
         #[schema(flag = "real-time")]
-        encoder_latency_limiter: Switch<EncoderLatencyLimiter>,
+        #[schema(gui(slider(min = 1.0, max = 50.0)), suffix = "Mbps")]
+        candidate_step_mbps: f32,
 
         #[schema(strings(
-            help = "Currently there is a bug where the decoder latency keeps rising when above a certain bitrate"
+            display_name = "QoE weights",
+            help = "Weights of the predicted QoE. Rebuffering is measured as the predicted decoder queue latency in milliseconds"
         ))]
+        qoe_weights: QoeWeightsConfig,
+
+        #[schema(strings(display_name = "Maximum bitrate"))]
         #[schema(flag = "real-time")]
-        decoder_latency_limiter: Switch<DecoderLatencyLimiter>,
+        #[schema(gui(slider(min = 1, max = 1000, logarithmic)), suffix = "Mbps")]
+        max_bitrate_mbps: u64,
+
+        #[schema(strings(display_name = "Minimum bitrate"))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1, max = 100, logarithmic)), suffix = "Mbps")]
+        min_bitrate_mbps: u64,
     },
+    #[schema(strings(display_name = "TFRC"))]
     #[schema(collapsible)]
-    SimpleHeuristic {
+    Tfrc {
         #[schema(strings(display_name = "Maximum bitrate"))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 1.0, max = 1000.0, logarithmic)))]
-        max_bitrate_mbps: Switch<f32>,
+        #[schema(gui(slider(min = 1, max = 1000, logarithmic)), suffix = "Mbps")]
+        max_bitrate_mbps: u64,
+
         #[schema(strings(display_name = "Minimum bitrate"))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 1.0, max = 100.0, logarithmic)))]
-        min_bitrate_mbps: Switch<f32>,
-        #[schema(strings(display_name = "Steps of heuristic in mbps"))]
+        #[schema(gui(slider(min = 1, max = 100, logarithmic)), suffix = "Mbps")]
+        min_bitrate_mbps: u64,
+    },
+    #[schema(strings(display_name = "AIMD"))]
+    #[schema(collapsible)]
+    Aimd {
+        #[schema(strings(
+            display_name = "Additive increase",
+            help = "Bitrate added at every update without a congestion signal"
+        ))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 1.0, max = 100.0, logarithmic)))]
-        steps_mbps: Switch<f32>,
+        #[schema(gui(slider(min = 0.5, max = 20.0, step = 0.5)), suffix = "Mbps")]
+        increase_mbps: f32,
 
-        #[schema(strings(display_name = "Threshold of Random Uniform distribution"))]
+        #[schema(strings(
+            display_name = "Multiplicative decrease",
+            help = "The bitrate is multiplied by this factor at every update with a congestion signal"
+        ))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 0.0, max = 1.0, logarithmic)))]
-        threshold_random_uniform: Switch<f32>,
+        #[schema(gui(slider(min = 0.1, max = 0.99, step = 0.01)))]
+        decrease_factor: f32,
 
-        #[schema(strings(display_name = "Update Interval for heuristic"))]
+        #[schema(strings(
+            display_name = "RTT inflation threshold",
+            help = "Congestion is signaled when the RTT average exceeds the frame interval times this factor, as in the simple heuristic"
+        ))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 0.0, max = 5.0, logarithmic)))]
-        update_interval_heuristic: Switch<f32>,
+        #[schema(gui(slider(min = 0.1, max = 5.0, step = 0.1)))]
+        rtt_inflation_threshold: f32,
 
-        #[schema(strings(display_name = "Multiplier for the threshold of 1/FPS in heuristic"))]
+        #[schema(strings(
+            display_name = "Packet loss threshold",
+            help = "Congestion is signaled when the shard loss ratio exceeds this value"
+        ))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 0.1, max = 5.0, logarithmic)))]
-        multiplier_rtt_threshold: Switch<f32>,
+        #[schema(gui(slider(min = 0.0, max = 0.2, step = 0.005)))]
+        loss_threshold: f32,
 
-        #[schema(strings(display_name = "Threshold of 1/FPS in heuristic"))]
+        #[schema(strings(display_name = "Maximum bitrate"))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1, max = 1000, logarithmic)), suffix = "Mbps")]
+        max_bitrate_mbps: u64,
+
+        #[schema(strings(display_name = "Minimum bitrate"))]
+        #[schema(flag = "real-time")]
+        #[schema(gui(slider(min = 1, max = 100, logarithmic)), suffix = "Mbps")]
+        min_bitrate_mbps: u64,
+    },
+    #[schema(collapsible)]
+    Blend {
+        #[schema(strings(
+            help = "Weight of the adaptive bitrate in the blend. The simple heuristic bitrate gets the rest"
+        ))]
         #[schema(flag = "real-time")]
-        #[schema(gui(slider(min = 0.1, max = 2.0, logarithmic)))]
-        fps_threshold_multiplier: Switch<f32>,
+        #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+        weight: f32,
+
+        #[schema(strings(display_name = "Adaptive"))]
+        adaptive_config: AdaptiveBitrateConfig,
+
+        #[schema(strings(display_name = "Simple heuristic"))]
+        heuristic_config: SimpleHeuristicConfig,
     },
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SustainedOutlierConfig {
+    #[schema(strings(
+        display_name = "Standard deviation multiplier",
+        help = "A frame interval is an outlier when it is farther than this many standard deviations from the mean"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 10.0, step = 0.5)))]
+    pub std_multiplier: f32,
+
+    #[schema(strings(
+        help = "Number of consecutive outlier frames that trigger a parameters update"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1, max = 90)), suffix = " frames")]
+    pub consecutive_frames: usize,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
 pub struct BitrateAdaptiveFramerateConfig {
     #[schema(strings(
@@ -344,6 +762,104 @@ pub struct BitrateAdaptiveFramerateConfig {
     #[schema(flag = "real-time")]
     #[schema(gui(slider(min = 1.0, max = 3.0, step = 0.1)))]
     pub framerate_reset_threshold_multiplier: f32,
+
+    #[schema(strings(
+        display_name = "Sustained outlier detection",
+        help = "Trigger a parameters update only when the frame interval is an outlier for several consecutive frames. When disabled, the FPS reset threshold multiplier is used"
+    ))]
+    #[schema(flag = "real-time")]
+    pub sustained_outlier_detection: Switch<SustainedOutlierConfig>,
+
+    #[schema(strings(
+        display_name = "Maximum framerate step",
+        help = "Maximum change of the framerate sent to the encoder in a single update, so that its rate control does not thrash when the frame interval average moves quickly"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 60.0, step = 1.0)), suffix = "Hz")]
+    pub max_framerate_step: Switch<f32>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct IdleResetConfig {
+    #[schema(strings(
+        display_name = "Timeout",
+        help = "A gap between presented frames longer than this is treated as a pause instead of a frame interval"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 10.0, logarithmic)), suffix = "s")]
+    pub timeout_s: f32,
+
+    #[schema(strings(
+        help = "On resume, keep only the most recent frame interval samples collected before the pause"
+    ))]
+    #[schema(flag = "real-time")]
+    pub reset_frame_interval_average: bool,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct QoeFloorConfig {
+    #[schema(strings(display_name = "Floor"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 100.0, logarithmic)), suffix = "Mbps")]
+    pub floor_mbps: f32,
+
+    #[schema(strings(
+        display_name = "Grace period",
+        help = "How long the floor is held while the bitrate mode asks for less. After that the bitrate is allowed to fall"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 10.0, logarithmic)), suffix = "s")]
+    pub grace_period_s: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct FecOverheadConfig {
+    #[schema(strings(
+        display_name = "Loss multiplier",
+        help = "FEC overhead suggested per unit of shard loss ratio"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 10.0, step = 0.1)))]
+    pub loss_multiplier: f32,
+
+    #[schema(strings(
+        display_name = "Maximum overhead",
+        help = "Ceiling of the suggested FEC overhead, as a fraction of the bitrate"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub max_overhead: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct AdaptiveUpdateIntervalConfig {
+    #[schema(strings(
+        display_name = "Minimum interval",
+        help = "Update interval used when the network volatility reaches the reference"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.05, max = 1.0, step = 0.05)), suffix = "s")]
+    pub min_interval_s: f32,
+
+    #[schema(strings(
+        display_name = "Maximum interval",
+        help = "Update interval used when the network is perfectly stable"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 10.0, step = 0.5)), suffix = "s")]
+    pub max_interval_s: f32,
+
+    #[schema(strings(
+        display_name = "Reference volatility",
+        help = "Coefficient of variation of the RTT or peak throughput at which the minimum interval is used"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.05, max = 2.0, step = 0.05)))]
+    pub reference_volatility: f32,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
@@ -367,6 +883,52 @@ This has an effect only on AMD GPUs."
     ))]
     #[schema(flag = "steamvr-restart")]
     pub image_corruption_fix: bool,
+
+    #[schema(strings(
+        display_name = "Left eye bitrate ratio",
+        help = "Fraction of the bitrate requested for the left eye by stereo-split encoders. The right eye gets the rest"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.05)))]
+    pub left_eye_bitrate_ratio: f32,
+
+    #[schema(strings(
+        display_name = "Maximum slew rate",
+        help = "Limit how fast the bitrate sent to the encoder can change, regardless of the bitrate mode. Abrupt changes can cause encoder artifacts"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 1.0, max = 500.0, logarithmic)), suffix = "Mbps/s")]
+    pub max_slew_rate_mbps_per_s: Switch<f32>,
+
+    #[schema(strings(
+        help = "Update the bitrate more often when the network is volatile and less often when it is stable. The heuristic update interval takes precedence"
+    ))]
+    #[schema(flag = "real-time")]
+    pub adaptive_update_interval: Switch<AdaptiveUpdateIntervalConfig>,
+
+    #[schema(strings(
+        help = "Detect when no frames are presented for a while (for example when the headset is set down) and resume without a spurious framerate reset"
+    ))]
+    pub idle_reset: Switch<IdleResetConfig>,
+
+    #[schema(strings(
+        display_name = "QoE floor",
+        help = "Keep the bitrate at the floor through short capacity dips, accepting some loss to avoid an unwatchable image. Unlike the minimum bitrate, the floor yields if the dip lasts longer than the grace period"
+    ))]
+    pub qoe_floor: Switch<QoeFloorConfig>,
+
+    #[schema(strings(
+        display_name = "FEC overhead",
+        help = "Suggest to the encoder a FEC overhead proportional to the recent shard loss, so that loss raises the redundancy and not only lowers the bitrate"
+    ))]
+    pub fec_overhead: Switch<FecOverheadConfig>,
+
+    #[schema(strings(
+        display_name = "Log bitrate decisions",
+        help = "Log every bitrate update as JSON, with the inputs, the decision before the limits and the binding constraint"
+    ))]
+    #[schema(flag = "real-time")]
+    pub decision_log: bool,
 }
 
 #[repr(u8)]
@@ -964,6 +1526,94 @@ pub enum SocketBufferSize {
     Custom(#[schema(suffix = "B")] u32),
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct QoeWeightsConfig {
+    #[schema(strings(help = "Score gained per Mbps of delivered video bitrate"))]
+    #[schema(gui(slider(min = 0.0, max = 10.0, step = 0.1)))]
+    pub bitrate: f32,
+
+    #[schema(strings(help = "Score lost per dropped or skipped frame per second"))]
+    #[schema(gui(slider(min = 0.0, max = 10.0, step = 0.1)))]
+    pub rebuffering: f32,
+
+    #[schema(strings(
+        help = "Score lost per Mbps of bitrate change between consecutive statistics summaries"
+    ))]
+    #[schema(gui(slider(min = 0.0, max = 10.0, step = 0.1)))]
+    pub bitrate_switch: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(gui = "button_group")]
+pub enum NetworkLatencyEstimator {
+    StageSubtraction,
+    #[schema(strings(display_name = "One-way delay"))]
+    OneWayDelay,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct ClientOwdFilterConfig {
+    #[schema(strings(
+        help = "Process noise of the filter of the one-way delay gradient. Lower values smooth more but react slower"
+    ))]
+    #[schema(gui(slider(min = 1e-10, max = 1e-5, logarithmic)))]
+    pub process_noise: f32,
+
+    #[schema(strings(
+        help = "Weight of the previous measurement noise estimate. Higher values smooth more"
+    ))]
+    #[schema(gui(slider(min = 0.5, max = 0.999, step = 0.001)))]
+    pub noise_estimation_smoothing: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct OwdKalmanFilterConfig {
+    #[schema(strings(
+        help = "How fast the one-way delay trend is expected to change. Higher values follow changes faster but smooth less"
+    ))]
+    #[schema(gui(slider(min = 0.01, max = 100.0, logarithmic)), suffix = "ms/s")]
+    pub process_noise_std_ms: f32,
+
+    #[schema(strings(help = "Standard deviation of the one-way delay samples around the trend"))]
+    #[schema(gui(slider(min = 0.1, max = 50.0, logarithmic)), suffix = "ms")]
+    pub measurement_noise_std_ms: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct LatencyBudgetsConfig {
+    #[schema(suffix = "ms")]
+    pub game_time_ms: Switch<f32>,
+
+    #[schema(suffix = "ms")]
+    pub server_compositor_ms: Switch<f32>,
+
+    #[schema(suffix = "ms")]
+    pub encoder_ms: Switch<f32>,
+
+    #[schema(suffix = "ms")]
+    pub network_ms: Switch<f32>,
+
+    #[schema(suffix = "ms")]
+    pub decoder_ms: Switch<f32>,
+
+    #[schema(suffix = "ms")]
+    pub decoder_queue_ms: Switch<f32>,
+
+    #[schema(suffix = "ms")]
+    pub rendering_ms: Switch<f32>,
+
+    #[schema(suffix = "ms")]
+    pub vsync_queue_ms: Switch<f32>,
+
+    #[schema(strings(help = "Minimum interval between two alarms for the same stage"))]
+    #[schema(gui(slider(min = 0.5, max = 60.0, step = 0.5)), suffix = "s")]
+    pub throttle_interval_s: f32,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 #[schema(collapsible)]
 pub struct ConnectionConfig {
@@ -1034,6 +1684,68 @@ For now works only on Windows+Nvidia"#
 
     #[schema(suffix = " frames")]
     pub statistics_history_size: usize,
+
+    #[schema(strings(
+        display_name = "Recent graph statistics size",
+        help = "Number of recent graph statistics kept to backfill a dashboard that connects mid-session. Uses the statistics history size when disabled"
+    ))]
+    #[schema(suffix = " frames")]
+    pub recent_graph_statistics_size: Switch<usize>,
+
+    #[schema(strings(
+        display_name = "Statistics averaging window size",
+        help = "Number of recent frames the latency averages are computed over. Uses the statistics history size when disabled"
+    ))]
+    #[schema(suffix = " frames")]
+    pub statistics_averaging_window_size: Switch<usize>,
+
+    #[schema(strings(help = "Interval between statistics summaries sent to the dashboard"))]
+    #[schema(gui(slider(min = 100, max = 5000, step = 100)), suffix = "ms")]
+    pub statistics_summary_interval_ms: u64,
+
+    #[schema(strings(
+        help = "A frame arriving later than this multiple of the nominal frame interval is counted as a stall"
+    ))]
+    #[schema(gui(slider(min = 1.5, max = 10.0, step = 0.5)), suffix = "x")]
+    pub stall_threshold_multiplier: f32,
+
+    #[schema(strings(
+        help = "Frame interarrivals longer than this multiple of the nominal frame interval are clamped before being averaged, so that a stall doesn't make the heuristic underestimate the framerate for a long time"
+    ))]
+    #[schema(gui(slider(min = 1.5, max = 10.0, step = 0.5)), suffix = "x")]
+    pub frame_interarrival_outlier_multiplier: Switch<f32>,
+
+    #[schema(strings(
+        help = "Stage subtraction: the network latency is what is left of the total latency after the other stages. One-way delay: the filtered one-way delay measured by the client, corrected for the clock skew, plus the frame span. Both are reported in the graph statistics, this selects the one used for the averages and the bitrate"
+    ))]
+    pub network_latency_estimator: NetworkLatencyEstimator,
+
+    #[schema(strings(
+        help = "Smoothing of the one-way delay measured by the client into a delay and a delay gradient estimate"
+    ))]
+    pub owd_kalman_filter: OwdKalmanFilterConfig,
+
+    #[schema(strings(
+        help = "Filter of the one-way delay gradient computed by the client for every video frame (filtered_ow_delay)"
+    ))]
+    pub client_owd_filter: ClientOwdFilterConfig,
+
+    #[schema(strings(
+        help = "Emit an alarm event when the latency of a pipeline stage of a frame exceeds its budget"
+    ))]
+    pub latency_budget_alarms: Switch<LatencyBudgetsConfig>,
+
+    #[schema(strings(
+        display_name = "QoE weights",
+        help = "Weights of the linear QoE score reported with each statistics summary"
+    ))]
+    pub qoe_weights: QoeWeightsConfig,
+
+    #[schema(strings(
+        display_name = "Latency histogram bounds",
+        help = "Upper bounds of the buckets of the total pipeline and network latency histograms. Latencies above the last bound are counted in an overflow bucket"
+    ))]
+    pub latency_histogram_bounds_ms: Vec<f32>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -1060,6 +1772,26 @@ pub enum DscpTos {
     ExpeditedForwarding,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct StatisticsOscConfig {
+    #[schema(strings(help = "Address of the OSC receiver, 127.0.0.1:9000 for VRChat"))]
+    pub destination: String,
+
+    #[schema(strings(
+        help = "The values are sent as floats in 0..1 to <prefix>/Bitrate, <prefix>/Latency and <prefix>/Loss"
+    ))]
+    pub address_prefix: String,
+
+    #[schema(strings(display_name = "Bitrate mapped to 1"))]
+    #[schema(suffix = "Mbps")]
+    pub max_bitrate_mbps: f32,
+
+    #[schema(strings(display_name = "Latency mapped to 1"))]
+    #[schema(suffix = "ms")]
+    pub max_latency_ms: f32,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 pub struct RawEventsConfig {
     #[schema(flag = "real-time")]
@@ -1083,6 +1815,19 @@ pub struct LoggingConfig {
     #[schema(flag = "real-time")]
     pub log_haptics: bool,
 
+    #[schema(strings(
+        help = "Write the expected, received and lost shards of every received frame to a CSV file in the log directory, for loss post-mortems"
+    ))]
+    pub record_shard_timeline: bool,
+
+    #[schema(strings(
+        help = "Write the decode, queue and render timeline of every frame completed by the client as JSON lines to a file in the client data directory"
+    ))]
+    pub record_client_frame_history: bool,
+
+    #[schema(strings(help = "Write the per-frame logs through a gzip encoder"))]
+    pub compress_frame_logs: bool,
+
     #[schema(flag = "real-time")]
     pub notification_level: LogSeverity,
 
@@ -1095,6 +1840,34 @@ pub struct LoggingConfig {
 
     #[schema(strings(help = "Notification tips teach you how to use ALVR"))]
     pub show_notification_tip: bool,
+
+    #[schema(strings(
+        display_name = "Prometheus metrics port",
+        help = "Expose the statistics in the Prometheus text format on http://<server>:<port>/metrics"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub prometheus_metrics_port: Switch<u16>,
+
+    #[schema(strings(
+        display_name = "gRPC statistics port",
+        help = "Stream the statistics over gRPC. Only available if the server is built with the grpc feature"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub grpc_statistics_port: Switch<u16>,
+
+    #[schema(strings(
+        display_name = "Statistics WebSocket port",
+        help = "Push every GraphStatistics as JSON to the WebSocket clients connected to ws://<server>:<port>"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub statistics_websocket_port: Switch<u16>,
+
+    #[schema(strings(
+        display_name = "Statistics OSC output",
+        help = "Send the bitrate, latency and packet loss as normalized OSC parameters every statistics report"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub statistics_osc: Switch<StatisticsOscConfig>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -1189,6 +1962,145 @@ pub fn session_settings_default() -> SettingsDefault {
         element: OPENVR_PROPS_DEFAULT.clone(),
         content: vec![],
     };
+    let adaptive_bitrate = AdaptiveBitrateConfigDefault {
+        gui_collapsed: true,
+        saturation_multiplier: 0.95,
+        max_bitrate_mbps: SwitchDefault {
+            enabled: false,
+            content: 100,
+        },
+        min_bitrate_mbps: SwitchDefault {
+            enabled: false,
+            content: 5,
+        },
+        max_network_latency_ms: SwitchDefault {
+            enabled: false,
+            content: 8,
+        },
+        network_latency_limiter_max_drop: SwitchDefault {
+            enabled: true,
+            content: 0.1,
+        },
+        encoder_latency_limiter: SwitchDefault {
+            enabled: true,
+            content: EncoderLatencyLimiterDefault {
+                max_saturation_multiplier: 0.9,
+                latency_percentile: SwitchDefault {
+                    enabled: false,
+                    content: 95.0,
+                },
+                reference_interval: EncoderLatencyReferenceIntervalDefault {
+                    variant: EncoderLatencyReferenceIntervalDefaultVariant::Automatic,
+                },
+            },
+        },
+        packet_loss_limiter: SwitchDefault {
+            enabled: false,
+            content: PacketLossLimiterDefault {
+                tcp_friendly_constant: 1.22,
+            },
+        },
+        decoder_latency_limiter: SwitchDefault {
+            enabled: true,
+            content: DecoderLatencyLimiterDefault {
+                gui_collapsed: true,
+                max_decoder_latency_ms: 30,
+                latency_overstep_frames: 90,
+                latency_overstep_multiplier: 0.99,
+                panic_decoder_latency_ms: SwitchDefault {
+                    enabled: false,
+                    content: 100,
+                },
+                recovery: SwitchDefault {
+                    enabled: true,
+                    content: DecoderLatencyRecoveryConfigDefault {
+                        gui_collapsed: true,
+                        interval_s: 5.0,
+                        multiplier: 1.05,
+                    },
+                },
+            },
+        },
+        content_limited_fill_ratio: SwitchDefault {
+            enabled: true,
+            content: 0.5,
+        },
+        cold_start: SwitchDefault {
+            enabled: false,
+            content: ColdStartConfigDefault {
+                duration_s: 5.0,
+                growth_factor: 1.25,
+            },
+        },
+    };
+
+    let simple_heuristic_bitrate = SimpleHeuristicConfigDefault {
+        gui_collapsed: false,
+        max_bitrate_mbps: SwitchDefault {
+            enabled: true,
+            content: 100.0,
+        },
+        min_bitrate_mbps: SwitchDefault {
+            enabled: true,
+            content: 30.0,
+        },
+
+        steps_mbps: SwitchDefault {
+            enabled: true,
+            content: 10.0,
+        },
+        threshold_random_uniform: SwitchDefault {
+            enabled: true,
+            content: 0.25,
+        },
+        threshold_annealing: SwitchDefault {
+            enabled: false,
+            content: ThresholdAnnealingConfigDefault {
+                gui_collapsed: true,
+                start_value: 0.5,
+                end_value: 0.1,
+                decay_time_constant_s: 60.0,
+            },
+        },
+        update_interval_heuristic: SwitchDefault {
+            enabled: true,
+            content: 1.0,
+        },
+        multiplier_rtt_threshold: SwitchDefault {
+            enabled: true,
+            content: 2.0,
+        },
+        fps_threshold_multiplier: SwitchDefault {
+            enabled: true,
+            content: 0.95,
+        },
+        capacity_estimation: PeakThroughputEstimatorDefault {
+            variant: PeakThroughputEstimatorDefaultVariant::Average,
+        },
+        capacity_from_goodput: false,
+        capacity_staleness: SwitchDefault {
+            enabled: true,
+            content: CapacityStalenessConfigDefault {
+                gui_collapsed: true,
+                staleness_window_s: 2.0,
+                decay_time_constant_s: 5.0,
+                floor_mbps: 10.0,
+            },
+        },
+        max_duplicate_ratio: SwitchDefault {
+            enabled: false,
+            content: 0.1,
+        },
+        capacity_warmup_samples: SwitchDefault {
+            enabled: true,
+            content: 10,
+        },
+        capacity_binding_backoff: SwitchDefault {
+            enabled: false,
+            content: 16,
+        },
+    };
+
     let socket_buffer = SocketBufferSizeDefault {
         Custom: 100000,
         variant: SocketBufferSizeDefaultVariant::Maximum,
@@ -1208,68 +2120,63 @@ pub fn session_settings_default() -> SettingsDefault {
                 gui_collapsed: false,
                 mode: BitrateModeDefault {
                     ConstantMbps: 30,
-                    Adaptive: BitrateModeAdaptiveDefault {
+                    Adaptive: adaptive_bitrate.clone(),
+                    SimpleHeuristic: simple_heuristic_bitrate.clone(),
+                    BufferBased: BitrateModeBufferBasedDefault {
                         gui_collapsed: true,
-                        saturation_multiplier: 0.95,
-                        max_bitrate_mbps: SwitchDefault {
-                            enabled: false,
-                            content: 100,
-                        },
-                        min_bitrate_mbps: SwitchDefault {
-                            enabled: false,
-                            content: 5,
-                        },
-                        max_network_latency_ms: SwitchDefault {
-                            enabled: false,
-                            content: 8,
-                        },
-                        encoder_latency_limiter: SwitchDefault {
-                            enabled: true,
-                            content: EncoderLatencyLimiterDefault {
-                                max_saturation_multiplier: 0.9,
-                            },
-                        },
-                        decoder_latency_limiter: SwitchDefault {
-                            enabled: true,
-                            content: DecoderLatencyLimiterDefault {
-                                gui_collapsed: true,
-                                max_decoder_latency_ms: 30,
-                                latency_overstep_frames: 90,
-                                latency_overstep_multiplier: 0.99,
-                            },
-                        },
+                        reservoir_ms: 2,
+                        cushion_ms: 10,
+                        max_bitrate_mbps: 100,
+                        min_bitrate_mbps: 10,
                     },
-                    SimpleHeuristic: BitrateModeSimpleHeuristicDefault {
-                        gui_collapsed: false,
+                    Bbr: BitrateModeBbrDefault {
+                        gui_collapsed: true,
+                        probe_interval_s: 5.0,
+                        probe_gain: 1.25,
+                        min_rtt_window_s: 10.0,
+                        max_rtt_inflation: 2.0,
                         max_bitrate_mbps: SwitchDefault {
                             enabled: true,
-                            content: 100.0,
+                            content: 100,
                         },
                         min_bitrate_mbps: SwitchDefault {
                             enabled: true,
-                            content: 30.0,
-                        },
-
-                        steps_mbps: SwitchDefault {
-                            enabled: true,
-                            content: 10.0,
-                        },
-                        threshold_random_uniform: SwitchDefault {
-                            enabled: true,
-                            content: 0.25,
-                        },
-                        update_interval_heuristic: SwitchDefault {
-                            enabled: true,
-                            content: 1.0,
+                            content: 10,
                         },
-                        multiplier_rtt_threshold: SwitchDefault {
-                            enabled: true,
-                            content: 2.0,
-                        },
-                        fps_threshold_multiplier: SwitchDefault {
-                            enabled: true,
-                            content: 0.95,
+                    },
+                    Mpc: BitrateModeMpcDefault {
+                        gui_collapsed: true,
+                        horizon: 3,
+                        candidate_steps: 2,
+                        candidate_step_mbps: 5.0,
+                        qoe_weights: QoeWeightsConfigDefault {
+                            gui_collapsed: true,
+                            bitrate: 1.0,
+                            rebuffering: 1.0,
+                            bitrate_switch: 0.5,
                         },
+                        max_bitrate_mbps: 100,
+                        min_bitrate_mbps: 10,
+                    },
+                    Tfrc: BitrateModeTfrcDefault {
+                        gui_collapsed: true,
+                        max_bitrate_mbps: 100,
+                        min_bitrate_mbps: 10,
+                    },
+                    Aimd: BitrateModeAimdDefault {
+                        gui_collapsed: true,
+                        increase_mbps: 2.0,
+                        decrease_factor: 0.7,
+                        rtt_inflation_threshold: 2.0,
+                        loss_threshold: 0.02,
+                        max_bitrate_mbps: 100,
+                        min_bitrate_mbps: 10,
+                    },
+                    Blend: BitrateModeBlendDefault {
+                        gui_collapsed: true,
+                        weight: 0.5,
+                        adaptive_config: adaptive_bitrate,
+                        heuristic_config: simple_heuristic_bitrate,
                     },
                     variant: BitrateModeDefaultVariant::SimpleHeuristic,
                 },
@@ -1277,10 +2184,60 @@ pub fn session_settings_default() -> SettingsDefault {
                     enabled: false,
                     content: BitrateAdaptiveFramerateConfigDefault {
                         framerate_reset_threshold_multiplier: 2.0,
+                        sustained_outlier_detection: SwitchDefault {
+                            enabled: true,
+                            content: SustainedOutlierConfigDefault {
+                                std_multiplier: 3.0,
+                                consecutive_frames: 10,
+                            },
+                        },
+                        max_framerate_step: SwitchDefault {
+                            enabled: false,
+                            content: 10.0,
+                        },
                     },
                 },
                 history_size: 256,
                 image_corruption_fix: false,
+                left_eye_bitrate_ratio: 0.5,
+                max_slew_rate_mbps_per_s: SwitchDefault {
+                    enabled: false,
+                    content: 50.0,
+                },
+                adaptive_update_interval: SwitchDefault {
+                    enabled: false,
+                    content: AdaptiveUpdateIntervalConfigDefault {
+                        gui_collapsed: true,
+                        min_interval_s: 0.25,
+                        max_interval_s: 2.0,
+                        reference_volatility: 0.5,
+                    },
+                },
+                idle_reset: SwitchDefault {
+                    enabled: true,
+                    content: IdleResetConfigDefault {
+                        gui_collapsed: true,
+                        timeout_s: 1.0,
+                        reset_frame_interval_average: true,
+                    },
+                },
+                qoe_floor: SwitchDefault {
+                    enabled: false,
+                    content: QoeFloorConfigDefault {
+                        gui_collapsed: true,
+                        floor_mbps: 10.0,
+                        grace_period_s: 2.0,
+                    },
+                },
+                fec_overhead: SwitchDefault {
+                    enabled: false,
+                    content: FecOverheadConfigDefault {
+                        gui_collapsed: true,
+                        loss_multiplier: 2.0,
+                        max_overhead: 0.25,
+                    },
+                },
+                decision_log: false,
             },
             preferred_codec: CodecTypeDefault {
                 variant: CodecTypeDefaultVariant::Hevc,
@@ -1640,6 +2597,85 @@ pub fn session_settings_default() -> SettingsDefault {
             on_disconnect_script: "".into(),
             packet_size: 1400,
             statistics_history_size: 256,
+            recent_graph_statistics_size: SwitchDefault {
+                enabled: false,
+                content: 256,
+            },
+            statistics_averaging_window_size: SwitchDefault {
+                enabled: false,
+                content: 256,
+            },
+            statistics_summary_interval_ms: 500,
+            stall_threshold_multiplier: 3.0,
+            frame_interarrival_outlier_multiplier: SwitchDefault {
+                enabled: false,
+                content: 2.0,
+            },
+            network_latency_estimator: NetworkLatencyEstimatorDefault {
+                variant: NetworkLatencyEstimatorDefaultVariant::StageSubtraction,
+            },
+            owd_kalman_filter: OwdKalmanFilterConfigDefault {
+                gui_collapsed: true,
+                process_noise_std_ms: 1.0,
+                measurement_noise_std_ms: 2.0,
+            },
+            client_owd_filter: ClientOwdFilterConfigDefault {
+                gui_collapsed: true,
+                process_noise: 1e-7,
+                noise_estimation_smoothing: 0.95,
+            },
+            latency_budget_alarms: SwitchDefault {
+                enabled: false,
+                content: LatencyBudgetsConfigDefault {
+                    gui_collapsed: true,
+                    game_time_ms: SwitchDefault {
+                        enabled: true,
+                        content: 20.0,
+                    },
+                    server_compositor_ms: SwitchDefault {
+                        enabled: true,
+                        content: 10.0,
+                    },
+                    encoder_ms: SwitchDefault {
+                        enabled: true,
+                        content: 15.0,
+                    },
+                    network_ms: SwitchDefault {
+                        enabled: true,
+                        content: 30.0,
+                    },
+                    decoder_ms: SwitchDefault {
+                        enabled: true,
+                        content: 15.0,
+                    },
+                    decoder_queue_ms: SwitchDefault {
+                        enabled: true,
+                        content: 10.0,
+                    },
+                    rendering_ms: SwitchDefault {
+                        enabled: true,
+                        content: 10.0,
+                    },
+                    vsync_queue_ms: SwitchDefault {
+                        enabled: true,
+                        content: 20.0,
+                    },
+                    throttle_interval_s: 5.0,
+                },
+            },
+            qoe_weights: QoeWeightsConfigDefault {
+                gui_collapsed: true,
+                bitrate: 1.0,
+                rebuffering: 1.0,
+                bitrate_switch: 1.0,
+            },
+            latency_histogram_bounds_ms: VectorDefault {
+                gui_collapsed: true,
+                element: 0.0,
+                content: vec![
+                    5.0, 10.0, 15.0, 20.0, 30.0, 40.0, 50.0, 75.0, 100.0, 150.0, 200.0,
+                ],
+            },
         },
         logging: LoggingConfigDefault {
             gui_collapsed: false,
@@ -1653,6 +2689,9 @@ pub fn session_settings_default() -> SettingsDefault {
             log_button_presses: false,
             log_tracking: false,
             log_haptics: false,
+            record_shard_timeline: false,
+            record_client_frame_history: false,
+            compress_frame_logs: false,
             notification_level: LogSeverityDefault {
                 variant: if cfg!(debug_assertions) {
                     LogSeverityDefaultVariant::Info
@@ -1668,6 +2707,28 @@ pub fn session_settings_default() -> SettingsDefault {
             },
             prefer_backtrace: false,
             show_notification_tip: true,
+            prometheus_metrics_port: SwitchDefault {
+                enabled: false,
+                content: 9464,
+            },
+            grpc_statistics_port: SwitchDefault {
+                enabled: false,
+                content: 9465,
+            },
+            statistics_websocket_port: SwitchDefault {
+                enabled: false,
+                content: 9466,
+            },
+            statistics_osc: SwitchDefault {
+                enabled: false,
+                content: StatisticsOscConfigDefault {
+                    gui_collapsed: true,
+                    destination: "127.0.0.1:9000".into(),
+                    address_prefix: "/avatar/parameters/ALVR".into(),
+                    max_bitrate_mbps: 200.0,
+                    max_latency_ms: 100.0,
+                },
+            },
         },
         steamvr_launcher: SteamvrLauncherDefault {
             gui_collapsed: false,
@@ -22,7 +22,7 @@ use alvr_common::{
     StatesWebrtc, ToCon,
 };
 use alvr_packets::VIDEO;
-use alvr_session::{DscpTos, SocketBufferSize, SocketProtocol};
+use alvr_session::{ClientOwdFilterConfig, DscpTos, SocketBufferSize, SocketProtocol};
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     cmp::Ordering,
@@ -35,6 +35,7 @@ use std::{
 };
 
 const Q_KALMAN: f32 = 10E-8;
+const NOISE_ESTIMATION_SMOOTHING: f32 = 0.95;
 const MAXSIZE_FRAMETRACKER: usize = 256; // wasteful, but consistent with HistoryFrame
 
 pub struct KalmanFilter {
@@ -57,6 +58,9 @@ pub struct KalmanFilter {
 
     state: StatesWebrtc,
     last_not_overuse_instant: Instant,
+
+    process_noise: f32,
+    noise_estimation_smoothing: f32,
 }
 
 impl Default for KalmanFilter {
@@ -81,11 +85,44 @@ impl Default for KalmanFilter {
 
             state: StatesWebrtc::NORMAL,
             last_not_overuse_instant: Instant::now(),
+
+            process_noise: Q_KALMAN,
+            noise_estimation_smoothing: NOISE_ESTIMATION_SMOOTHING,
         }
     }
 }
 
 impl KalmanFilter {
+    // ow_delay must be set before
+    fn update(&mut self, frame_interarrival: f32) {
+        let q = self.process_noise;
+        let smoothing = self.noise_estimation_smoothing;
+
+        self.k_gain = (self.p_prev + q) / (self.p_prev + q + self.noise_estimation);
+
+        self.m_current = (1.0 - self.k_gain) * self.m_prev + self.k_gain * self.ow_delay;
+
+        self.residual_z = self.ow_delay - self.m_prev;
+
+        self.noise_estimation =
+            (smoothing * self.noise_prev) + self.residual_z.powf(2.0) * (1.0 - smoothing);
+
+        self.p_current = (1.0 - self.k_gain) * (self.p_prev + q);
+
+        self.p_prev = self.p_current;
+        self.m_prev = self.m_current;
+        self.noise_prev = self.noise_estimation;
+
+        self.measured_delay += self.m_current;
+
+        self.adaptive_threshold = self.adaptive_threshold_prev
+            + frame_interarrival
+                * self.k_threshold()
+                * (f32::abs(self.m_current) - self.adaptive_threshold_prev);
+
+        self.adaptive_threshold_prev = self.adaptive_threshold;
+    }
+
     fn k_threshold(&self) -> f32 {
         if f32::abs(self.m_current) < self.adaptive_threshold_prev {
             self.k_threshold_d
@@ -324,6 +361,7 @@ pub struct ReceiverData<H> {
 
     rx_shard_counter: u32,
     duplicated_shard_counter: u32,
+    reordered_shard_counter: u32,
 
     highest_rx_frame_index: i32,
     highest_rx_shard_index: i32,
@@ -373,6 +411,9 @@ impl<H> ReceiverData<H> {
     pub fn get_duplicated_shard_counter(&self) -> u32 {
         self.duplicated_shard_counter
     }
+    pub fn get_reordered_shard_counter(&self) -> u32 {
+        self.reordered_shard_counter
+    }
     pub fn get_highest_rx_frame_index(&self) -> i32 {
         self.highest_rx_frame_index
     }
@@ -428,6 +469,7 @@ struct ReconstructedPacket {
 
     rx_shard_counter: u32,
     duplicated_shard_counter: u32,
+    reordered_shard_counter: u32,
 
     highest_rx_frame_index: i32,
     highest_rx_shard_index: i32,
@@ -448,6 +490,7 @@ pub struct StreamReceiver<H> {
 
     rx_shard_counter: u32,
     duplicated_shard_counter: u32,
+    reordered_shard_counter: u32,
 }
 
 fn wrapping_cmp(lhs: u32, rhs: u32) -> Ordering {
@@ -462,6 +505,29 @@ fn wrapping_cmp(lhs: u32, rhs: u32) -> Ordering {
     }
 }
 
+// Updates the highest shard received for the latest video frame. The shard index is reset when the
+// frame index advances. Returns true if the shard was received after a shard with a higher index of
+// the same frame
+fn update_highest_rx_shard(
+    highest_rx_frame_index: &mut i32,
+    highest_rx_shard_index: &mut i32,
+    frame_index: i32,
+    shard_index: i32,
+) -> bool {
+    if *highest_rx_frame_index == frame_index {
+        if *highest_rx_shard_index < shard_index {
+            *highest_rx_shard_index = shard_index;
+        } else {
+            return *highest_rx_shard_index > shard_index;
+        }
+    } else if *highest_rx_frame_index < frame_index {
+        *highest_rx_frame_index = frame_index;
+        *highest_rx_shard_index = shard_index;
+    }
+
+    false
+}
+
 /// Get next packet reconstructing from shards.
 /// Returns true if a packet has been recontructed and copied into the buffer.
 impl<H: DeserializeOwned + Serialize> StreamReceiver<H> {
@@ -479,6 +545,8 @@ impl<H: DeserializeOwned + Serialize> StreamReceiver<H> {
 
         self.duplicated_shard_counter += packet.duplicated_shard_counter;
 
+        self.reordered_shard_counter += packet.reordered_shard_counter;
+
         let mut had_packet_loss = false;
         let mut frames_skipped: u32 = 0;
 
@@ -503,11 +571,13 @@ impl<H: DeserializeOwned + Serialize> StreamReceiver<H> {
         let rx_bytes_val = self.rx_bytes;
         let rx_counter = self.rx_shard_counter;
         let duplicated_counter = self.duplicated_shard_counter;
+        let reordered_counter = self.reordered_shard_counter;
 
         self.frame_interarrival = 0.0;
         self.rx_bytes = 0;
         self.rx_shard_counter = 0;
         self.duplicated_shard_counter = 0;
+        self.reordered_shard_counter = 0;
 
         self.last_packet_index = Some(packet.index);
 
@@ -535,6 +605,7 @@ impl<H: DeserializeOwned + Serialize> StreamReceiver<H> {
 
             rx_shard_counter: rx_counter,
             duplicated_shard_counter: duplicated_counter,
+            reordered_shard_counter: reordered_counter,
 
             highest_rx_frame_index: packet.highest_rx_frame_index,
             highest_rx_shard_index: packet.highest_rx_shard_index,
@@ -627,6 +698,7 @@ impl StreamSocketBuilder {
 
             rx_shard_counter: 0,
             duplicated_shard_counter: 0,
+            reordered_shard_counter: 0,
 
             highest_rx_frame_index: -1,
             highest_rx_shard_index: -1,
@@ -692,6 +764,7 @@ impl StreamSocketBuilder {
 
             rx_shard_counter: 0,
             duplicated_shard_counter: 0,
+            reordered_shard_counter: 0,
 
             highest_rx_frame_index: -1,
             highest_rx_shard_index: -1,
@@ -749,6 +822,7 @@ pub struct StreamSocket {
 
     rx_shard_counter: u32,
     duplicated_shard_counter: u32,
+    reordered_shard_counter: u32,
 
     highest_rx_shard_index: i32,
     highest_rx_frame_index: i32,
@@ -762,6 +836,12 @@ struct ShardMapStats {
     rx_bytes_app: u32,
 }
 impl StreamSocket {
+    // Parameters of the filter producing filtered_ow_delay for the received video frames
+    pub fn set_owd_filter_config(&mut self, config: &ClientOwdFilterConfig) {
+        self.kalman.process_noise = config.process_noise;
+        self.kalman.noise_estimation_smoothing = config.noise_estimation_smoothing;
+    }
+
     pub fn request_stream<T>(&self, stream_id: u16) -> StreamSender<T> {
         StreamSender {
             inner: Arc::clone(&self.send_socket),
@@ -818,6 +898,7 @@ impl StreamSocket {
 
             rx_shard_counter: 0,
             duplicated_shard_counter: 0,
+            reordered_shard_counter: 0,
         }
     }
 
@@ -844,14 +925,12 @@ impl StreamSocket {
             if stream_id == VIDEO {
                 let rx_instant = Instant::now();
 
-                if self.highest_rx_frame_index == packet_index as i32 {
-                    if self.highest_rx_shard_index < shard_index as i32 {
-                        self.highest_rx_shard_index = shard_index as i32;
-                    }
-                } else if self.highest_rx_frame_index < packet_index as i32 {
-                    self.highest_rx_frame_index = packet_index as i32;
-                    self.highest_rx_shard_index = shard_index as i32;
-                }
+                let is_reordered = update_highest_rx_shard(
+                    &mut self.highest_rx_frame_index,
+                    &mut self.highest_rx_shard_index,
+                    packet_index as i32,
+                    shard_index as i32,
+                );
 
                 let header_bytes_transport: u32 = match self.transport_protocol {
                     SocketProtocol::Udp => 42,
@@ -871,6 +950,10 @@ impl StreamSocket {
                 } else {
                     shards_map.insert(shard_index, packet);
                     self.rx_shard_counter += 1;
+
+                    if is_reordered {
+                        self.reordered_shard_counter += 1;
+                    }
                 }
 
                 self.rx_bytes += shard_length as u32 + header_bytes_transport;
@@ -1041,33 +1124,7 @@ impl StreamSocket {
                         }
                         self.prev_frame_tx_r_instant = Some(first_shard_stats.tx_r_instant);
 
-                        self.kalman.k_gain = (self.kalman.p_prev + Q_KALMAN)
-                            / (self.kalman.p_prev + Q_KALMAN + self.kalman.noise_estimation);
-
-                        self.kalman.m_current = (1.0 - self.kalman.k_gain) * self.kalman.m_prev
-                            + self.kalman.k_gain * self.kalman.ow_delay;
-
-                        self.kalman.residual_z = self.kalman.ow_delay - self.kalman.m_prev;
-
-                        self.kalman.noise_estimation = (0.95 * self.kalman.noise_prev)
-                            + self.kalman.residual_z.powf(2.0) * 0.05;
-
-                        self.kalman.p_current =
-                            (1.0 - self.kalman.k_gain) * (self.kalman.p_prev + Q_KALMAN);
-
-                        self.kalman.p_prev = self.kalman.p_current;
-                        self.kalman.m_prev = self.kalman.m_current;
-                        self.kalman.noise_prev = self.kalman.noise_estimation;
-
-                        self.kalman.measured_delay += self.kalman.m_current;
-
-                        self.kalman.adaptive_threshold = self.kalman.adaptive_threshold_prev
-                            + frame_interarrival
-                                * self.kalman.k_threshold()
-                                * (f32::abs(self.kalman.m_current)
-                                    - self.kalman.adaptive_threshold_prev);
-
-                        self.kalman.adaptive_threshold_prev = self.kalman.adaptive_threshold;
+                        self.kalman.update(frame_interarrival);
 
                         // self.kalman.state_gcc(); // change state of internal state machine. TODO: Add Delay-based controller logic
                     }
@@ -1101,6 +1158,7 @@ impl StreamSocket {
 
                     rx_shard_counter: self.rx_shard_counter,
                     duplicated_shard_counter: self.duplicated_shard_counter,
+                    reordered_shard_counter: self.reordered_shard_counter,
 
                     highest_rx_frame_index: self.highest_rx_frame_index,
                     highest_rx_shard_index: self.highest_rx_shard_index,
@@ -1114,6 +1172,7 @@ impl StreamSocket {
                 self.rx_bytes = 0;
                 self.rx_shard_counter = 0;
                 self.duplicated_shard_counter = 0;
+                self.reordered_shard_counter = 0;
 
                 // Keep only shards data from the latest packets (using wrapping logic)
                 let mut idxs_to_remove = Vec::new();
@@ -1147,3 +1206,65 @@ impl StreamSocket {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reordered_shards() {
+        let mut highest_frame = -1;
+        let mut highest_shard = -1;
+
+        let reordered_count = [
+            (0, 0),
+            (0, 2),
+            (0, 1),
+            (0, 3),
+            (1, 1),
+            (1, 0),
+            (1, 2),
+            (0, 4),
+        ]
+        .into_iter()
+        .filter(|&(frame, shard)| {
+            update_highest_rx_shard(&mut highest_frame, &mut highest_shard, frame, shard)
+        })
+        .count();
+
+        // Shard 1 of frame 0 and shard 0 of frame 1. Shards of older frames are not counted
+        assert_eq!(reordered_count, 2);
+        assert_eq!((highest_frame, highest_shard), (1, 2));
+    }
+
+    #[test]
+    fn test_owd_filter_smoothing() {
+        let filtered_variance = |process_noise, noise_estimation_smoothing| {
+            let mut kalman = KalmanFilter {
+                process_noise,
+                noise_estimation_smoothing,
+                ..Default::default()
+            };
+
+            let mut filtered = vec![];
+            for i in 0..2000 {
+                // 0.5 ms gradient with up to 2 ms of noise
+                let noise = ((i * 7919) % 13) as f32 / 12.0 - 0.5;
+                kalman.ow_delay = 0.0005 + noise * 0.004;
+                kalman.update(0.011);
+
+                // Skip the convergence
+                if i >= 200 {
+                    filtered.push(kalman.m_current);
+                }
+            }
+
+            let mean = filtered.iter().sum::<f32>() / filtered.len() as f32;
+            filtered.iter().map(|m| (m - mean).powi(2)).sum::<f32>() / filtered.len() as f32
+        };
+
+        let default_variance = filtered_variance(Q_KALMAN, NOISE_ESTIMATION_SMOOTHING);
+        assert!(filtered_variance(Q_KALMAN / 100.0, NOISE_ESTIMATION_SMOOTHING) < default_variance);
+        assert!(filtered_variance(Q_KALMAN, 0.5) > default_variance);
+    }
+}